@@ -0,0 +1,285 @@
+// Amino acid scores rate how well the essential amino acids of a protein meet
+// human needs. Each one is compared with the FAO/WHO/UNU (2007) reference
+// pattern for adults, in mg per g of protein, and the one falling furthest
+// short limits the score. Like PDCAAS the score is capped at 100%, but it is
+// not corrected for digestibility, which the ingredients carry no data on.
+//
+// Imported ingredients often only come with a protein total, which
+// `NutritionalInfo::complete_totals` keeps as unspecified protein. Proteins
+// without any essential amino acids therefore count as having no amino acid
+// data instead of scoring zero. Recipes and days only score the protein of the
+// ingredients with amino acid data, see `ScoredProteins`.
+
+use crate::ingredients::{LogEntry, LogItem, NutritionalInfo, Proteins, Recipe};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AminoAcid {
+    Histidine,
+    Isoleucine,
+    Leucine,
+    Lysine,
+    // Methionine and cysteine, which the body makes from methionine.
+    SulfurAminoAcids,
+    // Phenylalanine and tyrosine, which the body makes from phenylalanine.
+    AromaticAminoAcids,
+    Threonine,
+    Tryptophan,
+    Valine,
+}
+
+impl AminoAcid {
+    pub const ALL: [AminoAcid; 9] = [
+        Self::Histidine,
+        Self::Isoleucine,
+        Self::Leucine,
+        Self::Lysine,
+        Self::SulfurAminoAcids,
+        Self::AromaticAminoAcids,
+        Self::Threonine,
+        Self::Tryptophan,
+        Self::Valine,
+    ];
+
+    // The need in mg per g of protein.
+    pub fn reference(&self) -> f32 {
+        match self {
+            Self::Histidine => 15.0,
+            Self::Isoleucine => 30.0,
+            Self::Leucine => 59.0,
+            Self::Lysine => 45.0,
+            Self::SulfurAminoAcids => 22.0,
+            Self::AromaticAminoAcids => 38.0,
+            Self::Threonine => 23.0,
+            Self::Tryptophan => 6.0,
+            Self::Valine => 39.0,
+        }
+    }
+
+    // The amount in g.
+    pub fn amount(&self, proteins: &Proteins) -> f32 {
+        let essential = &proteins.essential_amino_acids;
+        let non_essential = &proteins.non_essential_amino_acids;
+        match self {
+            Self::Histidine => essential.histidine,
+            Self::Isoleucine => essential.isoleucine,
+            Self::Leucine => essential.leucine,
+            Self::Lysine => essential.lysine,
+            Self::SulfurAminoAcids => essential.methionine + non_essential.cysteine,
+            Self::AromaticAminoAcids => essential.phenylalanine + non_essential.tyrosine,
+            Self::Threonine => essential.threonine,
+            Self::Tryptophan => essential.tryptophan,
+            Self::Valine => essential.valine,
+        }
+    }
+}
+
+impl std::fmt::Display for AminoAcid {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Histidine => write!(f, "Histidine"),
+            Self::Isoleucine => write!(f, "Isoleucine"),
+            Self::Leucine => write!(f, "Leucine"),
+            Self::Lysine => write!(f, "Lysine"),
+            Self::SulfurAminoAcids => write!(f, "Methionine + cysteine"),
+            Self::AromaticAminoAcids => write!(f, "Phenylalanine + tyrosine"),
+            Self::Threonine => write!(f, "Threonine"),
+            Self::Tryptophan => write!(f, "Tryptophan"),
+            Self::Valine => write!(f, "Valine"),
+        }
+    }
+}
+
+pub struct AminoAcidScore {
+    // Each amino acid with its share of the reference, in the order of
+    // `AminoAcid::ALL`.
+    pub ratios: Vec<(AminoAcid, f32)>,
+}
+
+impl AminoAcidScore {
+    // `None` without protein or amino acid data.
+    pub fn new(proteins: &Proteins) -> Option<Self> {
+        let protein = proteins.total_proteins();
+        if protein <= 0.0 || proteins.essential_amino_acids.total() <= 0.0 {
+            return None;
+        }
+        Some(AminoAcidScore {
+            ratios: AminoAcid::ALL
+                .into_iter()
+                .map(|amino_acid| {
+                    let milligrams_per_gram = amino_acid.amount(proteins) * 1000.0 / protein;
+                    (amino_acid, milligrams_per_gram / amino_acid.reference())
+                })
+                .collect(),
+        })
+    }
+
+    // The amino acid furthest below its reference and its share of it.
+    pub fn limiting(&self) -> (AminoAcid, f32) {
+        self.ratios
+            .iter()
+            .copied()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .expect("all amino acids are rated")
+    }
+
+    pub fn score(&self) -> f32 {
+        self.limiting().1.min(1.0)
+    }
+}
+
+// The proteins of recipes or log entries, split by whether their ingredients
+// have amino acid data. Protein without it would lower the score as if it
+// lacked every essential amino acid.
+#[derive(Clone, Default)]
+pub struct ScoredProteins {
+    // Only the proteins of ingredients with amino acid data.
+    pub profiled: Proteins,
+    // The protein of all ingredients in g.
+    pub total: f32,
+}
+
+impl ScoredProteins {
+    pub fn new(proteins: &Proteins) -> Self {
+        let mut scored = ScoredProteins::default();
+        scored.add(proteins, 1.0);
+        scored
+    }
+
+    pub fn add(&mut self, proteins: &Proteins, factor: f32) {
+        self.total += proteins.total_proteins() * factor;
+        if proteins.essential_amino_acids.total() > 0.0 {
+            self.profiled += &(proteins * factor);
+        }
+    }
+
+    fn add_scaled(&mut self, scaled: Option<(&NutritionalInfo, f32)>) {
+        if let Some((nutritional_info, factor)) = scaled {
+            self.add(&nutritional_info.macronutrients.proteins, factor);
+        }
+    }
+
+    // `servings` servings of `recipe`.
+    pub fn add_recipe(&mut self, recipe: &Recipe, servings: f32) {
+        if recipe.servings <= 0.0 {
+            return;
+        }
+        for item in &recipe.items {
+            let scaled = item.ingredient.scale(item.amount, item.unit);
+            self.add_scaled(
+                scaled.map(|(info, factor)| (info, factor * servings / recipe.servings)),
+            );
+        }
+    }
+
+    pub fn from_log_entries(log_entries: &[LogEntry]) -> Self {
+        let mut scored = ScoredProteins::default();
+        for log_entry in log_entries {
+            match &log_entry.item {
+                LogItem::Ingredient(ingredient) => scored.add_scaled(ingredient.scale_serving(
+                    log_entry.amount,
+                    log_entry.unit,
+                    log_entry.serving,
+                )),
+                LogItem::Recipe(recipe) => scored.add_recipe(recipe, log_entry.amount),
+            }
+        }
+        scored
+    }
+
+    // The share of the protein that has amino acid data.
+    pub fn profiled_share(&self) -> f32 {
+        if self.total > 0.0 {
+            (self.profiled.total_proteins() / self.total).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingredients::{Ingredient, RecipeItem, Unit};
+    use std::rc::Rc;
+
+    // Amounts per 100 g of cooked white rice, 2.69 g protein in total.
+    fn rice_proteins() -> Proteins {
+        let mut proteins = Proteins::default();
+        let essential = &mut proteins.essential_amino_acids;
+        essential.histidine = 0.063;
+        essential.isoleucine = 0.116;
+        essential.leucine = 0.222;
+        essential.lysine = 0.097;
+        essential.methionine = 0.063;
+        essential.phenylalanine = 0.144;
+        essential.threonine = 0.096;
+        essential.tryptophan = 0.031;
+        essential.valine = 0.164;
+        proteins.non_essential_amino_acids.cysteine = 0.055;
+        proteins.non_essential_amino_acids.tyrosine = 0.090;
+        proteins.unspecified = 2.69 - proteins.total_proteins();
+        proteins
+    }
+
+    #[test]
+    fn lowest_amino_acid_limits_the_score() {
+        let proteins = rice_proteins();
+        let score = AminoAcidScore::new(&proteins).unwrap();
+        let (limiting, ratio) = score.limiting();
+        assert_eq!(limiting, AminoAcid::Lysine);
+        assert!((ratio - 97.0 / 2.69 / 45.0).abs() < 1e-3);
+        assert_eq!(score.score(), ratio);
+
+        // Plenty of every amino acid still scores 100%.
+        let mut doubled = proteins.clone();
+        doubled.essential_amino_acids = &proteins.essential_amino_acids * 2.0;
+        doubled.unspecified = 0.0;
+        assert_eq!(AminoAcidScore::new(&doubled).unwrap().score(), 1.0);
+    }
+
+    #[test]
+    fn proteins_without_essential_amino_acids_have_no_score() {
+        let mut proteins = Proteins::default();
+        assert!(AminoAcidScore::new(&proteins).is_none());
+        proteins.unspecified = 12.0;
+        assert!(AminoAcidScore::new(&proteins).is_none());
+    }
+
+    #[test]
+    fn protein_without_amino_acid_data_is_left_out_of_recipe_scores() {
+        let ingredient = |proteins: Proteins| {
+            let mut ingredient = Ingredient::test("", 0.0);
+            ingredient.nutritional_info[0].macronutrients.proteins = proteins;
+            Rc::new(ingredient)
+        };
+        // Imported beans with only a protein total.
+        let beans = Proteins {
+            unspecified: 8.0,
+            ..Default::default()
+        };
+        let item = |proteins: Proteins, amount| RecipeItem {
+            ingredient: ingredient(proteins),
+            amount,
+            unit: Unit::Grams,
+        };
+        let recipe = Recipe {
+            id: 0,
+            name: String::new(),
+            servings: 2.0,
+            items: vec![item(rice_proteins(), 200.0), item(beans, 100.0)],
+        };
+
+        let mut scored = ScoredProteins::default();
+        scored.add_recipe(&recipe, 1.0);
+        assert!((scored.total - (2.69 + 4.0)).abs() < 1e-4);
+        assert!((scored.profiled_share() - 2.69 / 6.69).abs() < 1e-4);
+        let rice_score = AminoAcidScore::new(&rice_proteins()).unwrap().score();
+        let score = AminoAcidScore::new(&scored.profiled).unwrap().score();
+        assert!((score - rice_score).abs() < 1e-4);
+
+        // All protein counts towards the total of the recipe.
+        let total_proteins = recipe.nutrients_per_serving().macronutrients.proteins;
+        assert!((total_proteins.total_proteins() - scored.total).abs() < 1e-4);
+        assert!(AminoAcidScore::new(&total_proteins).unwrap().score() < rice_score);
+    }
+}
@@ -0,0 +1,584 @@
+// Backups of the whole database as a single JSON document.
+//
+// The document names every value instead of mirroring the tables, so it stays
+// readable, diffs cleanly and can be restored into later schema versions.
+// Units, nutrients, goal nutrients, weekdays and the intake profile are written
+// by name. IDs only link the entries of the document to each other, restoring
+// assigns new ones.
+//
+//   {
+//     "format": "sophross-backup",
+//     "version": 1,
+//     "categories": [{ "id", "name", "icon_name", "icon_color" }],
+//     "ingredients": [{
+//       "id", "name", "brand", "barcode", "categories": [category id], "density",
+//       "piece_weight", "archived",
+//       "nutritional_info": [{
+//         "id", "name", "default_amount", "default_unit", "nutrients": { name: amount }
+//       }]
+//     }],
+//     "meals": [{ "id", "name" }],
+//     "recipes": [{ "id", "name", "servings", "items": [{ "ingredient", "amount", "unit" }] }],
+//     "daily_logs": [{
+//       "date", "ingredient" or "recipe", "amount", "unit", "serving", "meal", "time"
+//     }],
+//     "goals": [{ "nutrient", "weekday", "target" }],
+//     "intake_profile": { "sex", "age_group" }
+//   }
+
+use crate::barcode;
+use crate::goals::{Goal, GoalNutrient};
+use crate::ingredients::*;
+use crate::reference_intakes::{AgeGroup, IntakeProfile, Sex};
+use chrono::{NaiveDate, NaiveTime, Weekday};
+use eframe::epaint::Color32;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::rc::Rc;
+
+const FORMAT_NAME: &str = "sophross-backup";
+pub const BACKUP_VERSION: u64 = 1;
+
+// Everything a backup holds. IDs are the ones of the database the backup was
+// taken from, or of the document when it was read from a file.
+#[derive(Default)]
+pub struct Backup {
+    pub categories: Vec<Category>,
+    pub ingredients: Vec<Rc<Ingredient>>,
+    pub meals: Vec<Meal>,
+    pub recipes: Vec<Rc<Recipe>>,
+    pub log_entries: Vec<(NaiveDate, LogEntry)>,
+    pub goals: Vec<Goal>,
+    // `None` for backups taken before the intake profile was added.
+    pub intake_profile: Option<IntakeProfile>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestoreMode {
+    // Adds what is missing and keeps the existing data.
+    Merge,
+    // Removes all existing data first.
+    Replace,
+}
+
+#[derive(Debug)]
+pub enum BackupError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Invalid(String),
+    NewerVersion { found: u64, supported: u64 },
+}
+
+impl std::fmt::Display for BackupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BackupError::Io(error) => write!(f, "Unable to access the backup: {}", error),
+            BackupError::Json(error) => write!(f, "The backup is not valid JSON: {}", error),
+            BackupError::Invalid(message) => write!(f, "Invalid backup: {}", message),
+            BackupError::NewerVersion { found, supported } => write!(
+                f,
+                "The backup has version {} but this build only supports up to version {}.",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<std::io::Error> for BackupError {
+    fn from(error: std::io::Error) -> Self {
+        BackupError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for BackupError {
+    fn from(error: serde_json::Error) -> Self {
+        BackupError::Json(error)
+    }
+}
+
+pub fn write(backup: &Backup, mut writer: impl Write) -> Result<(), BackupError> {
+    let categories: Vec<Value> = backup
+        .categories
+        .iter()
+        .map(|category| {
+            json!({
+                "id": category.id,
+                "name": category.name,
+                "icon_name": category.icon_name,
+                "icon_color": category.icon_color.to_hex(),
+            })
+        })
+        .collect();
+
+    let ingredients: Vec<Value> = backup
+        .ingredients
+        .iter()
+        .map(|ingredient| {
+            let nutritional_info: Vec<Value> = ingredient
+                .nutritional_info
+                .iter()
+                .map(|nutritional_info| {
+                    let nutrients: Map<String, Value> = NUTRIENT_FIELDS
+                        .iter()
+                        .map(|(name, _)| {
+                            (
+                                name.to_string(),
+                                json_number(nutritional_info.nutrient(name).unwrap_or(0.0)),
+                            )
+                        })
+                        .collect();
+                    json!({
+                        "id": nutritional_info.id,
+                        "name": nutritional_info.name,
+                        "default_amount": json_number(nutritional_info.default_amount),
+                        "default_unit": nutritional_info.default_unit.to_string(),
+                        "nutrients": nutrients,
+                    })
+                })
+                .collect();
+            let categories: Vec<u32> = ingredient
+                .categories
+                .iter()
+                .map(|category| category.id)
+                .collect();
+            json!({
+                "id": ingredient.id,
+                "name": ingredient.name,
+                "brand": ingredient.brand,
+                "barcode": ingredient.barcode,
+                "categories": categories,
+                "density": ingredient.conversions.density.map(json_number),
+                "piece_weight": ingredient.conversions.piece_weight.map(json_number),
+                "archived": ingredient.archived,
+                "nutritional_info": nutritional_info,
+            })
+        })
+        .collect();
+
+    let meals: Vec<Value> = backup
+        .meals
+        .iter()
+        .map(|meal| json!({ "id": meal.id, "name": meal.name }))
+        .collect();
+
+    let recipes: Vec<Value> = backup
+        .recipes
+        .iter()
+        .map(|recipe| {
+            let items: Vec<Value> = recipe
+                .items
+                .iter()
+                .map(|item| {
+                    json!({
+                        "ingredient": item.ingredient.id,
+                        "amount": json_number(item.amount),
+                        "unit": item.unit.to_string(),
+                    })
+                })
+                .collect();
+            json!({
+                "id": recipe.id,
+                "name": recipe.name,
+                "servings": json_number(recipe.servings),
+                "items": items,
+            })
+        })
+        .collect();
+
+    let daily_logs: Vec<Value> = backup
+        .log_entries
+        .iter()
+        .map(|(date, log_entry)| {
+            let mut entry = json!({
+                "date": date.to_string(),
+                "amount": json_number(log_entry.amount),
+                "unit": log_entry.unit.to_string(),
+                "serving": log_entry.serving,
+                "meal": log_entry.meal.as_ref().map(|meal| meal.id),
+                "time": log_entry.time.map(|time| time.format("%H:%M:%S").to_string()),
+            });
+            match &log_entry.item {
+                LogItem::Ingredient(ingredient) => entry["ingredient"] = json!(ingredient.id),
+                LogItem::Recipe(recipe) => entry["recipe"] = json!(recipe.id),
+            }
+            entry
+        })
+        .collect();
+
+    let goals: Vec<Value> = backup
+        .goals
+        .iter()
+        .map(|goal| {
+            json!({
+                "nutrient": goal.nutrient.to_string(),
+                "weekday": goal.weekday.map(|weekday| weekday.to_string()),
+                "target": json_number(goal.target),
+            })
+        })
+        .collect();
+
+    let intake_profile = backup.intake_profile.map(|profile| {
+        json!({
+            "sex": profile.sex.to_string(),
+            "age_group": profile.age.to_string(),
+        })
+    });
+
+    let document = json!({
+        "format": FORMAT_NAME,
+        "version": BACKUP_VERSION,
+        "categories": categories,
+        "ingredients": ingredients,
+        "meals": meals,
+        "recipes": recipes,
+        "daily_logs": daily_logs,
+        "goals": goals,
+        "intake_profile": intake_profile,
+    });
+    serde_json::to_writer_pretty(&mut writer, &document)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+pub fn read(reader: impl Read) -> Result<Backup, BackupError> {
+    let document: Value = serde_json::from_reader(reader)?;
+    if document["format"] != FORMAT_NAME {
+        return Err(BackupError::Invalid("not a backup file".to_owned()));
+    }
+    let version = document["version"]
+        .as_u64()
+        .ok_or_else(|| BackupError::Invalid("the version is missing".to_owned()))?;
+    if version > BACKUP_VERSION {
+        return Err(BackupError::NewerVersion {
+            found: version,
+            supported: BACKUP_VERSION,
+        });
+    }
+
+    let mut backup = Backup::default();
+
+    let mut categories: HashMap<u32, Category> = HashMap::new();
+    for value in list(&document, "categories")? {
+        let color = text(value, "icon_color", "category")?;
+        let category = Category {
+            id: id(value, "id", "category")?,
+            name: text(value, "name", "category")?,
+            icon_name: text(value, "icon_name", "category")?,
+            icon_color: Color32::from_hex(&color).map_err(|_| {
+                BackupError::Invalid(format!("\"{}\" is not a valid hex color", color))
+            })?,
+        };
+        categories.insert(category.id, category.clone());
+        backup.categories.push(category);
+    }
+
+    let mut ingredients: HashMap<u32, Rc<Ingredient>> = HashMap::new();
+    for value in list(&document, "ingredients")? {
+        let mut nutritional_info: Vec<NutritionalInfo> = Vec::new();
+        for info_value in list(value, "nutritional_info")? {
+            // Backups of version 1 written before servings had names lack both.
+            let mut info = NutritionalInfo {
+                id: info_value["id"]
+                    .as_u64()
+                    .and_then(|id| u32::try_from(id).ok())
+                    .unwrap_or(0),
+                name: info_value["name"].as_str().unwrap_or_default().to_owned(),
+                default_amount: number(info_value, "default_amount", "nutritional info")?,
+                default_unit: unit(info_value, "default_unit", "nutritional info")?,
+                ..Default::default()
+            };
+            // Nutrients this build does not know are skipped, missing ones stay 0.
+            if let Some(nutrients) = info_value["nutrients"].as_object() {
+                for (name, amount) in nutrients {
+                    if let (Some(target), Some(amount)) = (info.nutrient_mut(name), amount.as_f64())
+                    {
+                        *target = amount as f32;
+                    }
+                }
+            }
+            nutritional_info.push(info);
+        }
+        // Ingredients are only listed with at least one nutritional info.
+        if nutritional_info.is_empty() {
+            return Err(BackupError::Invalid(format!(
+                "ingredient \"{}\" has no nutritional info",
+                text(value, "name", "ingredient")?
+            )));
+        }
+
+        let mut ingredient_categories: Vec<Category> = Vec::new();
+        for category_id in list(value, "categories")? {
+            match category_id
+                .as_u64()
+                .and_then(|category_id| categories.get(&(category_id as u32)))
+            {
+                Some(category) => ingredient_categories.push(category.clone()),
+                None => {
+                    return Err(BackupError::Invalid(format!(
+                        "ingredient refers to unknown category {}",
+                        category_id
+                    )))
+                }
+            }
+        }
+
+        let ingredient = Rc::new(Ingredient {
+            id: id(value, "id", "ingredient")?,
+            name: text(value, "name", "ingredient")?,
+            brand: value["brand"].as_str().unwrap_or_default().to_owned(),
+            barcode: value["barcode"]
+                .as_str()
+                .and_then(|code| barcode::normalize(code).ok()),
+            categories: ingredient_categories,
+            nutritional_info,
+            conversions: Conversions {
+                density: value["density"].as_f64().map(|density| density as f32),
+                piece_weight: value["piece_weight"]
+                    .as_f64()
+                    .map(|piece_weight| piece_weight as f32),
+            },
+            archived: value["archived"].as_bool().unwrap_or(false),
+        });
+        ingredients.insert(ingredient.id, ingredient.clone());
+        backup.ingredients.push(ingredient);
+    }
+
+    let mut meals: HashMap<u32, Meal> = HashMap::new();
+    for value in list(&document, "meals")? {
+        let meal = Meal {
+            id: id(value, "id", "meal")?,
+            name: text(value, "name", "meal")?,
+        };
+        meals.insert(meal.id, meal.clone());
+        backup.meals.push(meal);
+    }
+
+    let mut recipes: HashMap<u32, Rc<Recipe>> = HashMap::new();
+    for value in list(&document, "recipes")? {
+        let mut items: Vec<RecipeItem> = Vec::new();
+        for item in list(value, "items")? {
+            items.push(RecipeItem {
+                ingredient: lookup(&ingredients, item, "ingredient", "recipe item")?,
+                amount: number(item, "amount", "recipe item")?,
+                unit: unit(item, "unit", "recipe item")?,
+            });
+        }
+        let recipe = Rc::new(Recipe {
+            id: id(value, "id", "recipe")?,
+            name: text(value, "name", "recipe")?,
+            servings: number(value, "servings", "recipe")?,
+            items,
+        });
+        recipes.insert(recipe.id, recipe.clone());
+        backup.recipes.push(recipe);
+    }
+
+    for value in list(&document, "daily_logs")? {
+        let date = text(value, "date", "log entry")?;
+        let date = date
+            .parse::<NaiveDate>()
+            .map_err(|_| BackupError::Invalid(format!("\"{}\" is not a valid date", date)))?;
+        let item = if value["recipe"].is_null() {
+            LogItem::Ingredient(lookup(&ingredients, value, "ingredient", "log entry")?)
+        } else {
+            LogItem::Recipe(lookup(&recipes, value, "recipe", "log entry")?)
+        };
+        let meal = match value["meal"].is_null() {
+            true => None,
+            false => Some(lookup(&meals, value, "meal", "log entry")?),
+        };
+        let time =
+            match value["time"].as_str() {
+                Some(time) => Some(time.parse::<NaiveTime>().map_err(|_| {
+                    BackupError::Invalid(format!("\"{}\" is not a valid time", time))
+                })?),
+                None => None,
+            };
+        backup.log_entries.push((
+            date,
+            LogEntry {
+                id: 0,
+                item,
+                amount: number(value, "amount", "log entry")?,
+                unit: unit(value, "unit", "log entry")?,
+                serving: value["serving"]
+                    .as_u64()
+                    .and_then(|id| u32::try_from(id).ok()),
+                meal,
+                time,
+            },
+        ));
+    }
+
+    for value in list(&document, "goals")? {
+        let nutrient = text(value, "nutrient", "goal")?;
+        let weekday = match value["weekday"].as_str() {
+            Some(weekday) => Some(weekday.parse::<Weekday>().map_err(|_| {
+                BackupError::Invalid(format!("\"{}\" is not a valid weekday", weekday))
+            })?),
+            None => None,
+        };
+        backup.goals.push(Goal {
+            nutrient: GoalNutrient::ALL
+                .into_iter()
+                .find(|goal_nutrient| goal_nutrient.to_string() == nutrient)
+                .ok_or_else(|| {
+                    BackupError::Invalid(format!("unknown goal nutrient \"{}\"", nutrient))
+                })?,
+            weekday,
+            target: number(value, "target", "goal")?,
+        });
+    }
+
+    let value = &document["intake_profile"];
+    if !value.is_null() {
+        let sex = text(value, "sex", "intake profile")?;
+        let age = text(value, "age_group", "intake profile")?;
+        backup.intake_profile = Some(IntakeProfile {
+            sex: Sex::ALL
+                .into_iter()
+                .find(|known| known.to_string() == sex)
+                .ok_or_else(|| BackupError::Invalid(format!("unknown sex \"{}\"", sex)))?,
+            age: AgeGroup::ALL
+                .into_iter()
+                .find(|known| known.to_string() == age)
+                .ok_or_else(|| BackupError::Invalid(format!("unknown age group \"{}\"", age)))?,
+        });
+    }
+
+    Ok(backup)
+}
+
+// A missing list counts as empty, so sections can be left out of hand-written files.
+fn list<'a>(value: &'a Value, key: &str) -> Result<&'a [Value], BackupError> {
+    match &value[key] {
+        Value::Null => Ok(&[]),
+        Value::Array(values) => Ok(values),
+        _ => Err(BackupError::Invalid(format!("\"{}\" is not a list", key))),
+    }
+}
+
+fn missing(key: &str, context: &str) -> BackupError {
+    BackupError::Invalid(format!("{} without a valid \"{}\"", context, key))
+}
+
+fn text(value: &Value, key: &str, context: &str) -> Result<String, BackupError> {
+    value[key]
+        .as_str()
+        .map(str::to_owned)
+        .ok_or_else(|| missing(key, context))
+}
+
+fn number(value: &Value, key: &str, context: &str) -> Result<f32, BackupError> {
+    value[key]
+        .as_f64()
+        .map(|number| number as f32)
+        .ok_or_else(|| missing(key, context))
+}
+
+fn id(value: &Value, key: &str, context: &str) -> Result<u32, BackupError> {
+    value[key]
+        .as_u64()
+        .and_then(|id| u32::try_from(id).ok())
+        .ok_or_else(|| missing(key, context))
+}
+
+fn unit(value: &Value, key: &str, context: &str) -> Result<Unit, BackupError> {
+    value[key]
+        .as_str()
+        .and_then(Unit::from_symbol)
+        .ok_or_else(|| missing(key, context))
+}
+
+fn lookup<T: Clone>(
+    entries: &HashMap<u32, T>,
+    value: &Value,
+    key: &str,
+    context: &str,
+) -> Result<T, BackupError> {
+    let id = id(value, key, context)?;
+    entries.get(&id).cloned().ok_or_else(|| {
+        BackupError::Invalid(format!("{} refers to unknown {} {}", context, key, id))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_hand_written_documents_and_rejects_newer_versions() {
+        let document = r#"{
+            "format": "sophross-backup",
+            "version": 1,
+            "ingredients": [{
+                "id": 3,
+                "name": "Oats",
+                "categories": [],
+                "nutritional_info": [{
+                    "default_amount": 100,
+                    "default_unit": "g",
+                    "nutrients": { "kilocalories": 389, "vitamin_z": 1 }
+                }]
+            }],
+            "daily_logs": [{ "date": "2024-03-26", "ingredient": 3, "amount": 0.5, "unit": "serving" }]
+        }"#;
+        let backup = read(document.as_bytes()).unwrap();
+        assert_eq!(backup.ingredients[0].brand, "");
+        assert_eq!(
+            backup.ingredients[0].nutritional_info[0].kilocalories,
+            389.0
+        );
+        assert!(backup.log_entries[0].1.meal.is_none());
+        assert!(backup.goals.is_empty());
+        assert!(backup.intake_profile.is_none());
+
+        let dangling = document.replace(r#""ingredient": 3"#, r#""ingredient": 4"#);
+        assert!(matches!(
+            read(dangling.as_bytes()),
+            Err(BackupError::Invalid(message)) if message == "log entry refers to unknown ingredient 4"
+        ));
+
+        let without_nutrients = document.replace(
+            r#""nutritional_info": [{
+                    "default_amount": 100,
+                    "default_unit": "g",
+                    "nutrients": { "kilocalories": 389, "vitamin_z": 1 }
+                }]"#,
+            r#""nutritional_info": []"#,
+        );
+        assert!(matches!(
+            read(without_nutrients.as_bytes()),
+            Err(BackupError::Invalid(message)) if message == "ingredient \"Oats\" has no nutritional info"
+        ));
+
+        let profile = document.replace(
+            r#""daily_logs""#,
+            r#""intake_profile": { "sex": "Male", "age_group": "19–30 years" }, "daily_logs""#,
+        );
+        assert_eq!(
+            read(profile.as_bytes()).unwrap().intake_profile,
+            Some(IntakeProfile {
+                sex: Sex::Male,
+                age: AgeGroup::Years19To30
+            })
+        );
+        let unknown = profile.replace("Male", "Other");
+        assert!(matches!(
+            read(unknown.as_bytes()),
+            Err(BackupError::Invalid(message)) if message == "unknown sex \"Other\""
+        ));
+
+        let newer = document.replace(r#""version": 1"#, r#""version": 2"#);
+        assert!(matches!(
+            read(newer.as_bytes()),
+            Err(BackupError::NewerVersion {
+                found: 2,
+                supported: 1
+            })
+        ));
+    }
+}
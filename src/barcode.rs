@@ -0,0 +1,149 @@
+// EAN/UPC barcodes of packaged foods and keyboard input from barcode scanners.
+//
+// EAN-8, UPC-A, EAN-13 and GTIN-14 codes are accepted. They all end in a check
+// digit and denote the same product when they only differ in leading zeros,
+// e.g. the UPC-A 012345678905 and the EAN-13 0012345678905.
+
+const LENGTHS: [usize; 4] = [8, 12, 13, 14];
+
+// Scanners type a whole code within milliseconds, people need much longer
+// between two keys.
+const MAX_SCAN_KEY_INTERVAL: f64 = 0.1;
+
+#[derive(Debug, PartialEq)]
+pub enum BarcodeError {
+    InvalidCharacter(char),
+    Length(usize),
+    CheckDigit { expected: u32 },
+    AllZeros,
+}
+
+impl std::fmt::Display for BarcodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BarcodeError::InvalidCharacter(character) => {
+                write!(f, "only digits are allowed, found {:?}", character)
+            }
+            BarcodeError::Length(length) => {
+                write!(f, "8, 12, 13 or 14 digits are needed, found {}", length)
+            }
+            BarcodeError::CheckDigit { expected } => {
+                write!(f, "wrong check digit, expected {}", expected)
+            }
+            BarcodeError::AllZeros => write!(f, "at least one digit must not be 0"),
+        }
+    }
+}
+
+impl std::error::Error for BarcodeError {}
+
+// Strips spaces and dashes from `code` and checks its length and check digit.
+pub fn normalize(code: &str) -> Result<String, BarcodeError> {
+    let code: String = code
+        .chars()
+        .filter(|character| !character.is_whitespace() && *character != '-')
+        .collect();
+    if let Some(character) = code.chars().find(|character| !character.is_ascii_digit()) {
+        return Err(BarcodeError::InvalidCharacter(character));
+    }
+    if !LENGTHS.contains(&code.len()) {
+        return Err(BarcodeError::Length(code.len()));
+    }
+    // Codes are compared without leading zeros, which would leave nothing of these.
+    if code.chars().all(|character| character == '0') {
+        return Err(BarcodeError::AllZeros);
+    }
+
+    let (payload, check) = code.split_at(code.len() - 1);
+    let expected = check_digit(payload);
+    if check.parse::<u32>() != Ok(expected) {
+        return Err(BarcodeError::CheckDigit { expected });
+    }
+    Ok(code)
+}
+
+// The digits are weighted 3 and 1 alternating from the right.
+fn check_digit(payload: &str) -> u32 {
+    let sum: u32 = payload
+        .chars()
+        .rev()
+        .filter_map(|digit| digit.to_digit(10))
+        .enumerate()
+        .map(|(index, digit)| if index % 2 == 0 { digit * 3 } else { digit })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+// Collects the digits typed by a keyboard-wedge scanner while no text field
+// has focus. A scan ends with Enter.
+#[derive(Default)]
+pub struct ScanBuffer {
+    digits: String,
+    last_input: f64,
+}
+
+impl ScanBuffer {
+    pub fn push(&mut self, text: &str, time: f64) {
+        if time - self.last_input > MAX_SCAN_KEY_INTERVAL {
+            self.digits.clear();
+        }
+        self.last_input = time;
+        if text.chars().all(|character| character.is_ascii_digit()) {
+            self.digits.push_str(text);
+        } else {
+            self.digits.clear();
+        }
+    }
+
+    // The scanned code if Enter follows a valid barcode without a pause.
+    pub fn finish(&mut self, time: f64) -> Option<String> {
+        let digits = std::mem::take(&mut self.digits);
+        if time - self.last_input > MAX_SCAN_KEY_INTERVAL {
+            return None;
+        }
+        normalize(&digits).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_digits_are_validated() {
+        assert_eq!(normalize("4006381333931"), Ok("4006381333931".to_owned()));
+        assert_eq!(normalize(" 0 12345-67890 5"), Ok("012345678905".to_owned()));
+        assert_eq!(normalize("96385074"), Ok("96385074".to_owned()));
+        assert_eq!(
+            normalize("4006381333932"),
+            Err(BarcodeError::CheckDigit { expected: 1 })
+        );
+        assert_eq!(normalize("12345"), Err(BarcodeError::Length(5)));
+        assert_eq!(
+            normalize("40063813339x1"),
+            Err(BarcodeError::InvalidCharacter('x'))
+        );
+    }
+
+    #[test]
+    fn codes_of_only_zeros_are_rejected() {
+        assert_eq!(normalize("00000000"), Err(BarcodeError::AllZeros));
+        assert_eq!(normalize("0000 0000 0000 0"), Err(BarcodeError::AllZeros));
+        assert_eq!(normalize("00000000000000"), Err(BarcodeError::AllZeros));
+    }
+
+    #[test]
+    fn scans_are_told_apart_from_typing() {
+        let mut buffer = ScanBuffer::default();
+        for (index, digit) in "4006381333931".chars().enumerate() {
+            buffer.push(&digit.to_string(), 10.0 + index as f64 * 0.01);
+        }
+        assert_eq!(buffer.finish(10.2), Some("4006381333931".to_owned()));
+        assert_eq!(buffer.finish(10.21), None);
+
+        for (index, digit) in "4006381333931".chars().enumerate() {
+            buffer.push(&digit.to_string(), 20.0 + index as f64 * 0.5);
+        }
+        assert_eq!(buffer.finish(26.1), None);
+    }
+}
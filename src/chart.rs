@@ -0,0 +1,247 @@
+use egui::{pos2, vec2, Align2, Color32, Pos2, Rect, Shape, Stroke};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChartStyle {
+    Line,
+    Bar,
+    // For reference values like goals, drawn over lines and bars.
+    Dashed,
+}
+
+pub struct ChartSeries {
+    pub name: String,
+    pub color: Color32,
+    pub style: ChartStyle,
+    // One value per label, `None` where the series has no data.
+    pub values: Vec<Option<f32>>,
+}
+
+const EMPTY_TEXT: &str = "No data";
+
+// Room for the value labels left of the plot.
+const VALUE_LABEL_WIDTH: f32 = 64.0;
+
+const GRID_LINES: f32 = 5.0;
+
+// The distance of the grid lines for values up to `maximum`, 1, 2 or 5 times a
+// power of ten so they fall on round numbers.
+pub fn grid_step(maximum: f32) -> f32 {
+    let rough = maximum / GRID_LINES;
+    if rough.is_nan() || rough <= 0.0 {
+        return 1.0;
+    }
+    let magnitude = 10.0_f32.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= rough * (1.0 - 1e-6))
+        .unwrap_or(10.0 * magnitude)
+}
+
+fn generate_chart(
+    ui: &mut egui::Ui,
+    height: f32,
+    labels: Vec<String>,
+    series: Vec<ChartSeries>,
+    unit: &str,
+) -> egui::Response {
+    let desired_size = vec2(ui.available_width(), ui.spacing().interact_size.y * height);
+    let (rect, mut response) = ui.allocate_exact_size(desired_size, egui::Sense::hover());
+    if response.hovered() {
+        response.mark_changed();
+    }
+    if !ui.is_rect_visible(rect) {
+        return response;
+    }
+
+    let visuals = ui.style().noninteractive();
+    let text_color = visuals.text_color();
+    let grid_stroke = Stroke::new(1.0, visuals.bg_stroke.color);
+    let font = egui::TextStyle::Small.resolve(ui.style());
+    let row_height = ui.fonts(|fonts| fonts.row_height(&font));
+    let painter = ui.painter_at(rect);
+
+    // The plot leaves room for the value labels on the left and for the date
+    // labels and the legend at the bottom.
+    let plot = Rect::from_min_max(
+        pos2(
+            rect.left() + VALUE_LABEL_WIDTH,
+            rect.top() + 0.5 * row_height,
+        ),
+        pos2(rect.right() - 4.0, rect.bottom() - 2.5 * row_height),
+    );
+    if plot.width() <= 0.0 || plot.height() <= 0.0 {
+        return response;
+    }
+
+    let maximum = series
+        .iter()
+        .flat_map(|series| series.values.iter().flatten())
+        .fold(0.0_f32, |maximum, value| maximum.max(*value));
+    if labels.is_empty() || maximum <= 0.0 {
+        painter.rect_stroke(plot, 0.0, grid_stroke);
+        painter.text(
+            plot.center(),
+            Align2::CENTER_CENTER,
+            EMPTY_TEXT,
+            font,
+            text_color,
+        );
+        return response;
+    }
+
+    let step = grid_step(maximum);
+    let top = step * (maximum / step).ceil();
+    let y = |value: f32| plot.bottom() - plot.height() * value / top;
+    let slot_width = plot.width() / labels.len() as f32;
+    let x = |index: usize| plot.left() + slot_width * (index as f32 + 0.5);
+
+    let decimals = (-step.log10()).ceil().max(0.0) as usize;
+    for line in 0..=(top / step).round() as u32 {
+        let value = step * line as f32;
+        painter.line_segment(
+            [pos2(plot.left(), y(value)), pos2(plot.right(), y(value))],
+            grid_stroke,
+        );
+        painter.text(
+            pos2(plot.left() - 4.0, y(value)),
+            Align2::RIGHT_CENTER,
+            format!("{:.*} {}", decimals, value, unit),
+            font.clone(),
+            text_color,
+        );
+    }
+
+    // Only every few labels are shown when they would overlap.
+    let label_width = labels
+        .iter()
+        .map(|label| {
+            painter
+                .layout_no_wrap(label.clone(), font.clone(), text_color)
+                .size()
+                .x
+        })
+        .fold(0.0_f32, f32::max);
+    let label_step = ((label_width + 8.0) / slot_width).ceil().max(1.0) as usize;
+    for (index, label) in labels.iter().enumerate().step_by(label_step) {
+        painter.text(
+            pos2(x(index), plot.bottom() + 2.0),
+            Align2::CENTER_TOP,
+            label,
+            font.clone(),
+            text_color,
+        );
+    }
+
+    // Several bar series share each slot side by side.
+    let bar_series: Vec<&ChartSeries> = series
+        .iter()
+        .filter(|series| series.style == ChartStyle::Bar)
+        .collect();
+    let bar_width = 0.8 * slot_width / bar_series.len().max(1) as f32;
+    for (position, series) in bar_series.iter().enumerate() {
+        for (index, value) in series.values.iter().enumerate() {
+            if let Some(value) = value {
+                let left = x(index) - 0.4 * slot_width + bar_width * position as f32;
+                painter.rect_filled(
+                    Rect::from_min_max(
+                        pos2(left, y(*value)),
+                        pos2(left + bar_width, plot.bottom()),
+                    ),
+                    0.0,
+                    series.color,
+                );
+            }
+        }
+    }
+
+    for style in [ChartStyle::Line, ChartStyle::Dashed] {
+        for series in series.iter().filter(|series| series.style == style) {
+            let stroke = Stroke::new(2.0, series.color);
+            // Days without a value split the line.
+            let mut segment: Vec<Pos2> = Vec::new();
+            let end = (series.values.len(), None);
+            for (index, value) in series.values.iter().copied().enumerate().chain([end]) {
+                if let Some(value) = value {
+                    segment.push(pos2(x(index), y(value)));
+                    continue;
+                }
+                match (segment.len(), style) {
+                    (0, _) => {}
+                    (1, _) => {
+                        painter.circle_filled(segment[0], 2.0, series.color);
+                    }
+                    (_, ChartStyle::Dashed) => {
+                        painter.extend(Shape::dashed_line(&segment, stroke, 6.0, 4.0))
+                    }
+                    _ => {
+                        painter.add(Shape::line(segment.clone(), stroke));
+                    }
+                }
+                segment.clear();
+            }
+        }
+    }
+
+    let mut legend_left = plot.left();
+    for series in &series {
+        let swatch = Rect::from_min_size(
+            pos2(legend_left, rect.bottom() - 0.75 * row_height),
+            vec2(0.5 * row_height, 0.5 * row_height),
+        );
+        painter.rect_filled(swatch, 0.0, series.color);
+        let text = painter.text(
+            pos2(swatch.right() + 4.0, swatch.center().y),
+            Align2::LEFT_CENTER,
+            &series.name,
+            font.clone(),
+            text_color,
+        );
+        legend_left = text.right() + 12.0;
+    }
+
+    if let Some(pointer) = response.hover_pos().filter(|pos| plot.contains(*pos)) {
+        let index = (((pointer.x - plot.left()) / slot_width) as usize).min(labels.len() - 1);
+        painter.line_segment(
+            [pos2(x(index), plot.top()), pos2(x(index), plot.bottom())],
+            Stroke::new(1.0, text_color),
+        );
+        egui::show_tooltip(ui.ctx(), response.id.with("tooltip"), |ui| {
+            ui.label(&labels[index]);
+            for series in &series {
+                if let Some(value) = series.values.get(index).copied().flatten() {
+                    ui.colored_label(
+                        series.color,
+                        format!("{}: {:.1} {}", series.name, value, unit),
+                    );
+                }
+            }
+        });
+    }
+
+    response
+}
+
+// Draws `series` over a row of `labels`, e.g. one value per day, as lines or
+// bars. The chart fills the available width and is `height` rows tall.
+pub fn chart<'a>(
+    height: f32,
+    labels: Vec<String>,
+    series: Vec<ChartSeries>,
+    unit: &'a str,
+) -> impl egui::Widget + 'a {
+    move |ui: &mut egui::Ui| generate_chart(ui, height, labels, series, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_steps_are_round() {
+        assert_eq!(grid_step(2130.0), 500.0);
+        assert_eq!(grid_step(120.0), 50.0);
+        assert_eq!(grid_step(3.2), 1.0);
+        assert_eq!(grid_step(0.0), 1.0);
+    }
+}
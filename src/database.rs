@@ -1,932 +1,742 @@
+mod error;
+mod history;
+mod migrations;
+
+pub use error::DatabaseError;
+
+use crate::backup::{Backup, RestoreMode};
+use crate::goals::Goal;
 use crate::ingredients::*;
-use chrono::NaiveDate;
+use crate::reference_intakes::IntakeProfile;
+use chrono::{NaiveDate, Weekday};
 use eframe::epaint::Color32;
-use log::log;
-use rusqlite::{Connection, Error as RusqliteError, Statement};
-use std::collections::HashSet;
+use history::{Command, History, Scope};
+use rusqlite::types::Type;
+use rusqlite::{Connection, Error as RusqliteError, OptionalExtension, Row};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::str::FromStr;
 
-const NAME: &str = "data.db";
+const INGREDIENT_QUERY: &str = "
+    SELECT
+        ing.id, ing.name, brand, barcode, density, piece_weight, archived,
+        ni.id AS nutritional_info_id, ni.name AS serving_name,
+        default_amount, default_unit, kilocalories,
+        --essentials
+        histidine, isoleucine, leucine, lysine, methionine, phenylalanine,
+        threonine, tryptophan, valine,
+        --non-essentials
+        alanine, arginine, asparagine, aspartic_acid, cysteine, glutamic_acid, glutamine,
+        glycine, proline, serine, tyrosine, unspecified_protein,
+        --fats
+        saturated, monounsaturated, polyunsaturated, unspecified_fat,
+        --carbohydrates
+        starch, fiber, sugars, sugar_alcohols, unspecified_carbohydrates,
+        --vitamins
+        vitamin_a, vitamin_b1, vitamin_b2, vitamin_b3, vitamin_b5, vitamin_b6, vitamin_b9,
+        vitamin_b12, vitamin_c, vitamin_d, vitamin_e, vitamin_k, betaine, choline,
+        --minerals
+        calcium, copper, iron, magnesium, manganese,
+        phosphorus, potassium, selenium, sodium, zinc
+    FROM ingredients ing
+    INNER JOIN nutritional_info ni
+        ON ing.id = ni.ingredient_id
+    INNER JOIN protein_sets ps
+        ON ni.id = ps.nutrition_info_id
+    INNER JOIN fat_sets fs
+        ON ni.id = fs.nutrition_info_id
+    INNER JOIN carbohydrate_sets cs
+        ON ni.id = cs.nutrition_info_id
+    INNER JOIN vitamin_sets vs
+        ON ni.id = vs.nutrition_info_id
+    INNER JOIN mineral_sets ms
+        ON ni.id = ms.nutrition_info_id
+";
+
+// Every table with user data, as touched by restoring a backup.
+const BACKUP_TABLES: [&str; 15] = [
+    "categories",
+    "ingredients",
+    "ingredient_categories",
+    "nutritional_info",
+    "protein_sets",
+    "fat_sets",
+    "carbohydrate_sets",
+    "vitamin_sets",
+    "mineral_sets",
+    "meals",
+    "recipes",
+    "recipe_items",
+    "daily_logs",
+    "goals",
+    "intake_profile",
+];
+
+// Tables holding the nutrient values of a `nutritional_info` row.
+const NUTRIENT_SET_TABLES: [&str; 5] = [
+    "protein_sets",
+    "fat_sets",
+    "carbohydrate_sets",
+    "vitamin_sets",
+    "mineral_sets",
+];
 
 pub struct Database {
+    path: PathBuf,
     db_connection: Option<Rc<Connection>>,
+    history: History,
 }
 
 impl Database {
-    pub fn new() -> Self {
-        let mut db = Database {
+    pub fn new(path: PathBuf) -> Self {
+        Database {
+            path,
             db_connection: None,
-        };
-        db.setup_tables();
-        db
+            history: History::default(),
+        }
     }
 
-    fn start_connection(&mut self) {
-        if self.db_connection.is_none() {
-            self.db_connection = Some(Rc::new(
-                Connection::open(NAME).expect("Unable to establish database connection!"),
-            ));
-            self.db_connection
-                .as_ref()
-                .unwrap()
-                .execute("PRAGMA foreign_keys = ON", [])
-                .expect("Failed to enable foreign key pragma!");
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self, DatabaseError> {
+        let connection = Connection::open_in_memory()?;
+        configure_connection(&connection)?;
+
+        Ok(Database {
+            path: PathBuf::from(":memory:"),
+            db_connection: Some(Rc::new(connection)),
+            history: History::default(),
+        })
+    }
+
+    //Opens the connection and brings the schema up to date on first use.
+    fn start_connection(&mut self) -> Result<Rc<Connection>, DatabaseError> {
+        if let Some(connection) = &self.db_connection {
+            return Ok(connection.clone());
+        }
+
+        if let Some(directory) = self.path.parent() {
+            std::fs::create_dir_all(directory).map_err(DatabaseError::FileSystem)?;
         }
+
+        let connection = Connection::open(&self.path)?;
+        configure_connection(&connection)?;
+
+        let connection = Rc::new(connection);
+        self.db_connection = Some(connection.clone());
+        Ok(connection)
     }
 
-    fn setup_tables(&mut self) {
-        self.start_connection();
+    pub fn undo_description(&self) -> Option<&str> {
+        self.history.undo_description()
+    }
 
-        let ingredients_create_query = "
-            CREATE TABLE IF NOT EXISTS ingredients (
-                id INTEGER PRIMARY KEY,
-                name TEXT,
-                brand TEXT
-            );
-        ";
-        self.db_connection
-            .as_ref()
-            .unwrap()
-            .execute(ingredients_create_query, ())
-            .expect("Failed to create table 'ingredients'!");
-
-        let categories_create_query = "
-            CREATE TABLE IF NOT EXISTS categories (
-                id INTEGER PRIMARY KEY,
-                name TEXT,
-                icon_name TEXT,
-                icon_color TEXT
-            );
-        ";
-        self.db_connection
-            .as_ref()
-            .unwrap()
-            .execute(categories_create_query, ())
-            .expect("Failed to create table 'categories'!");
-
-        let ingredient_categories_create_query = "
-            CREATE TABLE IF NOT EXISTS ingredient_categories (
-                ingredient_id INTEGER,
-                category_id INTEGER,
-                FOREIGN KEY(ingredient_id) REFERENCES ingredients(id) ON DELETE CASCADE
-                FOREIGN KEY(category_id) REFERENCES categories(id) ON DELETE CASCADE
-            );
-        ";
-        self.db_connection
-            .as_ref()
-            .unwrap()
-            .execute(ingredient_categories_create_query, ())
-            .expect("Failed to create table 'ingredient_categories'!");
-
-        let nutritional_info_create_query = "
-            CREATE TABLE IF NOT EXISTS nutritional_info (
-                id INTEGER PRIMARY KEY,
-                default_amount REAL,
-                default_unit INTEGER,
-                kilocalories REAL,
-                ingredient_id INTEGER,
-                FOREIGN KEY(ingredient_id) REFERENCES ingredients(id) ON DELETE CASCADE
-            );
-        ";
-        self.db_connection
-            .as_ref()
-            .unwrap()
-            .execute(nutritional_info_create_query, ())
-            .expect("Failed to create table 'nutritional_info'!");
+    pub fn redo_description(&self) -> Option<&str> {
+        self.history.redo_description()
+    }
 
-        let protein_sets_create_query = "
-            CREATE TABLE IF NOT EXISTS protein_sets (
-                id INTEGER PRIMARY KEY,
-                nutrition_info_id INTEGER,
-            --essentials
-                histidine REAL,
-                isoleucine REAL,
-                leucine REAL,
-                lysine REAL,
-                methionine REAL,
-                phenylalanine REAL,
-                threonine REAL,
-                tryptophan REAL,
-                valine REAL,
-            --non-essentials
-                alanine REAL,
-                arginine REAL,
-                asparagine REAL,
-                aspartic_acid REAL,
-                cysteine REAL,
-                glutamic_acid REAL,
-                glutamine REAL,
-                glycine REAL,
-                proline REAL,
-                serine REAL,
-                tyrosine REAL,
-                FOREIGN KEY(nutrition_info_id) REFERENCES nutritional_info(id) ON DELETE CASCADE
-            );
-        ";
-        self.db_connection
-            .as_ref()
-            .unwrap()
-            .execute(protein_sets_create_query, ())
-            .expect("Failed to create table 'protein_sets'!");
-
-        let fat_sets_create_query = "
-            CREATE TABLE IF NOT EXISTS fat_sets (
-                id INTEGER PRIMARY KEY,
-                nutrition_info_id INTEGER,
-                saturated REAL,
-                monounsaturated REAL,
-                polyunsaturated REAL,
-                FOREIGN KEY(nutrition_info_id) REFERENCES nutritional_info(id) ON DELETE CASCADE
-            );
-        ";
-        self.db_connection
-            .as_ref()
-            .unwrap()
-            .execute(fat_sets_create_query, ())
-            .expect("Failed to create table 'fat_sets'!");
-
-        let carbohydrate_sets_create_query = "
-            CREATE TABLE IF NOT EXISTS carbohydrate_sets (
-                id INTEGER PRIMARY KEY,
-                nutrition_info_id INTEGER,
-                starch REAL,
-                fiber REAL,
-                sugars REAL,
-                sugar_alcohols REAL,
-                FOREIGN KEY(nutrition_info_id) REFERENCES nutritional_info(id) ON DELETE CASCADE
-            );
-        ";
-        self.db_connection
-            .as_ref()
-            .unwrap()
-            .execute(carbohydrate_sets_create_query, ())
-            .expect("Failed to create table 'carbohydrate_sets'!");
-
-        let vitamin_sets_create_query = "
-            CREATE TABLE IF NOT EXISTS vitamin_sets (
-                id INTEGER PRIMARY KEY,
-                nutrition_info_id INTEGER,
-                vitamin_a REAL,
-                vitamin_b1 REAL,
-                vitamin_b2 REAL,
-                vitamin_b3 REAL,
-                vitamin_b5 REAL,
-                vitamin_b6 REAL,
-                vitamin_b9 REAL,
-                vitamin_b12 REAL,
-                vitamin_c REAL,
-                vitamin_d REAL,
-                vitamin_e REAL,
-                vitamin_k REAL,
-                betaine REAL,
-                choline REAL,
-                FOREIGN KEY(nutrition_info_id) REFERENCES nutritional_info(id) ON DELETE CASCADE
-            );
-        ";
-        self.db_connection
-            .as_ref()
-            .unwrap()
-            .execute(vitamin_sets_create_query, ())
-            .expect("Failed to create table 'vitamin_sets'!");
-
-        let mineral_sets_create_query = "
-            CREATE TABLE IF NOT EXISTS mineral_sets (
-                id INTEGER PRIMARY KEY,
-                nutrition_info_id INTEGER,
-                calcium REAL,
-                copper REAL,
-                iron REAL,
-                magnesium REAL,
-                manganese REAL,
-                phosphorus REAL,
-                potassium REAL,
-                selenium REAL,
-                sodium REAL,
-                zinc REAL,
-                FOREIGN KEY(nutrition_info_id) REFERENCES nutritional_info(id) ON DELETE CASCADE
-            );
-        ";
-        self.db_connection
-            .as_ref()
-            .unwrap()
-            .execute(mineral_sets_create_query, ())
-            .expect("Failed to create table 'mineral_sets'!");
-
-        let daily_logs_create_query = "
-            CREATE TABLE IF NOT EXISTS daily_logs (
-                id INTEGER PRIMARY KEY,
-                date TEXT,
-                ingredient_id INTEGER,
-                fraction REAL,
-                FOREIGN KEY(ingredient_id) REFERENCES ingredients(id) ON DELETE CASCADE
-            );
-        ";
-        self.db_connection
-            .as_ref()
-            .unwrap()
-            .execute(daily_logs_create_query, ())
-            .expect("Failed to create table 'daily_logs'!");
+    // Reverts the latest change and returns its description, if there was one.
+    pub fn undo(&mut self) -> Result<Option<String>, DatabaseError> {
+        let connection = self.start_connection()?;
+        Ok(self.history.undo(&connection)?)
     }
 
-    pub fn insert_category(&mut self, category: &Category) {
-        self.start_connection();
+    // Applies the latest undone change again and returns its description, if there was one.
+    pub fn redo(&mut self) -> Result<Option<String>, DatabaseError> {
+        let connection = self.start_connection()?;
+        Ok(self.history.redo(&connection)?)
+    }
 
-        let mut statement = self
-            .db_connection
-            .as_ref()
-            .unwrap()
-            .prepare("INSERT INTO categories (name, icon_name, icon_color) VALUES (?1, ?2, ?3);")
-            .unwrap();
-        let _ = statement
-            .insert(rusqlite::params![
-                category.name,
-                category.icon_name,
-                category.icon_color.to_hex()
-            ])
-            .unwrap();
+    pub fn insert_category(&mut self, category: &Category) -> Result<(), DatabaseError> {
+        let connection = self.start_connection()?;
+
+        let mut statement = connection
+            .prepare("INSERT INTO categories (name, icon_name, icon_color) VALUES (?1, ?2, ?3);")?;
+        let id = statement.insert(rusqlite::params![
+            category.name,
+            category.icon_name,
+            category.icon_color.to_hex()
+        ])?;
+
+        self.history.push(Command::inserted(
+            &connection,
+            format!("add category \"{}\"", category.name),
+            category_scopes(&[id as u32]),
+        )?);
+
+        Ok(())
     }
 
-    pub fn get_categories(&mut self) -> Vec<Category> {
-        self.start_connection();
+    pub fn get_categories(&mut self) -> Result<Vec<Category>, DatabaseError> {
+        let connection = self.start_connection()?;
+
+        let mut statement = connection
+            .prepare("SELECT id, name, icon_name, icon_color FROM categories ORDER BY id")?;
+        let categories_iter = statement.query_map([], category_from_row)?;
 
-        let mut statement = self
-            .db_connection
-            .as_ref()
-            .unwrap()
-            .prepare("SELECT id, name, icon_name, icon_color FROM categories")
-            .unwrap();
-        let categories_iter = statement
-            .query_map([], |row| {
-                Ok(Category {
-                    id: row.get("id")?,
-                    name: row.get("name")?,
-                    icon_name: row.get("icon_name")?,
-                    icon_color: Color32::from_hex(
-                        &row.get::<&str, String>("icon_color")
-                            .expect("Could not parse hex value into color!"),
-                    )
-                    .unwrap(),
-                })
-            })
-            .unwrap();
         let mut data: Vec<Category> = Vec::new();
         for category in categories_iter {
-            data.push(category.unwrap());
+            data.push(category?);
         }
 
-        data
+        Ok(data)
     }
 
-    pub fn delete_category(&mut self, category: &Category) -> Result<usize, RusqliteError> {
-        self.start_connection();
-
-        let category_delete_query = format!(
-            "
-            DELETE FROM categories
-            WHERE id = {}
-            ",
-            category.id
-        );
-        let mut delete_statement: Statement = self
-            .db_connection
-            .as_ref()
-            .unwrap()
-            .prepare(&category_delete_query)
-            .expect("Failed to prepare delete statement!");
-        delete_statement.execute([])
+    pub fn delete_category(&mut self, category: &Category) -> Result<usize, DatabaseError> {
+        self.delete_categories(std::slice::from_ref(category))
     }
 
-    pub fn delete_categories(&mut self, categories: &[Category]) -> Result<usize, RusqliteError> {
-        self.start_connection();
-
+    pub fn delete_categories(&mut self, categories: &[Category]) -> Result<usize, DatabaseError> {
+        let connection = self.start_connection()?;
+        let ids: Vec<u32> = categories.iter().map(|category| category.id).collect();
+        let command = Command::begin(
+            &connection,
+            match categories {
+                [category] => format!("delete category \"{}\"", category.name),
+                _ => format!("delete {} categories", categories.len()),
+            },
+            category_scopes(&ids),
+        )?;
+
+        let transaction = connection.unchecked_transaction()?;
         let mut row_count: usize = 0;
-
-        for category in categories {
-            match self.delete_category(category) {
-                Ok(value) => row_count += value,
-                Err(error) => return Err(error),
+        {
+            let mut delete_statement =
+                transaction.prepare("DELETE FROM categories WHERE id = ?1;")?;
+            for id in &ids {
+                row_count += delete_statement.execute([id])?;
             }
         }
 
+        transaction.commit()?;
+        self.history.push(command.finish(&connection)?);
+
         Ok(row_count)
     }
 
-    pub fn insert_ingredient(&mut self, ingredient: &Ingredient) {
-        self.start_connection();
+    pub fn insert_ingredient(&mut self, ingredient: &Ingredient) -> Result<u32, DatabaseError> {
+        let connection = self.start_connection()?;
+        let transaction = connection.unchecked_transaction()?;
 
-        fn category_inserts(ingredient: &Ingredient) -> String {
-            ingredient
-                .categories
-                .iter()
-                .map(|n| {
-                    format!(
-                        "INSERT INTO ingredient_categories (
-                            ingredient_id, category_id
-                        )
-                        VALUES({}, {});",
-                        "(SELECT value FROM _variables WHERE var_name = 'ingredient_id' LIMIT 1)",
-                        n.id.to_string()
-                    )
-                })
-                .collect::<Vec<String>>()
-                .join("\n")
-        }
+        let ingredient_id = insert_ingredient(&transaction, ingredient)?;
 
-        let ingredient_insert_query = format!(
-            "
-            BEGIN TRANSACTION;
+        transaction.commit()?;
 
-            CREATE TEMP TABLE IF NOT EXISTS _variables(var_name TEXT, value INTEGER);
+        self.history.push(Command::inserted(
+            &connection,
+            format!("add ingredient \"{}\"", ingredient.name),
+            ingredient_scopes(&[ingredient_id]),
+        )?);
 
-            INSERT INTO ingredients (
-                name, brand
-            )
-            VALUES ('{}', '{}');
+        Ok(ingredient_id)
+    }
 
-            INSERT INTO _variables (var_name, value) VALUES ('ingredient_id', last_insert_rowid());
+    // Inserts all ingredients or none of them, as a single change to undo.
+    pub fn insert_ingredients(
+        &mut self,
+        ingredients: &[Ingredient],
+    ) -> Result<Vec<u32>, DatabaseError> {
+        let connection = self.start_connection()?;
+        let transaction = connection.unchecked_transaction()?;
 
-            {}
+        let mut ids: Vec<u32> = Vec::new();
+        for ingredient in ingredients {
+            ids.push(insert_ingredient(&transaction, ingredient)?);
+        }
 
-            COMMIT;
-            ",
-            ingredient.name,
-            ingredient.brand,
-            category_inserts(&ingredient)
-        );
+        transaction.commit()?;
 
-        let _ = self
-            .db_connection
-            .as_ref()
-            .unwrap()
-            .execute_batch(&ingredient_insert_query);
+        self.history.push(Command::inserted(
+            &connection,
+            format!("import {} ingredients", ingredients.len()),
+            ingredient_scopes(&ids),
+        )?);
 
-        for nutritional_info in &ingredient.nutritional_info {
-            let nutritional_info_insert_query = format!(
-                "
-                BEGIN TRANSACTION;
+        Ok(ids)
+    }
 
-                INSERT INTO nutritional_info (
-                    default_amount, default_unit, kilocalories, ingredient_id
-                )
-                VALUES ({:.1}, {}, {:.1}, (SELECT value FROM _variables WHERE var_name = 'ingredient_id' LIMIT 1));
-
-                INSERT INTO _variables (var_name, value) VALUES ('nutritional_info_id', last_insert_rowid());
-
-                INSERT INTO protein_sets (
-                    nutrition_info_id,
-                    --essentials
-                    histidine, isoleucine, leucine, lysine, methionine, phenylalanine,
-                    threonine, tryptophan, valine,
-                    --non-essentials
-                    alanine, arginine, asparagine, aspartic_acid, cysteine, glutamic_acid, glutamine,
-                    glycine, proline, serine, tyrosine
-                )
-                VALUES ((SELECT value FROM _variables WHERE var_name = 'nutritional_info_id' LIMIT 1),
-                    {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1});
+    pub fn update_ingredient(&mut self, ingredient: &Ingredient) -> Result<(), DatabaseError> {
+        let connection = self.start_connection()?;
+        let command = Command::begin(
+            &connection,
+            format!("edit ingredient \"{}\"", ingredient.name),
+            ingredient_scopes(&[ingredient.id]),
+        )?;
+        let transaction = connection.unchecked_transaction()?;
 
-                INSERT INTO fat_sets (
-                    nutrition_info_id,
-                    saturated, monounsaturated, polyunsaturated
-                )
-                VALUES ((SELECT value FROM _variables WHERE var_name = 'nutritional_info_id' LIMIT 1),
-                    {:.1}, {:.1}, {:.1});
+        let row_count = transaction.execute(
+            "
+            UPDATE ingredients
+            SET name = ?1, brand = ?2, density = ?3, piece_weight = ?4, archived = ?5,
+                barcode = ?6
+            WHERE id = ?7;
+            ",
+            rusqlite::params![
+                ingredient.name,
+                ingredient.brand,
+                ingredient.conversions.density,
+                ingredient.conversions.piece_weight,
+                ingredient.archived,
+                ingredient.barcode,
+                ingredient.id
+            ],
+        )?;
+        if row_count == 0 {
+            return Err(DatabaseError::MissingRow(format!(
+                "ingredient with id {}",
+                ingredient.id
+            )));
+        }
 
-                INSERT INTO carbohydrate_sets (
-                    nutrition_info_id,
-                    starch, fiber, sugars, sugar_alcohols
-                )
-                VALUES ((SELECT value FROM _variables WHERE var_name = 'nutritional_info_id' LIMIT 1),
-                    {:.1}, {:.1}, {:.1}, {:.1});
+        transaction.execute(
+            "DELETE FROM ingredient_categories WHERE ingredient_id = ?1;",
+            [ingredient.id],
+        )?;
+        for category in &ingredient.categories {
+            transaction.execute(
+                "INSERT INTO ingredient_categories (ingredient_id, category_id) VALUES (?1, ?2);",
+                [ingredient.id, category.id],
+            )?;
+        }
 
-                INSERT INTO vitamin_sets (
-                    nutrition_info_id,
-                    vitamin_a, vitamin_b1, vitamin_b2, vitamin_b3, vitamin_b5, vitamin_b6, vitamin_b9,
-                    vitamin_b12, vitamin_c, vitamin_d, vitamin_e, vitamin_k, betaine, choline
-                )
-                VALUES ((SELECT value FROM _variables WHERE var_name = 'nutritional_info_id' LIMIT 1),
-                    {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1});
+        // Servings keep their ids, log entries refer to them. Removing a serving
+        // cascades to all of its *_sets rows, but a serving that is logged stays,
+        // its entries would silently fall back to the first serving.
+        let mut statement = transaction.prepare(
+            "
+            SELECT ni.id, ni.name, default_amount, default_unit, COUNT(dl.id) AS log_entries
+            FROM nutritional_info ni
+            LEFT JOIN daily_logs dl ON dl.nutritional_info_id = ni.id
+            WHERE ni.ingredient_id = ?1
+            GROUP BY ni.id;
+            ",
+        )?;
+        let stored = statement
+            .query_map([ingredient.id], |row| {
+                let serving = NutritionalInfo {
+                    id: row.get("id")?,
+                    name: row.get("name")?,
+                    default_amount: row.get("default_amount")?,
+                    default_unit: row.get("default_unit")?,
+                    ..Default::default()
+                };
+                Ok((serving, row.get::<_, usize>("log_entries")?))
+            })?
+            .collect::<Result<Vec<_>, RusqliteError>>()?;
+        drop(statement);
+        let removed = stored.into_iter().filter(|(serving, _)| {
+            !ingredient
+                .nutritional_info
+                .iter()
+                .any(|nutritional_info| nutritional_info.id == serving.id)
+        });
+        for (serving, log_entries) in removed {
+            if log_entries > 0 {
+                return Err(DatabaseError::ServingInUse {
+                    serving: serving.serving_name(),
+                    log_entries,
+                });
+            }
+            transaction.execute("DELETE FROM nutritional_info WHERE id = ?1;", [serving.id])?;
+        }
+        for nutritional_info in &ingredient.nutritional_info {
+            if !update_nutritional_info(&transaction, ingredient.id, nutritional_info)? {
+                insert_nutritional_info(&transaction, ingredient.id, nutritional_info)?;
+            }
+        }
 
-                INSERT INTO mineral_sets (
-                    nutrition_info_id,
-                    calcium, copper, iron, magnesium, manganese,
-                    phosphorus, potassium, selenium, sodium, zinc
-                )
-                VALUES ((SELECT value FROM _variables WHERE var_name = 'nutritional_info_id' LIMIT 1),
-                    {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1}, {:.1});
+        transaction.commit()?;
+        self.history.push(command.finish(&connection)?);
 
-                DROP TABLE IF EXISTS _variables;
+        Ok(())
+    }
 
-                COMMIT;
-                ",
-                nutritional_info.default_amount,
-                nutritional_info.default_unit as u8,
-                nutritional_info.kilocalories,
-                nutritional_info.macronutrients.proteins.essential_amino_acids.histidine,
-                nutritional_info.macronutrients.proteins.essential_amino_acids.isoleucine,
-                nutritional_info.macronutrients.proteins.essential_amino_acids.leucine,
-                nutritional_info.macronutrients.proteins.essential_amino_acids.lysine,
-                nutritional_info.macronutrients.proteins.essential_amino_acids.methionine,
-                nutritional_info.macronutrients.proteins.essential_amino_acids.phenylalanine,
-                nutritional_info.macronutrients.proteins.essential_amino_acids.threonine,
-                nutritional_info.macronutrients.proteins.essential_amino_acids.tryptophan,
-                nutritional_info.macronutrients.proteins.essential_amino_acids.valine,
-                nutritional_info.macronutrients.proteins.non_essential_amino_acids.alanine,
-                nutritional_info.macronutrients.proteins.non_essential_amino_acids.arginine,
-                nutritional_info.macronutrients.proteins.non_essential_amino_acids.asparagine,
-                nutritional_info.macronutrients.proteins.non_essential_amino_acids.aspartic_acid,
-                nutritional_info.macronutrients.proteins.non_essential_amino_acids.cysteine,
-                nutritional_info.macronutrients.proteins.non_essential_amino_acids.glutamic_acid,
-                nutritional_info.macronutrients.proteins.non_essential_amino_acids.glutamine,
-                nutritional_info.macronutrients.proteins.non_essential_amino_acids.glycine,
-                nutritional_info.macronutrients.proteins.non_essential_amino_acids.proline,
-                nutritional_info.macronutrients.proteins.non_essential_amino_acids.serine,
-                nutritional_info.macronutrients.proteins.non_essential_amino_acids.tyrosine,
-                nutritional_info.macronutrients.fats.saturated,
-                nutritional_info.macronutrients.fats.monounsaturated,
-                nutritional_info.macronutrients.fats.polyunsaturated,
-                nutritional_info.macronutrients.carbohydrates.starch,
-                nutritional_info.macronutrients.carbohydrates.fiber,
-                nutritional_info.macronutrients.carbohydrates.sugars,
-                nutritional_info.macronutrients.carbohydrates.sugar_alcohols,
-                nutritional_info.micronutrients.vitamins.vitamin_a,
-                nutritional_info.micronutrients.vitamins.vitamin_b1,
-                nutritional_info.micronutrients.vitamins.vitamin_b2,
-                nutritional_info.micronutrients.vitamins.vitamin_b3,
-                nutritional_info.micronutrients.vitamins.vitamin_b5,
-                nutritional_info.micronutrients.vitamins.vitamin_b6,
-                nutritional_info.micronutrients.vitamins.vitamin_b9,
-                nutritional_info.micronutrients.vitamins.vitamin_b12,
-                nutritional_info.micronutrients.vitamins.vitamin_c,
-                nutritional_info.micronutrients.vitamins.vitamin_d,
-                nutritional_info.micronutrients.vitamins.vitamin_e,
-                nutritional_info.micronutrients.vitamins.vitamin_k,
-                nutritional_info.micronutrients.vitamins.betaine,
-                nutritional_info.micronutrients.vitamins.choline,
-                nutritional_info.micronutrients.minerals.calcium,
-                nutritional_info.micronutrients.minerals.copper,
-                nutritional_info.micronutrients.minerals.iron,
-                nutritional_info.micronutrients.minerals.magnesium,
-                nutritional_info.micronutrients.minerals.manganese,
-                nutritional_info.micronutrients.minerals.phosphorus,
-                nutritional_info.micronutrients.minerals.potassium,
-                nutritional_info.micronutrients.minerals.selenium,
-                nutritional_info.micronutrients.minerals.sodium,
-                nutritional_info.micronutrients.minerals.zinc
-            );
-
-            let _ = self
-                .db_connection
-                .as_ref()
-                .unwrap()
-                .execute_batch(&nutritional_info_insert_query);
+    pub fn get_ingredient_by_id(&mut self, id: u32) -> Result<Rc<Ingredient>, DatabaseError> {
+        let mut ingredients = self.query_ingredients("WHERE ing.id = ?1", [id])?;
+        match ingredients.pop() {
+            Some(ingredient) => Ok(ingredient),
+            None => Err(DatabaseError::MissingRow(format!(
+                "ingredient with id {}",
+                id
+            ))),
         }
     }
 
-    pub fn get_ingredient_by_id(&mut self, id: u32) -> Vec<Rc<Ingredient>> {
-        self.start_connection();
-
-        let query = format!(
-            "
-            SELECT
-                ing.id, name, brand,
-                default_amount, default_unit, kilocalories,
-                --essentials
-                histidine, isoleucine, leucine, lysine, methionine, phenylalanine,
-                threonine, tryptophan, valine,
-                --non-essentials
-                alanine, arginine, asparagine, aspartic_acid, cysteine, glutamic_acid, glutamine,
-                glycine, proline, serine, tyrosine,
-                --fats
-                saturated, monounsaturated, polyunsaturated,
-                --carbohydrates
-                starch, fiber, sugars, sugar_alcohols,
-                --vitamins
-                vitamin_a, vitamin_b1, vitamin_b2, vitamin_b3, vitamin_b5, vitamin_b6, vitamin_b9,
-                vitamin_b12, vitamin_c, vitamin_d, vitamin_e, vitamin_k, betaine, choline,
-                --minerals
-                calcium, copper, iron, magnesium, manganese,
-                phosphorus, potassium, selenium, sodium, zinc
-            FROM ingredients ing
-            INNER JOIN nutritional_info ni
-                ON ing.id = ni.ingredient_id
-            INNER JOIN protein_sets ps
-                ON ni.id = ps.nutrition_info_id
-            INNER JOIN fat_sets fs
-                ON ni.id = fs.nutrition_info_id
-            INNER JOIN carbohydrate_sets cs
-                ON ni.id = cs.nutrition_info_id
-            INNER JOIN vitamin_sets vs
-                ON ni.id = vs.nutrition_info_id
-            INNER JOIN mineral_sets ms
-                ON ni.id = ms.nutrition_info_id
-            WHERE ing.id = {} LIMIT 1;
-            ",
-            id
-        );
+    // Finds the ingredient with `barcode`, archived or not, ignoring leading zeros.
+    pub fn get_ingredient_by_barcode(
+        &mut self,
+        barcode: &str,
+    ) -> Result<Option<Rc<Ingredient>>, DatabaseError> {
+        let mut ingredients =
+            self.query_ingredients("WHERE ltrim(ing.barcode, '0') = ltrim(?1, '0')", [barcode])?;
+        Ok(ingredients.pop())
+    }
 
-        let mut statement = self
-            .db_connection
-            .as_ref()
-            .unwrap()
-            .prepare(&query)
-            .unwrap();
-        let ingredients_iter = statement
-            .query_map([], |row| {
-                let categories: Vec<Category> = {
-                    let sql = format!(
-                        "
-                        SELECT id, name, icon_name, icon_color
-                        FROM categories c
-                        INNER JOIN ingredient_categories ic
-                            ON ic.category_id = c.id
-                            AND ic.ingredient_id = {};
-                        ",
-                        &row.get::<usize, u32>(0).unwrap()
-                    );
-                    let mut category_statement =
-                        self.db_connection.as_ref().unwrap().prepare(&sql).unwrap();
-                    let categories_iter = category_statement
-                        .query_map([], |category_row| {
-                            Ok(Category {
-                                id: category_row.get("id")?,
-                                name: category_row.get("name")?,
-                                icon_name: category_row.get("icon_name")?,
-                                icon_color: Color32::from_hex(
-                                    &category_row
-                                        .get::<&str, String>("icon_color")
-                                        .expect("Could not parse hex value into color!"),
-                                )
-                                .unwrap(),
-                            })
-                        })
-                        .unwrap();
+    // Finds an ingredient, archived or not, that `ingredient` would duplicate: one with
+    // the same barcode or, failing that, the same name and brand ignoring case.
+    pub fn find_duplicate_ingredient(
+        &mut self,
+        ingredient: &Ingredient,
+    ) -> Result<Option<Rc<Ingredient>>, DatabaseError> {
+        if let Some(barcode) = &ingredient.barcode {
+            if let Some(existing) = self.get_ingredient_by_barcode(barcode)? {
+                return Ok(Some(existing));
+            }
+        }
+        let mut ingredients = self.query_ingredients(
+            "WHERE lower(trim(ing.name)) = lower(trim(?1))
+                AND lower(trim(ing.brand)) = lower(trim(?2))",
+            [&ingredient.name, &ingredient.brand],
+        )?;
+        Ok(ingredients.pop())
+    }
 
-                    let mut data: Vec<Category> = Vec::new();
-                    for category in categories_iter {
-                        data.push(category.unwrap());
-                    }
+    // Returns the ingredients that are not archived.
+    pub fn get_ingredients(&mut self) -> Result<Vec<Rc<Ingredient>>, DatabaseError> {
+        self.query_ingredients("WHERE ing.archived = 0", [])
+    }
 
-                    data
-                };
-                let nutritional_info: NutritionalInfo = {
-                    NutritionalInfo {
-                        default_amount: row.get("default_amount")?,
-                        default_unit: Unit::from_uint(row.get("default_unit")?),
-                        kilocalories: row.get("kilocalories")?,
-                        macronutrients: Macronutrients {
-                            proteins: Proteins {
-                                essential_amino_acids: EssentialAminoAcids {
-                                    histidine: row.get("histidine")?,
-                                    isoleucine: row.get("isoleucine")?,
-                                    leucine: row.get("leucine")?,
-                                    lysine: row.get("lysine")?,
-                                    methionine: row.get("methionine")?,
-                                    phenylalanine: row.get("phenylalanine")?,
-                                    threonine: row.get("threonine")?,
-                                    tryptophan: row.get("tryptophan")?,
-                                    valine: row.get("valine")?,
-                                },
-                                non_essential_amino_acids: NonEssentialAminoAcids {
-                                    alanine: row.get("alanine")?,
-                                    arginine: row.get("arginine")?,
-                                    asparagine: row.get("asparagine")?,
-                                    aspartic_acid: row.get("aspartic_acid")?,
-                                    cysteine: row.get("cysteine")?,
-                                    glutamic_acid: row.get("glutamic_acid")?,
-                                    glutamine: row.get("glutamine")?,
-                                    glycine: row.get("glycine")?,
-                                    proline: row.get("proline")?,
-                                    serine: row.get("serine")?,
-                                    tyrosine: row.get("tyrosine")?,
-                                },
-                            },
-                            fats: Fats {
-                                saturated: row.get("saturated")?,
-                                monounsaturated: row.get("monounsaturated")?,
-                                polyunsaturated: row.get("polyunsaturated")?,
-                            },
-                            carbohydrates: Carbohydrates {
-                                starch: row.get("starch")?,
-                                fiber: row.get("fiber")?,
-                                sugars: row.get("sugars")?,
-                                sugar_alcohols: row.get("sugar_alcohols")?,
-                            },
-                        },
-                        micronutrients: Micronutrients {
-                            vitamins: Vitamins {
-                                vitamin_a: row.get("vitamin_a")?,
-                                vitamin_b1: row.get("vitamin_b1")?,
-                                vitamin_b2: row.get("vitamin_b2")?,
-                                vitamin_b3: row.get("vitamin_b3")?,
-                                vitamin_b5: row.get("vitamin_b5")?,
-                                vitamin_b6: row.get("vitamin_b6")?,
-                                vitamin_b9: row.get("vitamin_b9")?,
-                                vitamin_b12: row.get("vitamin_b12")?,
-                                vitamin_c: row.get("vitamin_c")?,
-                                vitamin_d: row.get("vitamin_d")?,
-                                vitamin_e: row.get("vitamin_e")?,
-                                vitamin_k: row.get("vitamin_k")?,
-                                betaine: row.get("betaine")?,
-                                choline: row.get("choline")?,
-                            },
-                            minerals: Minerals {
-                                calcium: row.get("calcium")?,
-                                copper: row.get("copper")?,
-                                iron: row.get("iron")?,
-                                magnesium: row.get("magnesium")?,
-                                manganese: row.get("manganese")?,
-                                phosphorus: row.get("phosphorus")?,
-                                potassium: row.get("potassium")?,
-                                selenium: row.get("selenium")?,
-                                sodium: row.get("sodium")?,
-                                zinc: row.get("zinc")?,
-                            },
-                        },
-                    }
-                };
-                Ok(Ingredient {
-                    id: row.get("id")?,
-                    name: row.get("name")?,
-                    brand: row.get("brand")?,
-                    categories,
-                    nutritional_info: vec![nutritional_info],
-                })
-            })
-            .unwrap();
+    pub fn get_archived_ingredients(&mut self) -> Result<Vec<Rc<Ingredient>>, DatabaseError> {
+        self.query_ingredients("WHERE ing.archived = 1", [])
+    }
 
-        let mut data = Vec::new();
-        for ingredient in ingredients_iter {
-            data.push(Rc::new(ingredient.unwrap()));
-            break;
+    // The query yields one row per nutritional info, rows of the same
+    // ingredient are collected into a single `Ingredient`.
+    fn query_ingredients(
+        &mut self,
+        condition: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<Rc<Ingredient>>, DatabaseError> {
+        let connection = self.start_connection()?;
+
+        let query = format!("{} {} ORDER BY ing.id, ni.id;", INGREDIENT_QUERY, condition);
+        let mut statement = connection.prepare(&query)?;
+        let mut rows = statement.query(params)?;
+
+        let mut data: Vec<Ingredient> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let id: u32 = row.get("id")?;
+            match data.last_mut() {
+                Some(ingredient) if ingredient.id == id => ingredient
+                    .nutritional_info
+                    .push(nutritional_info_from_row(row)?),
+                _ => data.push(ingredient_from_row(&connection, row)?),
+            }
         }
 
-        data
+        Ok(data.into_iter().map(Rc::new).collect())
     }
 
-    pub fn get_ingredients(&mut self) -> Vec<Rc<Ingredient>> {
-        self.start_connection();
+    // Hides the ingredient from lists while keeping the log entries that use it.
+    pub fn set_ingredient_archived(
+        &mut self,
+        ingredient: &Ingredient,
+        archived: bool,
+    ) -> Result<(), DatabaseError> {
+        let connection = self.start_connection()?;
+        let command = Command::begin(
+            &connection,
+            format!(
+                "{} ingredient \"{}\"",
+                if archived { "archive" } else { "restore" },
+                ingredient.name
+            ),
+            vec![Scope::new("ingredients", format!("id = {}", ingredient.id))],
+        )?;
+
+        connection.execute(
+            "UPDATE ingredients SET archived = ?1 WHERE id = ?2;",
+            rusqlite::params![archived, ingredient.id],
+        )?;
+
+        self.history.push(command.finish(&connection)?);
+
+        Ok(())
+    }
 
-        let query = "
-            SELECT
-                ing.id, name, brand,
-                default_amount, default_unit, kilocalories,
-                --essentials
-                histidine, isoleucine, leucine, lysine, methionine, phenylalanine,
-                threonine, tryptophan, valine,
-                --non-essentials
-                alanine, arginine, asparagine, aspartic_acid, cysteine, glutamic_acid, glutamine,
-                glycine, proline, serine, tyrosine,
-                --fats
-                saturated, monounsaturated, polyunsaturated,
-                --carbohydrates
-                starch, fiber, sugars, sugar_alcohols,
-                --vitamins
-                vitamin_a, vitamin_b1, vitamin_b2, vitamin_b3, vitamin_b5, vitamin_b6, vitamin_b9,
-                vitamin_b12, vitamin_c, vitamin_d, vitamin_e, vitamin_k, betaine, choline,
-                --minerals
-                calcium, copper, iron, magnesium, manganese,
-                phosphorus, potassium, selenium, sodium, zinc
-            FROM ingredients ing
-            INNER JOIN nutritional_info ni
-                ON ing.id = ni.ingredient_id
-            INNER JOIN protein_sets ps
-                ON ni.id = ps.nutrition_info_id
-            INNER JOIN fat_sets fs
-                ON ni.id = fs.nutrition_info_id
-            INNER JOIN carbohydrate_sets cs
-                ON ni.id = cs.nutrition_info_id
-            INNER JOIN vitamin_sets vs
-                ON ni.id = vs.nutrition_info_id
-            INNER JOIN mineral_sets ms
-                ON ni.id = ms.nutrition_info_id;
-            ";
+    // The number of log entries of each serving of `ingredient`, by serving id.
+    pub fn get_serving_usage(
+        &mut self,
+        ingredient: &Ingredient,
+    ) -> Result<HashMap<u32, usize>, DatabaseError> {
+        let connection = self.start_connection()?;
 
-        let mut statement = self.db_connection.as_ref().unwrap().prepare(query).unwrap();
-        let ingredients_iter = statement
-            .query_map([], |row| {
-                let categories: Vec<Category> = {
-                    let sql = format!(
-                        "
-                        SELECT id, name, icon_name, icon_color
-                        FROM categories c
-                        INNER JOIN ingredient_categories ic
-                            ON ic.category_id = c.id
-                            AND ic.ingredient_id = {};
-                        ",
-                        &row.get::<usize, u32>(0).unwrap()
-                    );
-                    let mut category_statement =
-                        self.db_connection.as_ref().unwrap().prepare(&sql).unwrap();
-                    let categories_iter = category_statement
-                        .query_map([], |category_row| {
-                            Ok(Category {
-                                id: category_row.get("id")?,
-                                name: category_row.get("name")?,
-                                icon_name: category_row.get("icon_name")?,
-                                icon_color: Color32::from_hex(
-                                    &category_row
-                                        .get::<&str, String>("icon_color")
-                                        .expect("Could not parse hex value into color!"),
-                                )
-                                .unwrap(),
-                            })
-                        })
-                        .unwrap();
+        let mut statement = connection.prepare(
+            "
+            SELECT nutritional_info_id, COUNT(*) FROM daily_logs
+            WHERE ingredient_id = ?1 AND nutritional_info_id IS NOT NULL
+            GROUP BY nutritional_info_id;
+            ",
+        )?;
+        let usage = statement
+            .query_map([ingredient.id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<u32, usize>, RusqliteError>>()?;
 
-                    let mut data: Vec<Category> = Vec::new();
-                    for category in categories_iter {
-                        data.push(category.unwrap());
-                    }
+        Ok(usage)
+    }
 
-                    data
-                };
-                let nutritional_info: NutritionalInfo = {
-                    NutritionalInfo {
-                        default_amount: row.get("default_amount")?,
-                        default_unit: Unit::from_uint(row.get("default_unit")?),
-                        kilocalories: row.get("kilocalories")?,
-                        macronutrients: Macronutrients {
-                            proteins: Proteins {
-                                essential_amino_acids: EssentialAminoAcids {
-                                    histidine: row.get("histidine")?,
-                                    isoleucine: row.get("isoleucine")?,
-                                    leucine: row.get("leucine")?,
-                                    lysine: row.get("lysine")?,
-                                    methionine: row.get("methionine")?,
-                                    phenylalanine: row.get("phenylalanine")?,
-                                    threonine: row.get("threonine")?,
-                                    tryptophan: row.get("tryptophan")?,
-                                    valine: row.get("valine")?,
-                                },
-                                non_essential_amino_acids: NonEssentialAminoAcids {
-                                    alanine: row.get("alanine")?,
-                                    arginine: row.get("arginine")?,
-                                    asparagine: row.get("asparagine")?,
-                                    aspartic_acid: row.get("aspartic_acid")?,
-                                    cysteine: row.get("cysteine")?,
-                                    glutamic_acid: row.get("glutamic_acid")?,
-                                    glutamine: row.get("glutamine")?,
-                                    glycine: row.get("glycine")?,
-                                    proline: row.get("proline")?,
-                                    serine: row.get("serine")?,
-                                    tyrosine: row.get("tyrosine")?,
-                                },
-                            },
-                            fats: Fats {
-                                saturated: row.get("saturated")?,
-                                monounsaturated: row.get("monounsaturated")?,
-                                polyunsaturated: row.get("polyunsaturated")?,
-                            },
-                            carbohydrates: Carbohydrates {
-                                starch: row.get("starch")?,
-                                fiber: row.get("fiber")?,
-                                sugars: row.get("sugars")?,
-                                sugar_alcohols: row.get("sugar_alcohols")?,
-                            },
-                        },
-                        micronutrients: Micronutrients {
-                            vitamins: Vitamins {
-                                vitamin_a: row.get("vitamin_a")?,
-                                vitamin_b1: row.get("vitamin_b1")?,
-                                vitamin_b2: row.get("vitamin_b2")?,
-                                vitamin_b3: row.get("vitamin_b3")?,
-                                vitamin_b5: row.get("vitamin_b5")?,
-                                vitamin_b6: row.get("vitamin_b6")?,
-                                vitamin_b9: row.get("vitamin_b9")?,
-                                vitamin_b12: row.get("vitamin_b12")?,
-                                vitamin_c: row.get("vitamin_c")?,
-                                vitamin_d: row.get("vitamin_d")?,
-                                vitamin_e: row.get("vitamin_e")?,
-                                vitamin_k: row.get("vitamin_k")?,
-                                betaine: row.get("betaine")?,
-                                choline: row.get("choline")?,
-                            },
-                            minerals: Minerals {
-                                calcium: row.get("calcium")?,
-                                copper: row.get("copper")?,
-                                iron: row.get("iron")?,
-                                magnesium: row.get("magnesium")?,
-                                manganese: row.get("manganese")?,
-                                phosphorus: row.get("phosphorus")?,
-                                potassium: row.get("potassium")?,
-                                selenium: row.get("selenium")?,
-                                sodium: row.get("sodium")?,
-                                zinc: row.get("zinc")?,
-                            },
-                        },
-                    }
-                };
-                Ok(Ingredient {
-                    id: row.get("id")?,
-                    name: row.get("name")?,
-                    brand: row.get("brand")?,
-                    categories,
-                    nutritional_info: vec![nutritional_info],
-                })
-            })
-            .unwrap();
+    pub fn get_ingredient_usage(
+        &mut self,
+        ingredient: &Ingredient,
+    ) -> Result<IngredientUsage, DatabaseError> {
+        let connection = self.start_connection()?;
+
+        let log_entries: usize = connection.query_row(
+            "SELECT COUNT(*) FROM daily_logs WHERE ingredient_id = ?1;",
+            [ingredient.id],
+            |row| row.get(0),
+        )?;
+        let recipes: usize = connection.query_row(
+            "SELECT COUNT(DISTINCT recipe_id) FROM recipe_items WHERE ingredient_id = ?1;",
+            [ingredient.id],
+            |row| row.get(0),
+        )?;
+
+        Ok(IngredientUsage {
+            log_entries,
+            recipes,
+        })
+    }
 
-        let mut data: Vec<Rc<Ingredient>> = Vec::new();
-        for ingredient in ingredients_iter {
-            data.push(Rc::new(ingredient.unwrap()));
+    pub fn delete_ingredient(&mut self, ingredient: &Ingredient) -> Result<usize, DatabaseError> {
+        self.delete_ingredients(std::slice::from_ref(ingredient))
+    }
+
+    pub fn delete_ingredients(
+        &mut self,
+        ingredients: &[Ingredient],
+    ) -> Result<usize, DatabaseError> {
+        let connection = self.start_connection()?;
+        let ids: Vec<u32> = ingredients.iter().map(|ingredient| ingredient.id).collect();
+        let command = Command::begin(
+            &connection,
+            match ingredients {
+                [ingredient] => format!("delete ingredient \"{}\"", ingredient.name),
+                _ => format!("delete {} ingredients", ingredients.len()),
+            },
+            ingredient_scopes(&ids),
+        )?;
+
+        let transaction = connection.unchecked_transaction()?;
+        let mut row_count: usize = 0;
+        {
+            let mut delete_statement =
+                transaction.prepare("DELETE FROM ingredients WHERE id = ?1;")?;
+            for id in &ids {
+                row_count += delete_statement.execute([id])?;
+            }
         }
 
-        data
+        transaction.commit()?;
+        self.history.push(command.finish(&connection)?);
+
+        Ok(row_count)
     }
 
-    pub fn delete_ingredient(&mut self, ingredient: &Ingredient) -> Result<usize, RusqliteError> {
-        self.start_connection();
+    pub fn insert_log_entry(
+        &mut self,
+        date: &NaiveDate,
+        log_entry: &LogEntry,
+    ) -> Result<(), DatabaseError> {
+        let connection = self.start_connection()?;
 
-        let ingredient_delete_query = format!(
+        let (ingredient_id, recipe_id) = log_item_ids(&log_entry.item);
+        let mut statement = connection.prepare(
             "
-            DELETE FROM ingredients
-            WHERE id = {}
+            INSERT INTO daily_logs (
+                date, ingredient_id, recipe_id, amount, unit, meal_id, time, nutritional_info_id
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);
             ",
-            ingredient.id
-        );
-        let mut delete_statement: Statement = self
-            .db_connection
-            .as_ref()
-            .unwrap()
-            .prepare(&ingredient_delete_query)
-            .expect("Failed to prepare delete statement!");
-        delete_statement.execute([])
+        )?;
+        let id = statement.insert(rusqlite::params![
+            date,
+            ingredient_id,
+            recipe_id,
+            log_entry.amount,
+            log_entry.unit,
+            log_entry.meal.as_ref().map(|meal| meal.id),
+            log_entry.time,
+            log_entry.serving
+        ])?;
+
+        self.history.push(Command::inserted(
+            &connection,
+            format!("add log entry \"{}\"", log_entry.item.name()),
+            log_entry_scopes(&[id as u32]),
+        )?);
+
+        Ok(())
     }
 
-    pub fn delete_ingredients(
+    pub fn update_log_entry(
         &mut self,
-        ingredients: &[Ingredient],
-    ) -> Result<usize, RusqliteError> {
-        self.start_connection();
+        date: &NaiveDate,
+        log_entry: &LogEntry,
+    ) -> Result<(), DatabaseError> {
+        let connection = self.start_connection()?;
+        let command = Command::begin(
+            &connection,
+            format!("edit log entry \"{}\"", log_entry.item.name()),
+            log_entry_scopes(&[log_entry.id]),
+        )?;
+
+        let (ingredient_id, recipe_id) = log_item_ids(&log_entry.item);
+        let row_count = connection.execute(
+            "
+            UPDATE daily_logs
+            SET date = ?1, ingredient_id = ?2, recipe_id = ?3, amount = ?4, unit = ?5,
+                meal_id = ?6, time = ?7, nutritional_info_id = ?8
+            WHERE id = ?9;
+            ",
+            rusqlite::params![
+                date,
+                ingredient_id,
+                recipe_id,
+                log_entry.amount,
+                log_entry.unit,
+                log_entry.meal.as_ref().map(|meal| meal.id),
+                log_entry.time,
+                log_entry.serving,
+                log_entry.id
+            ],
+        )?;
+        if row_count == 0 {
+            return Err(DatabaseError::MissingRow(format!(
+                "log entry with id {}",
+                log_entry.id
+            )));
+        }
 
-        let mut row_count: usize = 0;
+        self.history.push(command.finish(&connection)?);
 
-        for ingredient in ingredients {
-            match self.delete_ingredient(ingredient) {
-                Ok(value) => row_count += value,
-                Err(error) => return Err(error),
+        Ok(())
+    }
+
+    pub fn delete_log_entries(&mut self, log_entries: &[LogEntry]) -> Result<usize, DatabaseError> {
+        let connection = self.start_connection()?;
+        let ids: Vec<u32> = log_entries.iter().map(|log_entry| log_entry.id).collect();
+        let command = Command::begin(
+            &connection,
+            match log_entries {
+                [log_entry] => format!("delete log entry \"{}\"", log_entry.item.name()),
+                _ => format!("delete {} log entries", log_entries.len()),
+            },
+            log_entry_scopes(&ids),
+        )?;
+
+        let transaction = connection.unchecked_transaction()?;
+        let mut row_count: usize = 0;
+        {
+            let mut delete_statement =
+                transaction.prepare("DELETE FROM daily_logs WHERE id = ?1;")?;
+            for id in &ids {
+                row_count += delete_statement.execute([id])?;
             }
         }
 
+        transaction.commit()?;
+        self.history.push(command.finish(&connection)?);
+
         Ok(row_count)
     }
 
-    pub fn insert_log_entry(&mut self, date: &NaiveDate, log_entry: &LogEntry) {
-        self.start_connection();
+    pub fn get_log_entries(&mut self, date: &NaiveDate) -> Result<Vec<LogEntry>, DatabaseError> {
+        Ok(self
+            .query_log_entries("WHERE date = ?1", [date])?
+            .into_iter()
+            .map(|(_, log_entry)| log_entry)
+            .collect())
+    }
 
-        let mut statement = self
-            .db_connection
-            .as_ref()
-            .unwrap()
-            .prepare("INSERT INTO daily_logs (date, ingredient_id, fraction) VALUES (?1, ?2, ?3);")
-            .unwrap();
-        let _ = statement
-            .insert(rusqlite::params![
-                date,
-                log_entry.ingredient.id,
-                log_entry.fraction
-            ])
-            .unwrap();
+    // All log entries from `start` to `end`, both inclusive, with their dates.
+    pub fn get_log_entries_in_range(
+        &mut self,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<Vec<(NaiveDate, LogEntry)>, DatabaseError> {
+        self.query_log_entries("WHERE date BETWEEN ?1 AND ?2", [start, end])
     }
 
-    pub fn get_log_entries(&mut self, date: &NaiveDate) -> Vec<LogEntry> {
-        self.start_connection();
+    // Loads the log entries matching `condition` together with their dates.
+    // Ingredients and recipes shared by several entries are only loaded once.
+    fn query_log_entries(
+        &mut self,
+        condition: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<(NaiveDate, LogEntry)>, DatabaseError> {
+        let connection = self.start_connection()?;
 
         let query = format!(
             "
             SELECT
-                id, date, ingredient_id, fraction
-            FROM daily_logs WHERE date = '{}';
+                dl.id, date, ingredient_id, recipe_id, amount, unit, time,
+                nutritional_info_id, meal_id, m.name AS meal_name
+            FROM daily_logs dl
+            LEFT JOIN meals m
+                ON dl.meal_id = m.id
+            {}
+            ORDER BY date, dl.id;
             ",
-            date
+            condition
         );
 
-        let binding = self.db_connection.clone().unwrap();
-        let mut statement = binding.prepare(&query).unwrap();
-
-        let log_entries_iter = statement
-            .query_map([], |row| {
-                let ingredient: Rc<Ingredient> =
-                    self.get_ingredient_by_id(row.get("ingredient_id")?)[0].to_owned();
-                Ok(LogEntry {
+        let mut statement = connection.prepare(&query)?;
+        let mut rows = statement.query(params)?;
+
+        let mut ingredients: HashMap<u32, Rc<Ingredient>> = HashMap::new();
+        let mut recipes: HashMap<u32, Rc<Recipe>> = HashMap::new();
+        let mut data: Vec<(NaiveDate, LogEntry)> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let recipe_id: Option<u32> = row.get("recipe_id")?;
+            let item = match recipe_id {
+                Some(recipe_id) => LogItem::Recipe(match recipes.get(&recipe_id) {
+                    Some(recipe) => recipe.clone(),
+                    None => {
+                        let recipe = self.get_recipe_by_id(recipe_id)?;
+                        recipes.insert(recipe_id, recipe.clone());
+                        recipe
+                    }
+                }),
+                None => LogItem::Ingredient(
+                    self.cached_ingredient(&mut ingredients, row.get("ingredient_id")?)?,
+                ),
+            };
+            data.push((
+                row.get("date")?,
+                LogEntry {
                     id: row.get("id")?,
-                    ingredient,
-                    fraction: row.get("fraction")?,
-                })
-            })
-            .unwrap();
-
-        let mut data: Vec<LogEntry> = Vec::new();
-        for log_entry in log_entries_iter {
-            data.push(log_entry.unwrap());
+                    item,
+                    amount: row.get("amount")?,
+                    unit: row.get("unit")?,
+                    serving: row.get("nutritional_info_id")?,
+                    meal: meal_from_row(row)?,
+                    time: row.get("time")?,
+                },
+            ));
         }
 
-        data
+        Ok(data)
+    }
+
+    fn cached_ingredient(
+        &mut self,
+        cache: &mut HashMap<u32, Rc<Ingredient>>,
+        id: u32,
+    ) -> Result<Rc<Ingredient>, DatabaseError> {
+        if let Some(ingredient) = cache.get(&id) {
+            return Ok(ingredient.clone());
+        }
+        let ingredient = self.get_ingredient_by_id(id)?;
+        cache.insert(id, ingredient.clone());
+        Ok(ingredient)
     }
 
-    pub fn get_log_entry_dates(&mut self) -> HashSet<NaiveDate> {
-        self.start_connection();
+    pub fn get_log_entry_dates(&mut self) -> Result<HashSet<NaiveDate>, DatabaseError> {
+        let connection = self.start_connection()?;
 
         let query = "
             SELECT
@@ -934,16 +744,2091 @@ impl Database {
             FROM daily_logs;
             ";
 
-        let binding = self.db_connection.clone().unwrap();
-        let mut statement = binding.prepare(&query).unwrap();
-
-        let log_entries_iter = statement.query_map([], |row| Ok(row.get("date")?)).unwrap();
+        let mut statement = connection.prepare(query)?;
+        let log_entries_iter = statement.query_map([], |row| row.get("date"))?;
 
         let mut data: HashSet<NaiveDate> = HashSet::new();
         for date in log_entries_iter {
-            data.insert(date.expect("Failed to parse NaiveDate!"));
+            data.insert(date?);
+        }
+
+        Ok(data)
+    }
+
+    pub fn insert_meal(&mut self, name: &str) -> Result<Meal, DatabaseError> {
+        let connection = self.start_connection()?;
+
+        let mut statement = connection.prepare(
+            "
+            INSERT INTO meals (name, position)
+            VALUES (?1, (SELECT IFNULL(MAX(position) + 1, 0) FROM meals));
+            ",
+        )?;
+        let id = statement.insert([name])?;
+
+        self.history.push(Command::inserted(
+            &connection,
+            format!("add meal \"{}\"", name),
+            vec![Scope::new("meals", format!("id = {}", id))],
+        )?);
+
+        Ok(Meal {
+            id: id as u32,
+            name: name.to_owned(),
+        })
+    }
+
+    pub fn get_meals(&mut self) -> Result<Vec<Meal>, DatabaseError> {
+        let connection = self.start_connection()?;
+
+        let mut statement =
+            connection.prepare("SELECT id, name FROM meals ORDER BY position, id")?;
+        let meals_iter = statement.query_map([], |row| {
+            Ok(Meal {
+                id: row.get("id")?,
+                name: row.get("name")?,
+            })
+        })?;
+
+        let mut data: Vec<Meal> = Vec::new();
+        for meal in meals_iter {
+            data.push(meal?);
+        }
+
+        Ok(data)
+    }
+
+    // Sums the calories logged per day from `start` to `end`, both inclusive, computed
+    // the same way as `LogEntry::nutrients`.
+    pub fn get_daily_calories(
+        &mut self,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<HashMap<NaiveDate, f32>, DatabaseError> {
+        let mut data: HashMap<NaiveDate, f32> = HashMap::new();
+        for (date, log_entry) in self.get_log_entries_in_range(start, end)? {
+            *data.entry(date).or_default() += log_entry.nutrients().kilocalories;
+        }
+
+        Ok(data)
+    }
+
+    // The nutrients logged on each day from `start` to `end`, both inclusive.
+    // Days without log entries are left out.
+    //
+    // This sums in Rust rather than aggregating in SQL: which nutritional info an
+    // amount scales and by how much depends on the unit, the serving, densities,
+    // piece weights and for recipes on every item, see `Ingredient::scale_serving`
+    // and `Recipe::nutrients_per_serving`. Repeating that in SQL would let the two
+    // drift apart. Each ingredient and recipe is only loaded once per range.
+    pub fn get_daily_totals(
+        &mut self,
+        start: &NaiveDate,
+        end: &NaiveDate,
+    ) -> Result<HashMap<NaiveDate, NutrientTotals>, DatabaseError> {
+        let mut data: HashMap<NaiveDate, NutrientTotals> = HashMap::new();
+        for (date, log_entry) in self.get_log_entries_in_range(start, end)? {
+            data.entry(date).or_default().add_log_entry(&log_entry);
+        }
+
+        Ok(data)
+    }
+
+    pub fn insert_recipe(&mut self, recipe: &Recipe) -> Result<u32, DatabaseError> {
+        let connection = self.start_connection()?;
+        let transaction = connection.unchecked_transaction()?;
+
+        transaction.execute(
+            "INSERT INTO recipes (name, servings) VALUES (?1, ?2);",
+            rusqlite::params![recipe.name, recipe.servings],
+        )?;
+        let recipe_id = transaction.last_insert_rowid() as u32;
+        insert_recipe_items(&transaction, recipe_id, &recipe.items)?;
+
+        transaction.commit()?;
+
+        self.history.push(Command::inserted(
+            &connection,
+            format!("add recipe \"{}\"", recipe.name),
+            recipe_scopes(recipe_id),
+        )?);
+
+        Ok(recipe_id)
+    }
+
+    pub fn update_recipe(&mut self, recipe: &Recipe) -> Result<(), DatabaseError> {
+        let connection = self.start_connection()?;
+        let command = Command::begin(
+            &connection,
+            format!("edit recipe \"{}\"", recipe.name),
+            recipe_scopes(recipe.id),
+        )?;
+        let transaction = connection.unchecked_transaction()?;
+
+        let row_count = transaction.execute(
+            "UPDATE recipes SET name = ?1, servings = ?2 WHERE id = ?3;",
+            rusqlite::params![recipe.name, recipe.servings, recipe.id],
+        )?;
+        if row_count == 0 {
+            return Err(DatabaseError::MissingRow(format!(
+                "recipe with id {}",
+                recipe.id
+            )));
+        }
+        transaction.execute(
+            "DELETE FROM recipe_items WHERE recipe_id = ?1;",
+            [recipe.id],
+        )?;
+        insert_recipe_items(&transaction, recipe.id, &recipe.items)?;
+
+        transaction.commit()?;
+        self.history.push(command.finish(&connection)?);
+
+        Ok(())
+    }
+
+    pub fn get_recipe_by_id(&mut self, id: u32) -> Result<Rc<Recipe>, DatabaseError> {
+        let mut recipes = self.query_recipes("WHERE id = ?1", [id])?;
+        match recipes.pop() {
+            Some(recipe) => Ok(recipe),
+            None => Err(DatabaseError::MissingRow(format!("recipe with id {}", id))),
+        }
+    }
+
+    pub fn get_recipes(&mut self) -> Result<Vec<Rc<Recipe>>, DatabaseError> {
+        self.query_recipes("", [])
+    }
+
+    fn query_recipes(
+        &mut self,
+        condition: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<Rc<Recipe>>, DatabaseError> {
+        let connection = self.start_connection()?;
+
+        let query = format!(
+            "SELECT id, name, servings FROM recipes {} ORDER BY name, id;",
+            condition
+        );
+        let mut statement = connection.prepare(&query)?;
+        let mut rows = statement.query(params)?;
+        let mut item_statement = connection.prepare(
+            "SELECT ingredient_id, amount, unit FROM recipe_items WHERE recipe_id = ?1 ORDER BY id;",
+        )?;
+
+        let mut ingredients: HashMap<u32, Rc<Ingredient>> = HashMap::new();
+        let mut data: Vec<Rc<Recipe>> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let id: u32 = row.get("id")?;
+
+            let mut items: Vec<RecipeItem> = Vec::new();
+            let mut item_rows = item_statement.query([id])?;
+            while let Some(item_row) = item_rows.next()? {
+                items.push(RecipeItem {
+                    ingredient: self
+                        .cached_ingredient(&mut ingredients, item_row.get("ingredient_id")?)?,
+                    amount: item_row.get("amount")?,
+                    unit: item_row.get("unit")?,
+                });
+            }
+
+            data.push(Rc::new(Recipe {
+                id,
+                name: row.get("name")?,
+                servings: row.get("servings")?,
+                items,
+            }));
+        }
+
+        Ok(data)
+    }
+
+    pub fn delete_recipe(&mut self, recipe: &Recipe) -> Result<usize, DatabaseError> {
+        let connection = self.start_connection()?;
+        let command = Command::begin(
+            &connection,
+            format!("delete recipe \"{}\"", recipe.name),
+            recipe_scopes(recipe.id),
+        )?;
+
+        let row_count = connection.execute("DELETE FROM recipes WHERE id = ?1;", [recipe.id])?;
+
+        self.history.push(command.finish(&connection)?);
+
+        Ok(row_count)
+    }
+
+    pub fn get_goals(&mut self) -> Result<Vec<Goal>, DatabaseError> {
+        let connection = self.start_connection()?;
+
+        let mut statement = connection
+            .prepare("SELECT nutrient, weekday, target FROM goals ORDER BY nutrient, weekday")?;
+        let goals_iter = statement.query_map([], goal_from_row)?;
+
+        let mut data: Vec<Goal> = Vec::new();
+        for goal in goals_iter {
+            data.push(goal?);
         }
 
-        data
+        Ok(data)
+    }
+
+    // Stores `goal`, replacing the goal for the same nutrient and weekday if there is one.
+    pub fn set_goal(&mut self, goal: &Goal) -> Result<(), DatabaseError> {
+        let connection = self.start_connection()?;
+        let command = Command::begin(
+            &connection,
+            format!("set goal for {}", goal.nutrient),
+            goal_scopes(goal),
+        )?;
+
+        let transaction = connection.unchecked_transaction()?;
+        delete_goal(&transaction, goal)?;
+        transaction.execute(
+            "INSERT INTO goals (nutrient, weekday, target) VALUES (?1, ?2, ?3);",
+            rusqlite::params![
+                goal.nutrient,
+                goal.weekday.map(|weekday| weekday.num_days_from_monday()),
+                goal.target
+            ],
+        )?;
+        transaction.commit()?;
+
+        self.history.push(command.finish(&connection)?);
+
+        Ok(())
+    }
+
+    pub fn delete_goal(&mut self, goal: &Goal) -> Result<usize, DatabaseError> {
+        let connection = self.start_connection()?;
+        let command = Command::begin(
+            &connection,
+            format!("delete goal for {}", goal.nutrient),
+            goal_scopes(goal),
+        )?;
+
+        let row_count = delete_goal(&connection, goal)?;
+
+        self.history.push(command.finish(&connection)?);
+
+        Ok(row_count)
+    }
+
+    // The stored intake profile, or the default one if none was set yet.
+    pub fn get_intake_profile(&mut self) -> Result<IntakeProfile, DatabaseError> {
+        let connection = self.start_connection()?;
+
+        let profile = connection
+            .query_row(
+                "SELECT sex, age_group FROM intake_profile WHERE id = 1;",
+                [],
+                |row| {
+                    Ok(IntakeProfile {
+                        sex: row.get("sex")?,
+                        age: row.get("age_group")?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(profile.unwrap_or_default())
+    }
+
+    pub fn set_intake_profile(&mut self, profile: &IntakeProfile) -> Result<(), DatabaseError> {
+        let connection = self.start_connection()?;
+        let command = Command::begin(
+            &connection,
+            "set intake profile".to_owned(),
+            vec![Scope::new("intake_profile", "id = 1".to_owned())],
+        )?;
+
+        connection.execute(
+            "INSERT OR REPLACE INTO intake_profile (id, sex, age_group) VALUES (1, ?1, ?2);",
+            rusqlite::params![profile.sex, profile.age],
+        )?;
+
+        self.history.push(command.finish(&connection)?);
+
+        Ok(())
+    }
+
+    // Collects everything stored in the database, including archived ingredients.
+    pub fn get_backup(&mut self) -> Result<Backup, DatabaseError> {
+        Ok(Backup {
+            categories: self.get_categories()?,
+            ingredients: self.query_ingredients("", [])?,
+            meals: self.get_meals()?,
+            recipes: self.get_recipes()?,
+            log_entries: self.query_log_entries("", [])?,
+            goals: self.get_goals()?,
+            intake_profile: Some(self.get_intake_profile()?),
+        })
+    }
+
+    // Inserts the content of `backup` with new IDs. When merging, categories,
+    // meals and recipes with the same name, ingredients with the same name and
+    // brand and identical log entries and goals are reused instead of duplicated,
+    // and the intake profile is only set if there is none yet. Replacing inserts
+    // every row of the backup.
+    pub fn restore_backup(
+        &mut self,
+        backup: &Backup,
+        mode: RestoreMode,
+    ) -> Result<(), DatabaseError> {
+        let connection = self.start_connection()?;
+        let command = Command::begin(
+            &connection,
+            match mode {
+                RestoreMode::Merge => "merge backup".to_owned(),
+                RestoreMode::Replace => "restore backup".to_owned(),
+            },
+            BACKUP_TABLES
+                .iter()
+                .map(|table| Scope::new(table, "1".to_owned()))
+                .collect(),
+        )?;
+        let transaction = connection.unchecked_transaction()?;
+
+        if mode == RestoreMode::Replace {
+            // Children first, the nutrient sets go with their nutritional info.
+            for table in [
+                "daily_logs",
+                "recipe_items",
+                "recipes",
+                "goals",
+                "intake_profile",
+                "ingredient_categories",
+                "nutritional_info",
+                "ingredients",
+                "categories",
+                "meals",
+            ] {
+                transaction.execute(&format!("DELETE FROM {};", table), [])?;
+            }
+        }
+
+        // Replacing inserts every row as it is, the only rows to match would be
+        // the ones this restore inserted.
+        let existing_id = |query: &str, params: &[&dyn rusqlite::ToSql]| match mode {
+            RestoreMode::Merge => transaction
+                .query_row(query, params, |row| row.get::<_, u32>(0))
+                .optional(),
+            RestoreMode::Replace => Ok(None),
+        };
+
+        let mut category_ids: HashMap<u32, u32> = HashMap::new();
+        for category in &backup.categories {
+            let id = match existing_id(
+                "SELECT id FROM categories WHERE name = ?1;",
+                &[&category.name],
+            )? {
+                Some(id) => id,
+                None => {
+                    transaction.execute(
+                        "INSERT INTO categories (name, icon_name, icon_color) VALUES (?1, ?2, ?3);",
+                        rusqlite::params![
+                            category.name,
+                            category.icon_name,
+                            category.icon_color.to_hex()
+                        ],
+                    )?;
+                    transaction.last_insert_rowid() as u32
+                }
+            };
+            category_ids.insert(category.id, id);
+        }
+
+        let mut ingredient_ids: HashMap<u32, u32> = HashMap::new();
+        // By backup ingredient id and position, hand-written backups may leave
+        // out the serving ids.
+        let mut serving_ids: HashMap<(u32, usize), u32> = HashMap::new();
+        for ingredient in &backup.ingredients {
+            // An ingredient with the same barcode is the same product.
+            let id = match existing_id(
+                "
+                SELECT id FROM ingredients
+                WHERE (name = ?1 AND brand = ?2) OR ltrim(barcode, '0') = ltrim(?3, '0');
+                ",
+                &[&ingredient.name, &ingredient.brand, &ingredient.barcode],
+            )? {
+                Some(id) => id,
+                None => {
+                    let mut ingredient = Ingredient::clone(ingredient);
+                    for category in &mut ingredient.categories {
+                        category.id = category_ids[&category.id];
+                    }
+                    insert_ingredient(&transaction, &ingredient)?
+                }
+            };
+            ingredient_ids.insert(ingredient.id, id);
+
+            // Servings are matched by name, amount and unit, missing ones are added.
+            let mut statement = transaction.prepare(
+                "
+                SELECT id, name, default_amount, default_unit FROM nutritional_info
+                WHERE ingredient_id = ?1 ORDER BY id;
+                ",
+            )?;
+            let mut stored = statement
+                .query_map([id], |row| {
+                    Ok((
+                        row.get::<_, u32>("id")?,
+                        row.get::<_, String>("name")?,
+                        row.get::<_, f32>("default_amount")?,
+                        row.get::<_, Unit>("default_unit")?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, RusqliteError>>()?;
+            for (index, nutritional_info) in ingredient.nutritional_info.iter().enumerate() {
+                let matching = stored.iter().position(|(_, name, amount, unit)| {
+                    *name == nutritional_info.name
+                        && *amount == nutritional_info.default_amount
+                        && *unit == nutritional_info.default_unit
+                });
+                let stored_id = match matching {
+                    Some(index) => stored.remove(index).0,
+                    None => insert_nutritional_info(&transaction, id, nutritional_info)?,
+                };
+                serving_ids.insert((ingredient.id, index), stored_id);
+            }
+        }
+
+        let mut meal_ids: HashMap<u32, u32> = HashMap::new();
+        for meal in &backup.meals {
+            let id = match existing_id("SELECT id FROM meals WHERE name = ?1;", &[&meal.name])? {
+                Some(id) => id,
+                None => {
+                    transaction.execute(
+                        "
+                        INSERT INTO meals (name, position)
+                        VALUES (?1, (SELECT IFNULL(MAX(position) + 1, 0) FROM meals));
+                        ",
+                        [&meal.name],
+                    )?;
+                    transaction.last_insert_rowid() as u32
+                }
+            };
+            meal_ids.insert(meal.id, id);
+        }
+
+        let mut recipe_ids: HashMap<u32, u32> = HashMap::new();
+        for recipe in &backup.recipes {
+            let id = match existing_id("SELECT id FROM recipes WHERE name = ?1;", &[&recipe.name])?
+            {
+                Some(id) => id,
+                None => {
+                    transaction.execute(
+                        "INSERT INTO recipes (name, servings) VALUES (?1, ?2);",
+                        rusqlite::params![recipe.name, recipe.servings],
+                    )?;
+                    let id = transaction.last_insert_rowid() as u32;
+                    let items: Vec<RecipeItem> = recipe
+                        .items
+                        .iter()
+                        .map(|item| RecipeItem {
+                            ingredient: Rc::new(Ingredient {
+                                id: ingredient_ids[&item.ingredient.id],
+                                ..Ingredient::clone(&item.ingredient)
+                            }),
+                            ..item.clone()
+                        })
+                        .collect();
+                    insert_recipe_items(&transaction, id, &items)?;
+                    id
+                }
+            };
+            recipe_ids.insert(recipe.id, id);
+        }
+
+        // Each existing log entry can stand in for one identical entry of the backup.
+        let mut matched_log_entries: HashSet<u32> = HashSet::new();
+        for (date, log_entry) in &backup.log_entries {
+            let (ingredient_id, recipe_id, serving_id) = match &log_entry.item {
+                LogItem::Ingredient(ingredient) => (
+                    Some(ingredient_ids[&ingredient.id]),
+                    None,
+                    log_entry
+                        .serving
+                        .and_then(|serving| {
+                            ingredient
+                                .nutritional_info
+                                .iter()
+                                .position(|nutritional_info| nutritional_info.id == serving)
+                        })
+                        .and_then(|index| serving_ids.get(&(ingredient.id, index)).copied()),
+                ),
+                LogItem::Recipe(recipe) => (None, Some(recipe_ids[&recipe.id]), None),
+            };
+            let params = rusqlite::params![
+                date,
+                ingredient_id,
+                recipe_id,
+                log_entry.amount,
+                log_entry.unit,
+                log_entry.meal.as_ref().map(|meal| meal_ids[&meal.id]),
+                log_entry.time,
+                serving_id
+            ];
+
+            if mode == RestoreMode::Merge {
+                let mut statement = transaction.prepare(
+                    "
+                    SELECT id FROM daily_logs
+                    WHERE date = ?1 AND ingredient_id IS ?2 AND recipe_id IS ?3
+                        AND amount = ?4 AND unit = ?5 AND meal_id IS ?6 AND time IS ?7
+                        AND nutritional_info_id IS ?8;
+                    ",
+                )?;
+                let ids = statement
+                    .query_map(params, |row| row.get::<_, u32>(0))?
+                    .collect::<Result<Vec<u32>, RusqliteError>>()?;
+                if let Some(id) = ids.into_iter().find(|id| !matched_log_entries.contains(id)) {
+                    matched_log_entries.insert(id);
+                    continue;
+                }
+            }
+
+            transaction.execute(
+                "
+                INSERT INTO daily_logs (
+                    date, ingredient_id, recipe_id, amount, unit, meal_id, time,
+                    nutritional_info_id
+                )
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);
+                ",
+                params,
+            )?;
+            matched_log_entries.insert(transaction.last_insert_rowid() as u32);
+        }
+
+        // Goals already set for the same nutrient and weekday are kept.
+        for goal in &backup.goals {
+            transaction.execute(
+                "INSERT OR IGNORE INTO goals (nutrient, weekday, target) VALUES (?1, ?2, ?3);",
+                rusqlite::params![
+                    goal.nutrient,
+                    goal.weekday.map(|weekday| weekday.num_days_from_monday()),
+                    goal.target
+                ],
+            )?;
+        }
+
+        // Like goals, an intake profile that is already set is kept.
+        if let Some(profile) = &backup.intake_profile {
+            transaction.execute(
+                "INSERT OR IGNORE INTO intake_profile (id, sex, age_group) VALUES (1, ?1, ?2);",
+                rusqlite::params![profile.sex, profile.age],
+            )?;
+        }
+
+        transaction.commit()?;
+        self.history.push(command.finish(&connection)?);
+
+        Ok(())
+    }
+}
+
+fn configure_connection(connection: &Connection) -> Result<(), DatabaseError> {
+    connection.execute("PRAGMA foreign_keys = ON", [])?;
+    migrations::run(connection)
+}
+
+fn category_from_row(row: &Row) -> Result<Category, RusqliteError> {
+    let icon_color: String = row.get("icon_color")?;
+    Ok(Category {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        icon_name: row.get("icon_name")?,
+        icon_color: Color32::from_hex(&icon_color).map_err(|error| {
+            RusqliteError::FromSqlConversionFailure(
+                3,
+                Type::Text,
+                format!("'{}' is not a valid hex color ({:?})", icon_color, error).into(),
+            )
+        })?,
+    })
+}
+
+fn insert_recipe_items(
+    connection: &Connection,
+    recipe_id: u32,
+    items: &[RecipeItem],
+) -> Result<(), RusqliteError> {
+    let mut statement = connection.prepare(
+        "
+        INSERT INTO recipe_items (recipe_id, ingredient_id, amount, unit)
+        VALUES (?1, ?2, ?3, ?4);
+        ",
+    )?;
+    for item in items {
+        statement.insert(rusqlite::params![
+            recipe_id,
+            item.ingredient.id,
+            item.amount,
+            item.unit
+        ])?;
+    }
+
+    Ok(())
+}
+
+// Splits a log item into the `ingredient_id` and `recipe_id` columns of `daily_logs`.
+fn log_item_ids(item: &LogItem) -> (Option<u32>, Option<u32>) {
+    match item {
+        LogItem::Ingredient(ingredient) => (Some(ingredient.id), None),
+        LogItem::Recipe(recipe) => (None, Some(recipe.id)),
+    }
+}
+
+fn meal_from_row(row: &Row) -> Result<Option<Meal>, RusqliteError> {
+    let id: Option<u32> = row.get("meal_id")?;
+    Ok(match id {
+        Some(id) => Some(Meal {
+            id,
+            name: row.get("meal_name")?,
+        }),
+        None => None,
+    })
+}
+
+fn goal_from_row(row: &Row) -> Result<Goal, RusqliteError> {
+    let weekday: Option<u8> = row.get("weekday")?;
+    Ok(Goal {
+        nutrient: row.get("nutrient")?,
+        weekday: weekday
+            .map(|weekday| {
+                Weekday::try_from(weekday).map_err(|error| {
+                    RusqliteError::FromSqlConversionFailure(1, Type::Integer, Box::new(error))
+                })
+            })
+            .transpose()?,
+        target: row.get("target")?,
+    })
+}
+
+fn id_list(ids: &[u32]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Everything removed along with the ingredients by `ON DELETE CASCADE`.
+fn ingredient_scopes(ids: &[u32]) -> Vec<Scope> {
+    let ids = id_list(ids);
+    let mut scopes = vec![
+        Scope::new("ingredients", format!("id IN ({})", ids)),
+        Scope::new(
+            "ingredient_categories",
+            format!("ingredient_id IN ({})", ids),
+        ),
+        Scope::new("nutritional_info", format!("ingredient_id IN ({})", ids)),
+        Scope::new("daily_logs", format!("ingredient_id IN ({})", ids)),
+        Scope::new("recipe_items", format!("ingredient_id IN ({})", ids)),
+    ];
+    for table in NUTRIENT_SET_TABLES {
+        scopes.push(Scope::new(
+            table,
+            format!(
+                "nutrition_info_id IN (SELECT id FROM nutritional_info WHERE ingredient_id IN ({}))",
+                ids
+            ),
+        ));
+    }
+    scopes
+}
+
+fn category_scopes(ids: &[u32]) -> Vec<Scope> {
+    let ids = id_list(ids);
+    vec![
+        Scope::new("categories", format!("id IN ({})", ids)),
+        Scope::new("ingredient_categories", format!("category_id IN ({})", ids)),
+    ]
+}
+
+fn log_entry_scopes(ids: &[u32]) -> Vec<Scope> {
+    vec![Scope::new(
+        "daily_logs",
+        format!("id IN ({})", id_list(ids)),
+    )]
+}
+
+fn recipe_scopes(id: u32) -> Vec<Scope> {
+    vec![
+        Scope::new("recipes", format!("id = {}", id)),
+        Scope::new("recipe_items", format!("recipe_id = {}", id)),
+        Scope::new("daily_logs", format!("recipe_id = {}", id)),
+    ]
+}
+
+fn goal_scopes(goal: &Goal) -> Vec<Scope> {
+    vec![Scope::new(
+        "goals",
+        format!(
+            "nutrient = {} AND IFNULL(weekday, -1) = {}",
+            goal.nutrient as u32,
+            goal.weekday
+                .map_or(-1, |weekday| weekday.num_days_from_monday() as i64)
+        ),
+    )]
+}
+
+fn delete_goal(connection: &Connection, goal: &Goal) -> Result<usize, RusqliteError> {
+    connection.execute(
+        "DELETE FROM goals WHERE nutrient = ?1 AND weekday IS ?2;",
+        rusqlite::params![
+            goal.nutrient,
+            goal.weekday.map(|weekday| weekday.num_days_from_monday())
+        ],
+    )
+}
+
+fn get_ingredient_categories(
+    connection: &Connection,
+    ingredient_id: u32,
+) -> Result<Vec<Category>, RusqliteError> {
+    let mut statement = connection.prepare(
+        "
+        SELECT id, name, icon_name, icon_color
+        FROM categories c
+        INNER JOIN ingredient_categories ic
+            ON ic.category_id = c.id
+            AND ic.ingredient_id = ?1;
+        ",
+    )?;
+    let categories_iter = statement.query_map([ingredient_id], category_from_row)?;
+
+    categories_iter.collect()
+}
+
+fn ingredient_from_row(connection: &Connection, row: &Row) -> Result<Ingredient, RusqliteError> {
+    let id: u32 = row.get("id")?;
+    Ok(Ingredient {
+        id,
+        name: row.get("name")?,
+        brand: row.get("brand")?,
+        barcode: row.get("barcode")?,
+        categories: get_ingredient_categories(connection, id)?,
+        nutritional_info: vec![nutritional_info_from_row(row)?],
+        conversions: Conversions {
+            density: row.get("density")?,
+            piece_weight: row.get("piece_weight")?,
+        },
+        archived: row.get("archived")?,
+    })
+}
+
+fn nutritional_info_from_row(row: &Row) -> Result<NutritionalInfo, RusqliteError> {
+    Ok(NutritionalInfo {
+        id: row.get("nutritional_info_id")?,
+        name: row.get("serving_name")?,
+        default_amount: row.get("default_amount")?,
+        default_unit: row.get("default_unit")?,
+        kilocalories: row.get("kilocalories")?,
+        macronutrients: Macronutrients {
+            proteins: Proteins {
+                essential_amino_acids: EssentialAminoAcids {
+                    histidine: row.get("histidine")?,
+                    isoleucine: row.get("isoleucine")?,
+                    leucine: row.get("leucine")?,
+                    lysine: row.get("lysine")?,
+                    methionine: row.get("methionine")?,
+                    phenylalanine: row.get("phenylalanine")?,
+                    threonine: row.get("threonine")?,
+                    tryptophan: row.get("tryptophan")?,
+                    valine: row.get("valine")?,
+                },
+                non_essential_amino_acids: NonEssentialAminoAcids {
+                    alanine: row.get("alanine")?,
+                    arginine: row.get("arginine")?,
+                    asparagine: row.get("asparagine")?,
+                    aspartic_acid: row.get("aspartic_acid")?,
+                    cysteine: row.get("cysteine")?,
+                    glutamic_acid: row.get("glutamic_acid")?,
+                    glutamine: row.get("glutamine")?,
+                    glycine: row.get("glycine")?,
+                    proline: row.get("proline")?,
+                    serine: row.get("serine")?,
+                    tyrosine: row.get("tyrosine")?,
+                },
+                unspecified: row.get("unspecified_protein")?,
+            },
+            fats: Fats {
+                saturated: row.get("saturated")?,
+                monounsaturated: row.get("monounsaturated")?,
+                polyunsaturated: row.get("polyunsaturated")?,
+                unspecified: row.get("unspecified_fat")?,
+            },
+            carbohydrates: Carbohydrates {
+                starch: row.get("starch")?,
+                fiber: row.get("fiber")?,
+                sugars: row.get("sugars")?,
+                sugar_alcohols: row.get("sugar_alcohols")?,
+                unspecified: row.get("unspecified_carbohydrates")?,
+            },
+        },
+        micronutrients: Micronutrients {
+            vitamins: Vitamins {
+                vitamin_a: row.get("vitamin_a")?,
+                vitamin_b1: row.get("vitamin_b1")?,
+                vitamin_b2: row.get("vitamin_b2")?,
+                vitamin_b3: row.get("vitamin_b3")?,
+                vitamin_b5: row.get("vitamin_b5")?,
+                vitamin_b6: row.get("vitamin_b6")?,
+                vitamin_b9: row.get("vitamin_b9")?,
+                vitamin_b12: row.get("vitamin_b12")?,
+                vitamin_c: row.get("vitamin_c")?,
+                vitamin_d: row.get("vitamin_d")?,
+                vitamin_e: row.get("vitamin_e")?,
+                vitamin_k: row.get("vitamin_k")?,
+                betaine: row.get("betaine")?,
+                choline: row.get("choline")?,
+            },
+            minerals: Minerals {
+                calcium: row.get("calcium")?,
+                copper: row.get("copper")?,
+                iron: row.get("iron")?,
+                magnesium: row.get("magnesium")?,
+                manganese: row.get("manganese")?,
+                phosphorus: row.get("phosphorus")?,
+                potassium: row.get("potassium")?,
+                selenium: row.get("selenium")?,
+                sodium: row.get("sodium")?,
+                zinc: row.get("zinc")?,
+            },
+        },
+    })
+}
+
+fn insert_ingredient(
+    connection: &Connection,
+    ingredient: &Ingredient,
+) -> Result<u32, RusqliteError> {
+    connection.execute(
+        "
+        INSERT INTO ingredients (name, brand, density, piece_weight, archived, barcode)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6);
+        ",
+        rusqlite::params![
+            ingredient.name,
+            ingredient.brand,
+            ingredient.conversions.density,
+            ingredient.conversions.piece_weight,
+            ingredient.archived,
+            ingredient.barcode
+        ],
+    )?;
+    let ingredient_id = connection.last_insert_rowid() as u32;
+
+    for category in &ingredient.categories {
+        connection.execute(
+            "INSERT INTO ingredient_categories (ingredient_id, category_id) VALUES (?1, ?2);",
+            [ingredient_id, category.id],
+        )?;
+    }
+
+    for nutritional_info in &ingredient.nutritional_info {
+        insert_nutritional_info(connection, ingredient_id, nutritional_info)?;
+    }
+
+    Ok(ingredient_id)
+}
+
+fn insert_nutritional_info(
+    connection: &Connection,
+    ingredient_id: u32,
+    nutritional_info: &NutritionalInfo,
+) -> Result<u32, RusqliteError> {
+    connection.execute(
+        "
+        INSERT INTO nutritional_info (
+            name, default_amount, default_unit, kilocalories, ingredient_id
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5);
+        ",
+        rusqlite::params![
+            nutritional_info.name,
+            nutritional_info.default_amount,
+            nutritional_info.default_unit,
+            nutritional_info.kilocalories,
+            ingredient_id
+        ],
+    )?;
+    let nutritional_info_id = connection.last_insert_rowid();
+    insert_nutrient_sets(connection, nutritional_info_id, nutritional_info)?;
+    Ok(nutritional_info_id as u32)
+}
+
+// Overwrites the stored serving with the id of `nutritional_info`, returns
+// whether the ingredient had such a serving.
+fn update_nutritional_info(
+    connection: &Connection,
+    ingredient_id: u32,
+    nutritional_info: &NutritionalInfo,
+) -> Result<bool, RusqliteError> {
+    let updated = connection.execute(
+        "
+        UPDATE nutritional_info
+        SET name = ?1, default_amount = ?2, default_unit = ?3, kilocalories = ?4
+        WHERE id = ?5 AND ingredient_id = ?6;
+        ",
+        rusqlite::params![
+            nutritional_info.name,
+            nutritional_info.default_amount,
+            nutritional_info.default_unit,
+            nutritional_info.kilocalories,
+            nutritional_info.id,
+            ingredient_id
+        ],
+    )?;
+    if updated == 0 {
+        return Ok(false);
+    }
+
+    for table in NUTRIENT_SET_TABLES {
+        connection.execute(
+            &format!("DELETE FROM {} WHERE nutrition_info_id = ?1;", table),
+            [nutritional_info.id],
+        )?;
+    }
+    insert_nutrient_sets(connection, nutritional_info.id.into(), nutritional_info)?;
+    Ok(true)
+}
+
+fn insert_nutrient_sets(
+    connection: &Connection,
+    nutritional_info_id: i64,
+    nutritional_info: &NutritionalInfo,
+) -> Result<(), RusqliteError> {
+    let proteins = &nutritional_info.macronutrients.proteins;
+    connection.execute(
+        "
+        INSERT INTO protein_sets (
+            nutrition_info_id,
+            --essentials
+            histidine, isoleucine, leucine, lysine, methionine, phenylalanine,
+            threonine, tryptophan, valine,
+            --non-essentials
+            alanine, arginine, asparagine, aspartic_acid, cysteine, glutamic_acid, glutamine,
+            glycine, proline, serine, tyrosine, unspecified_protein
+        )
+        VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19,
+            ?20, ?21, ?22
+        );
+        ",
+        rusqlite::params![
+            nutritional_info_id,
+            proteins.essential_amino_acids.histidine,
+            proteins.essential_amino_acids.isoleucine,
+            proteins.essential_amino_acids.leucine,
+            proteins.essential_amino_acids.lysine,
+            proteins.essential_amino_acids.methionine,
+            proteins.essential_amino_acids.phenylalanine,
+            proteins.essential_amino_acids.threonine,
+            proteins.essential_amino_acids.tryptophan,
+            proteins.essential_amino_acids.valine,
+            proteins.non_essential_amino_acids.alanine,
+            proteins.non_essential_amino_acids.arginine,
+            proteins.non_essential_amino_acids.asparagine,
+            proteins.non_essential_amino_acids.aspartic_acid,
+            proteins.non_essential_amino_acids.cysteine,
+            proteins.non_essential_amino_acids.glutamic_acid,
+            proteins.non_essential_amino_acids.glutamine,
+            proteins.non_essential_amino_acids.glycine,
+            proteins.non_essential_amino_acids.proline,
+            proteins.non_essential_amino_acids.serine,
+            proteins.non_essential_amino_acids.tyrosine,
+            proteins.unspecified
+        ],
+    )?;
+
+    let fats = &nutritional_info.macronutrients.fats;
+    connection.execute(
+        "
+        INSERT INTO fat_sets (
+            nutrition_info_id,
+            saturated, monounsaturated, polyunsaturated, unspecified_fat
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5);
+        ",
+        rusqlite::params![
+            nutritional_info_id,
+            fats.saturated,
+            fats.monounsaturated,
+            fats.polyunsaturated,
+            fats.unspecified
+        ],
+    )?;
+
+    let carbohydrates = &nutritional_info.macronutrients.carbohydrates;
+    connection.execute(
+        "
+        INSERT INTO carbohydrate_sets (
+            nutrition_info_id,
+            starch, fiber, sugars, sugar_alcohols, unspecified_carbohydrates
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6);
+        ",
+        rusqlite::params![
+            nutritional_info_id,
+            carbohydrates.starch,
+            carbohydrates.fiber,
+            carbohydrates.sugars,
+            carbohydrates.sugar_alcohols,
+            carbohydrates.unspecified
+        ],
+    )?;
+
+    let vitamins = &nutritional_info.micronutrients.vitamins;
+    connection.execute(
+        "
+        INSERT INTO vitamin_sets (
+            nutrition_info_id,
+            vitamin_a, vitamin_b1, vitamin_b2, vitamin_b3, vitamin_b5, vitamin_b6, vitamin_b9,
+            vitamin_b12, vitamin_c, vitamin_d, vitamin_e, vitamin_k, betaine, choline
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15);
+        ",
+        rusqlite::params![
+            nutritional_info_id,
+            vitamins.vitamin_a,
+            vitamins.vitamin_b1,
+            vitamins.vitamin_b2,
+            vitamins.vitamin_b3,
+            vitamins.vitamin_b5,
+            vitamins.vitamin_b6,
+            vitamins.vitamin_b9,
+            vitamins.vitamin_b12,
+            vitamins.vitamin_c,
+            vitamins.vitamin_d,
+            vitamins.vitamin_e,
+            vitamins.vitamin_k,
+            vitamins.betaine,
+            vitamins.choline
+        ],
+    )?;
+
+    let minerals = &nutritional_info.micronutrients.minerals;
+    connection.execute(
+        "
+        INSERT INTO mineral_sets (
+            nutrition_info_id,
+            calcium, copper, iron, magnesium, manganese,
+            phosphorus, potassium, selenium, sodium, zinc
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11);
+        ",
+        rusqlite::params![
+            nutritional_info_id,
+            minerals.calcium,
+            minerals.copper,
+            minerals.iron,
+            minerals.magnesium,
+            minerals.manganese,
+            minerals.phosphorus,
+            minerals.potassium,
+            minerals.selenium,
+            minerals.sodium,
+            minerals.zinc
+        ],
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::goals::GoalNutrient;
+    use crate::reference_intakes::{AgeGroup, Sex};
+    use chrono::NaiveTime;
+
+    fn test_category(database: &mut Database, name: &str) -> Category {
+        database
+            .insert_category(&Category {
+                id: 0,
+                name: name.to_owned(),
+                icon_name: "bread".to_owned(),
+                icon_color: Color32::from_rgb(200, 150, 50),
+            })
+            .unwrap();
+        database
+            .get_categories()
+            .unwrap()
+            .into_iter()
+            .find(|category| category.name == name)
+            .unwrap()
+    }
+
+    fn test_ingredient(name: &str, brand: &str, categories: Vec<Category>) -> Ingredient {
+        let mut ingredient = Ingredient {
+            brand: brand.to_owned(),
+            categories,
+            ..Ingredient::test(name, 151.37)
+        };
+        let nutritional_info = &mut ingredient.nutritional_info[0];
+        nutritional_info.default_amount = 40.0;
+        nutritional_info
+            .macronutrients
+            .proteins
+            .essential_amino_acids
+            .leucine = 0.456;
+        nutritional_info.macronutrients.carbohydrates.fiber = 4.28;
+        nutritional_info.micronutrients.vitamins.vitamin_b1 = 0.0307;
+        nutritional_info.micronutrients.minerals.sodium = 2.55;
+
+        ingredient
+    }
+
+    #[test]
+    fn insert_ingredient_with_quotes_in_name() {
+        let mut database = Database::open_in_memory().unwrap();
+        let category = test_category(&mut database, "Grain's");
+
+        let ingredient = test_ingredient(
+            "O'Brien's Oats",
+            "Mill \"Best\"; DROP TABLE ingredients;--",
+            vec![category],
+        );
+        let id = database.insert_ingredient(&ingredient).unwrap();
+
+        let stored = database.get_ingredient_by_id(id).unwrap();
+        assert_eq!(stored.name, "O'Brien's Oats");
+        assert_eq!(stored.brand, ingredient.brand);
+        assert_eq!(stored.categories.len(), 1);
+        assert_eq!(stored.categories[0].name, "Grain's");
+        assert_eq!(database.get_ingredients().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn insert_ingredient_keeps_full_precision() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let ingredient = test_ingredient("O'Brien's Oats", "", Vec::new());
+        let id = database.insert_ingredient(&ingredient).unwrap();
+
+        let stored = &database.get_ingredient_by_id(id).unwrap().nutritional_info[0];
+        assert_eq!(stored.kilocalories, 151.37);
+        assert_eq!(
+            stored.macronutrients.proteins.essential_amino_acids.leucine,
+            0.456
+        );
+        assert_eq!(stored.macronutrients.carbohydrates.fiber, 4.28);
+        assert_eq!(stored.micronutrients.vitamins.vitamin_b1, 0.0307);
+        assert_eq!(stored.micronutrients.minerals.sodium, 2.55);
+    }
+
+    #[test]
+    fn insert_ingredient_is_atomic() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let missing_category = Category {
+            id: 42,
+            name: "Missing".to_owned(),
+            icon_name: "bean".to_owned(),
+            icon_color: Color32::WHITE,
+        };
+        let ingredient = test_ingredient("O'Brien's Oats", "", vec![missing_category]);
+
+        assert!(matches!(
+            database.insert_ingredient(&ingredient),
+            Err(DatabaseError::Constraint(_))
+        ));
+        assert!(database.get_ingredients().unwrap().is_empty());
+    }
+
+    #[test]
+    fn deleting_several_ingredients_is_atomic() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let oats = database
+            .insert_ingredient(&test_ingredient("Oats", "", Vec::new()))
+            .unwrap();
+        let rice = database
+            .insert_ingredient(&test_ingredient("Rice", "", Vec::new()))
+            .unwrap();
+        database
+            .start_connection()
+            .unwrap()
+            .execute_batch(&format!(
+                "CREATE TRIGGER keep_rice BEFORE DELETE ON ingredients WHEN OLD.id = {}
+                BEGIN SELECT RAISE(ABORT, 'rice is kept'); END;",
+                rice
+            ))
+            .unwrap();
+        let undo_description = database.undo_description().map(str::to_owned);
+
+        let ingredients: Vec<Ingredient> = [oats, rice]
+            .into_iter()
+            .map(|id| (*database.get_ingredient_by_id(id).unwrap()).clone())
+            .collect();
+        assert!(database.delete_ingredients(&ingredients).is_err());
+        assert_eq!(database.get_ingredients().unwrap().len(), 2);
+        assert_eq!(
+            database.undo_description().map(str::to_owned),
+            undo_description
+        );
+    }
+
+    #[test]
+    fn get_log_entries_only_returns_requested_date() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let mut oats = test_ingredient("O'Brien's Oats", "", Vec::new());
+        oats.conversions.piece_weight = Some(20.0);
+        let id = database.insert_ingredient(&oats).unwrap();
+        let ingredient = database.get_ingredient_by_id(id).unwrap();
+        assert_eq!(ingredient.conversions, oats.conversions);
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        let other_date = NaiveDate::from_ymd_opt(2024, 3, 27).unwrap();
+        for (date, amount, unit) in [(date, 3.0, Unit::Pieces), (other_date, 2.0, Unit::Servings)] {
+            database
+                .insert_log_entry(
+                    &date,
+                    &LogEntry {
+                        id: 0,
+                        item: LogItem::Ingredient(ingredient.clone()),
+                        amount,
+                        unit,
+                        serving: None,
+                        meal: None,
+                        time: None,
+                    },
+                )
+                .unwrap();
+        }
+
+        let log_entries = database.get_log_entries(&date).unwrap();
+        assert_eq!(log_entries.len(), 1);
+        assert_eq!(log_entries[0].amount, 3.0);
+        assert_eq!(log_entries[0].unit, Unit::Pieces);
+        assert_eq!(log_entries[0].item.name(), "O'Brien's Oats");
+        let expected_calories = 151.37 * (3.0 * 20.0 / 40.0);
+        assert!((log_entries[0].nutrients().kilocalories - expected_calories).abs() < 1e-3);
+    }
+
+    #[test]
+    fn set_goal_replaces_goal_for_same_weekday() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        for (weekday, target) in [(None, 2000.0), (Some(Weekday::Sat), 2500.0), (None, 1800.0)] {
+            database
+                .set_goal(&Goal {
+                    nutrient: GoalNutrient::Kilocalories,
+                    weekday,
+                    target,
+                })
+                .unwrap();
+        }
+
+        let goals = database.get_goals().unwrap();
+        assert_eq!(goals.len(), 2);
+        assert_eq!(goals[0].weekday, None);
+        assert_eq!(goals[0].target, 1800.0);
+        assert_eq!(goals[1].weekday, Some(Weekday::Sat));
+        assert_eq!(goals[1].target, 2500.0);
+    }
+
+    #[test]
+    fn intake_profile_is_stored_and_undone() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        assert_eq!(
+            database.get_intake_profile().unwrap(),
+            IntakeProfile::default()
+        );
+        let profile = IntakeProfile {
+            sex: Sex::Male,
+            age: AgeGroup::Years71Plus,
+        };
+        database.set_intake_profile(&profile).unwrap();
+        assert_eq!(database.get_intake_profile().unwrap(), profile);
+
+        database.undo().unwrap();
+        assert_eq!(
+            database.get_intake_profile().unwrap(),
+            IntakeProfile::default()
+        );
+    }
+
+    #[test]
+    fn get_daily_calories_sums_servings_per_day() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let id = database
+            .insert_ingredient(&test_ingredient("O'Brien's Oats", "", Vec::new()))
+            .unwrap();
+        let ingredient = database.get_ingredient_by_id(id).unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        let other_date = NaiveDate::from_ymd_opt(2024, 3, 27).unwrap();
+        for (date, servings) in [(date, 1.0), (date, 0.5), (other_date, 2.0)] {
+            database
+                .insert_log_entry(
+                    &date,
+                    &LogEntry {
+                        id: 0,
+                        item: LogItem::Ingredient(ingredient.clone()),
+                        amount: servings,
+                        unit: Unit::Servings,
+                        serving: None,
+                        meal: None,
+                        time: None,
+                    },
+                )
+                .unwrap();
+        }
+
+        let daily_calories = database.get_daily_calories(&date, &other_date).unwrap();
+        assert_eq!(daily_calories.len(), 2);
+        assert!((daily_calories[&date] - 151.37 * 1.5).abs() < 1e-3);
+        assert!((daily_calories[&other_date] - 151.37 * 2.0).abs() < 1e-3);
+        assert_eq!(
+            database
+                .get_daily_calories(&other_date, &NaiveDate::from_ymd_opt(2024, 4, 30).unwrap())
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let daily_totals = database.get_daily_totals(&date, &date).unwrap();
+        assert_eq!(daily_totals.len(), 1);
+        assert!((daily_totals[&date].kilocalories - 151.37 * 1.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn log_entries_keep_meal_and_time() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let id = database
+            .insert_ingredient(&test_ingredient("O'Brien's Oats", "", Vec::new()))
+            .unwrap();
+        let ingredient = database.get_ingredient_by_id(id).unwrap();
+        let breakfast = database
+            .get_meals()
+            .unwrap()
+            .into_iter()
+            .find(|meal| meal.name == "Breakfast")
+            .unwrap();
+        let brunch = database.insert_meal("Brunch").unwrap();
+        assert_eq!(database.get_meals().unwrap().last(), Some(&brunch));
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        let time = NaiveTime::from_hms_opt(7, 45, 0).unwrap();
+        for (meal, time) in [(Some(breakfast.clone()), Some(time)), (None, None)] {
+            database
+                .insert_log_entry(
+                    &date,
+                    &LogEntry {
+                        id: 0,
+                        item: LogItem::Ingredient(ingredient.clone()),
+                        amount: 1.0,
+                        unit: Unit::Servings,
+                        serving: None,
+                        meal,
+                        time,
+                    },
+                )
+                .unwrap();
+        }
+
+        let log_entries = database.get_log_entries(&date).unwrap();
+        assert_eq!(log_entries.len(), 2);
+        assert_eq!(log_entries[0].meal, Some(breakfast));
+        assert_eq!(log_entries[0].time, Some(time));
+        assert_eq!(log_entries[1].meal, None);
+        assert_eq!(log_entries[1].time, None);
+    }
+
+    #[test]
+    fn log_entries_can_be_moved_and_deleted() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let id = database
+            .insert_ingredient(&test_ingredient("O'Brien's Oats", "", Vec::new()))
+            .unwrap();
+        let ingredient = database.get_ingredient_by_id(id).unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        let other_date = NaiveDate::from_ymd_opt(2024, 3, 27).unwrap();
+        for amount in [1.0, 2.0, 3.0] {
+            database
+                .insert_log_entry(
+                    &date,
+                    &LogEntry {
+                        id: 0,
+                        item: LogItem::Ingredient(ingredient.clone()),
+                        amount,
+                        unit: Unit::Servings,
+                        serving: None,
+                        meal: None,
+                        time: None,
+                    },
+                )
+                .unwrap();
+        }
+
+        let mut log_entries = database.get_log_entries(&date).unwrap();
+        log_entries[0].amount = 80.0;
+        log_entries[0].unit = Unit::Grams;
+        database
+            .update_log_entry(&other_date, &log_entries[0])
+            .unwrap();
+        let moved = database.get_log_entries(&other_date).unwrap();
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].id, log_entries[0].id);
+        assert_eq!(moved[0].amount, 80.0);
+        assert_eq!(moved[0].unit, Unit::Grams);
+
+        let in_range = database
+            .get_log_entries_in_range(&date, &other_date)
+            .unwrap();
+        let dates: Vec<NaiveDate> = in_range.iter().map(|(date, _)| *date).collect();
+        assert_eq!(dates, vec![date, date, other_date]);
+        assert!(database
+            .get_log_entries_in_range(&other_date.succ_opt().unwrap(), &NaiveDate::MAX)
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(database.delete_log_entries(&log_entries[1..]).unwrap(), 2);
+        assert!(database.get_log_entries(&date).unwrap().is_empty());
+        assert_eq!(database.get_log_entries(&other_date).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn archived_ingredients_are_hidden_but_keep_their_log_entries() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let id = database
+            .insert_ingredient(&test_ingredient("O'Brien's Oats", "", Vec::new()))
+            .unwrap();
+        let ingredient = database.get_ingredient_by_id(id).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        for _ in 0..2 {
+            database
+                .insert_log_entry(
+                    &date,
+                    &LogEntry {
+                        id: 0,
+                        item: LogItem::Ingredient(ingredient.clone()),
+                        amount: 1.0,
+                        unit: Unit::Servings,
+                        serving: None,
+                        meal: None,
+                        time: None,
+                    },
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            database.get_ingredient_usage(&ingredient).unwrap(),
+            IngredientUsage {
+                log_entries: 2,
+                recipes: 0,
+            }
+        );
+
+        database.set_ingredient_archived(&ingredient, true).unwrap();
+        assert!(database.get_ingredients().unwrap().is_empty());
+        assert_eq!(database.get_archived_ingredients().unwrap().len(), 1);
+        assert!(database.get_ingredient_by_id(id).unwrap().archived);
+        let log_entries = database.get_log_entries(&date).unwrap();
+        assert_eq!(log_entries.len(), 2);
+        assert!(
+            (database.get_daily_calories(&date, &date).unwrap()[&date] - 151.37 * 2.0).abs() < 1e-3
+        );
+
+        database
+            .set_ingredient_archived(&ingredient, false)
+            .unwrap();
+        assert_eq!(database.get_ingredients().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn servings_keep_their_ids_for_log_entries() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let id = database
+            .insert_ingredient(&test_ingredient("Oats", "", Vec::new()))
+            .unwrap();
+        let mut ingredient = (*database.get_ingredient_by_id(id).unwrap()).clone();
+        let mut bowl = ingredient.nutritional_info[0].clone();
+        bowl.id = 0;
+        bowl.name = "Bowl".to_owned();
+        ingredient.nutritional_info.push(bowl);
+        database.update_ingredient(&ingredient).unwrap();
+
+        let ingredient = database.get_ingredient_by_id(id).unwrap();
+        assert_eq!(ingredient.nutritional_info.len(), 2);
+        let bowl = ingredient.nutritional_info[1].id;
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        database
+            .insert_log_entry(
+                &date,
+                &LogEntry {
+                    id: 0,
+                    item: LogItem::Ingredient(ingredient.clone()),
+                    amount: 2.0,
+                    unit: Unit::Servings,
+                    serving: Some(bowl),
+                    meal: None,
+                    time: None,
+                },
+            )
+            .unwrap();
+
+        let mut edited = (*ingredient).clone();
+        edited.nutritional_info[1].name = "Large bowl".to_owned();
+        database.update_ingredient(&edited).unwrap();
+        let log_entries = database.get_log_entries(&date).unwrap();
+        assert_eq!(log_entries[0].serving, Some(bowl));
+
+        assert_eq!(
+            database.get_serving_usage(&edited).unwrap(),
+            HashMap::from([(bowl, 1)])
+        );
+
+        // A logged serving stays, only an unused one can be removed.
+        edited.nutritional_info.truncate(1);
+        assert!(matches!(
+            database.update_ingredient(&edited),
+            Err(DatabaseError::ServingInUse { serving, log_entries: 1 })
+                if serving == "Large bowl (40 g)"
+        ));
+        let log_entries = database.get_log_entries(&date).unwrap();
+        assert_eq!(log_entries[0].serving, Some(bowl));
+        assert_eq!(
+            database
+                .get_ingredient_by_id(id)
+                .unwrap()
+                .nutritional_info
+                .len(),
+            2
+        );
+
+        database.delete_log_entries(&log_entries).unwrap();
+        database.update_ingredient(&edited).unwrap();
+        assert_eq!(
+            database
+                .get_ingredient_by_id(id)
+                .unwrap()
+                .nutritional_info
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn barcodes_are_unique_and_found_without_leading_zeros() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let mut milk = test_ingredient("Milk", "Dairy Co", Vec::new());
+        milk.barcode = Some("012345678905".to_owned());
+        let id = database.insert_ingredient(&milk).unwrap();
+        database
+            .insert_ingredient(&test_ingredient("Oats", "", Vec::new()))
+            .unwrap();
+        database
+            .insert_ingredient(&test_ingredient("Rice", "", Vec::new()))
+            .unwrap();
+
+        let found = database.get_ingredient_by_barcode("0012345678905").unwrap();
+        assert_eq!(found.map(|ingredient| ingredient.id), Some(id));
+        assert!(database
+            .get_ingredient_by_barcode("4006381333931")
+            .unwrap()
+            .is_none());
+
+        let mut copy = test_ingredient("Milk", "Other", Vec::new());
+        copy.barcode = Some("0012345678905".to_owned());
+        assert!(matches!(
+            database.insert_ingredient(&copy),
+            Err(DatabaseError::Constraint(_))
+        ));
+    }
+
+    #[test]
+    fn duplicates_are_found_by_barcode_or_name_and_brand() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let mut oats = test_ingredient("Oats", "Mill", Vec::new());
+        oats.barcode = Some("4006381333931".to_owned());
+        let id = database.insert_ingredient(&oats).unwrap();
+
+        let mut other = test_ingredient("Porridge Oats", "", Vec::new());
+        assert!(database
+            .find_duplicate_ingredient(&other)
+            .unwrap()
+            .is_none());
+        other.barcode = Some("04006381333931".to_owned());
+        assert_eq!(
+            database
+                .find_duplicate_ingredient(&other)
+                .unwrap()
+                .map(|n| n.id),
+            Some(id)
+        );
+
+        let same_name = test_ingredient(" oats ", "MILL", Vec::new());
+        assert_eq!(
+            database
+                .find_duplicate_ingredient(&same_name)
+                .unwrap()
+                .map(|n| n.id),
+            Some(id)
+        );
+    }
+
+    #[test]
+    fn undo_restores_deleted_ingredient_with_cascaded_rows() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let category = test_category(&mut database, "Grains");
+        let id = database
+            .insert_ingredient(&test_ingredient("O'Brien's Oats", "", vec![category]))
+            .unwrap();
+        let ingredient = database.get_ingredient_by_id(id).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        database
+            .insert_log_entry(
+                &date,
+                &LogEntry {
+                    id: 0,
+                    item: LogItem::Ingredient(ingredient.clone()),
+                    amount: 1.0,
+                    unit: Unit::Servings,
+                    serving: None,
+                    meal: None,
+                    time: None,
+                },
+            )
+            .unwrap();
+
+        database.delete_ingredient(&ingredient).unwrap();
+        assert!(database.get_ingredients().unwrap().is_empty());
+        assert!(database.get_log_entries(&date).unwrap().is_empty());
+        assert_eq!(
+            database.undo_description(),
+            Some("delete ingredient \"O'Brien's Oats\"")
+        );
+
+        database.undo().unwrap();
+        let restored = database.get_ingredient_by_id(id).unwrap();
+        assert_eq!(restored.categories.len(), 1);
+        assert_eq!(
+            restored.nutritional_info[0].micronutrients.minerals.sodium,
+            2.55
+        );
+        assert_eq!(database.get_log_entries(&date).unwrap().len(), 1);
+
+        database.redo().unwrap();
+        assert!(database.get_ingredients().unwrap().is_empty());
+        assert!(database.get_log_entries(&date).unwrap().is_empty());
+    }
+
+    #[test]
+    fn undo_reverts_edits_and_inserts() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let id = database
+            .insert_ingredient(&test_ingredient("Oats", "", Vec::new()))
+            .unwrap();
+        let mut ingredient = (*database.get_ingredient_by_id(id).unwrap()).clone();
+        ingredient.name = "Rolled Oats".to_owned();
+        ingredient.nutritional_info[0].kilocalories = 150.0;
+        database.update_ingredient(&ingredient).unwrap();
+
+        assert_eq!(
+            database.undo().unwrap().as_deref(),
+            Some("edit ingredient \"Rolled Oats\"")
+        );
+        let stored = database.get_ingredient_by_id(id).unwrap();
+        assert_eq!(stored.name, "Oats");
+        assert_eq!(stored.nutritional_info[0].kilocalories, 151.37);
+
+        database.undo().unwrap();
+        assert!(database.get_ingredients().unwrap().is_empty());
+        assert_eq!(database.undo().unwrap(), None);
+
+        database.redo().unwrap();
+        database.redo().unwrap();
+        assert_eq!(
+            database.get_ingredient_by_id(id).unwrap().name,
+            "Rolled Oats"
+        );
+        assert_eq!(database.redo_description(), None);
+    }
+
+    #[test]
+    fn updating_undone_inserts_reports_missing_rows() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let id = database
+            .insert_ingredient(&test_ingredient("Oats", "", Vec::new()))
+            .unwrap();
+        let ingredient = database.get_ingredient_by_id(id).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        database
+            .insert_log_entry(
+                &date,
+                &LogEntry {
+                    id: 0,
+                    item: LogItem::Ingredient(ingredient.clone()),
+                    amount: 1.0,
+                    unit: Unit::Servings,
+                    serving: None,
+                    meal: None,
+                    time: None,
+                },
+            )
+            .unwrap();
+        let log_entry = database.get_log_entries(&date).unwrap().remove(0);
+
+        database.undo().unwrap();
+        assert!(matches!(
+            database.update_log_entry(&date, &log_entry),
+            Err(DatabaseError::MissingRow(_))
+        ));
+        assert_eq!(database.redo_description(), Some("add log entry \"Oats\""));
+
+        database.undo().unwrap();
+        assert!(matches!(
+            database.update_ingredient(&ingredient),
+            Err(DatabaseError::MissingRow(_))
+        ));
+        assert!(database.get_ingredients().unwrap().is_empty());
+    }
+
+    #[test]
+    fn recipes_round_trip_and_can_be_logged() {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let id = database
+            .insert_ingredient(&test_ingredient("O'Brien's Oats", "", Vec::new()))
+            .unwrap();
+        let oats = database.get_ingredient_by_id(id).unwrap();
+        let mut recipe = Recipe {
+            id: 0,
+            name: "Porridge".to_owned(),
+            servings: 2.0,
+            items: vec![RecipeItem {
+                ingredient: oats.clone(),
+                amount: 80.0,
+                unit: Unit::Grams,
+            }],
+        };
+        recipe.id = database.insert_recipe(&recipe).unwrap();
+
+        recipe.servings = 4.0;
+        recipe.items[0].amount = 160.0;
+        database.update_recipe(&recipe).unwrap();
+        let stored = database.get_recipe_by_id(recipe.id).unwrap();
+        assert_eq!(stored.servings, 4.0);
+        assert_eq!(stored.items.len(), 1);
+        assert_eq!(stored.items[0].amount, 160.0);
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        database
+            .insert_log_entry(
+                &date,
+                &LogEntry {
+                    id: 0,
+                    item: LogItem::Recipe(stored),
+                    amount: 1.5,
+                    unit: Unit::Servings,
+                    serving: None,
+                    meal: None,
+                    time: None,
+                },
+            )
+            .unwrap();
+
+        let log_entries = database.get_log_entries(&date).unwrap();
+        assert!(
+            matches!(&log_entries[0].item, LogItem::Recipe(recipe) if recipe.name == "Porridge")
+        );
+        let expected_calories = 151.37 * (160.0 / 40.0) / 4.0 * 1.5;
+        assert!((log_entries[0].nutrients().kilocalories - expected_calories).abs() < 1e-3);
+        assert!(
+            (database.get_daily_calories(&date, &date).unwrap()[&date] - expected_calories).abs()
+                < 1e-3
+        );
+
+        database.delete_recipe(&recipe).unwrap();
+        assert!(database.get_recipes().unwrap().is_empty());
+        assert!(database.get_log_entries(&date).unwrap().is_empty());
+    }
+
+    // A database with one of everything, for the backup tests.
+    fn filled_database() -> Database {
+        let mut database = Database::open_in_memory().unwrap();
+
+        let grains = test_category(&mut database, "Grains");
+        let mut oats = test_ingredient("O'Brien's Oats", "Mill", vec![grains]);
+        oats.nutritional_info.push(NutritionalInfo {
+            default_amount: 1.0,
+            default_unit: Unit::Cups,
+            kilocalories: 307.0,
+            ..Default::default()
+        });
+        oats.conversions.density = Some(0.34);
+        let oats_id = database.insert_ingredient(&oats).unwrap();
+        let oats = database.get_ingredient_by_id(oats_id).unwrap();
+        assert_eq!(oats.nutritional_info.len(), 2);
+
+        let recipe = Recipe {
+            id: 0,
+            name: "Porridge".to_owned(),
+            servings: 2.0,
+            items: vec![RecipeItem {
+                ingredient: oats.clone(),
+                amount: 80.0,
+                unit: Unit::Grams,
+            }],
+        };
+        let recipe_id = database.insert_recipe(&recipe).unwrap();
+        let recipe = database.get_recipe_by_id(recipe_id).unwrap();
+        let brunch = database.insert_meal("Brunch").unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        // The same entry twice, both have to survive a restore.
+        for item in [
+            LogItem::Ingredient(oats.clone()),
+            LogItem::Ingredient(oats),
+            LogItem::Recipe(recipe),
+        ] {
+            database
+                .insert_log_entry(
+                    &date,
+                    &LogEntry {
+                        id: 0,
+                        item,
+                        amount: 0.5,
+                        unit: Unit::Servings,
+                        serving: None,
+                        meal: Some(brunch.clone()),
+                        time: NaiveTime::from_hms_opt(10, 30, 0),
+                    },
+                )
+                .unwrap();
+        }
+        database
+            .set_goal(&Goal {
+                nutrient: GoalNutrient::Fiber,
+                weekday: Some(Weekday::Sat),
+                target: 30.0,
+            })
+            .unwrap();
+        database
+            .set_intake_profile(&IntakeProfile {
+                sex: Sex::Male,
+                age: AgeGroup::Years51To70,
+            })
+            .unwrap();
+
+        database
+    }
+
+    fn backup_round_trip(database: &mut Database) -> Backup {
+        let mut file: Vec<u8> = Vec::new();
+        crate::backup::write(&database.get_backup().unwrap(), &mut file).unwrap();
+        crate::backup::read(file.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn backup_restores_into_other_database_with_new_ids() {
+        let mut source = filled_database();
+        let backup = backup_round_trip(&mut source);
+
+        let mut target = Database::open_in_memory().unwrap();
+        // Taken IDs force the restored rows onto different ones.
+        test_category(&mut target, "Sweets");
+        target
+            .insert_ingredient(&test_ingredient("Chocolate", "", Vec::new()))
+            .unwrap();
+        target.restore_backup(&backup, RestoreMode::Merge).unwrap();
+
+        let categories = target.get_categories().unwrap();
+        assert_eq!(categories.len(), 2);
+        let ingredients = target.get_ingredients().unwrap();
+        assert_eq!(ingredients.len(), 2);
+        let oats = &ingredients[1];
+        assert_ne!(oats.id, source.get_ingredients().unwrap()[0].id);
+        assert_eq!(oats.brand, "Mill");
+        assert_eq!(oats.categories[0].id, categories[1].id);
+        assert_eq!(oats.conversions.density, Some(0.34));
+        assert_eq!(oats.nutritional_info.len(), 2);
+        assert_eq!(
+            oats.nutritional_info[0].micronutrients.minerals.sodium,
+            2.55
+        );
+        assert_eq!(oats.nutritional_info[1].default_unit, Unit::Cups);
+
+        let recipes = target.get_recipes().unwrap();
+        assert_eq!(recipes[0].items[0].ingredient.id, oats.id);
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        let log_entries = target.get_log_entries(&date).unwrap();
+        assert_eq!(log_entries.len(), 3);
+        assert!(matches!(&log_entries[0].item, LogItem::Ingredient(item) if item.id == oats.id));
+        assert!(matches!(&log_entries[2].item, LogItem::Recipe(item) if item.id == recipes[0].id));
+        assert_eq!(log_entries[0].meal.as_ref().unwrap().name, "Brunch");
+        assert_eq!(log_entries[0].time, NaiveTime::from_hms_opt(10, 30, 0));
+        assert_eq!(target.get_goals().unwrap().len(), 1);
+        assert_eq!(
+            target.get_intake_profile().unwrap(),
+            source.get_intake_profile().unwrap()
+        );
+
+        target.undo().unwrap();
+        assert_eq!(target.get_ingredients().unwrap().len(), 1);
+        assert!(target.get_recipes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn merged_servings_are_matched_by_name_amount_and_unit() {
+        let mut source = filled_database();
+        let oats = source.get_ingredients().unwrap()[0].clone();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 27).unwrap();
+        source
+            .insert_log_entry(
+                &date,
+                &LogEntry {
+                    id: 0,
+                    item: LogItem::Ingredient(oats.clone()),
+                    amount: 1.0,
+                    unit: Unit::Servings,
+                    serving: Some(oats.nutritional_info[1].id),
+                    meal: None,
+                    time: None,
+                },
+            )
+            .unwrap();
+        let backup = backup_round_trip(&mut source);
+
+        // The same ingredient with the cup serving first and one of its own.
+        let mut target = Database::open_in_memory().unwrap();
+        let mut stored = test_ingredient("O'Brien's Oats", "Mill", Vec::new());
+        let mut handful = stored.nutritional_info[0].clone();
+        handful.name = "Handful".to_owned();
+        stored.nutritional_info = vec![oats.nutritional_info[1].clone(), handful];
+        let id = target.insert_ingredient(&stored).unwrap();
+        target.restore_backup(&backup, RestoreMode::Merge).unwrap();
+
+        // The unnamed gram serving of the backup is missing and gets added.
+        let ingredient = target.get_ingredient_by_id(id).unwrap();
+        assert_eq!(ingredient.nutritional_info.len(), 3);
+        assert_eq!(ingredient.nutritional_info[1].name, "Handful");
+        assert_eq!(ingredient.nutritional_info[2].name, "");
+        assert_eq!(ingredient.nutritional_info[2].default_unit, Unit::Grams);
+
+        let log_entries = target.get_log_entries(&date).unwrap();
+        assert_eq!(log_entries.len(), 1);
+        assert_eq!(
+            log_entries[0].serving,
+            Some(ingredient.nutritional_info[0].id)
+        );
+    }
+
+    #[test]
+    fn replacing_keeps_ingredients_with_the_same_name_apart() {
+        let document = r#"{
+            "format": "sophross-backup",
+            "version": 1,
+            "ingredients": [{
+                "id": 1,
+                "name": "Oats",
+                "nutritional_info": [
+                    { "default_amount": 100, "default_unit": "g", "nutrients": { "kilocalories": 389 } },
+                    { "name": "Cup", "default_amount": 80, "default_unit": "g", "nutrients": {} }
+                ]
+            }, {
+                "id": 2,
+                "name": "Oats",
+                "nutritional_info": [
+                    { "id": 5, "default_amount": 40, "default_unit": "g", "nutrients": { "kilocalories": 150 } },
+                    { "id": 6, "name": "Scoop", "default_amount": 30, "default_unit": "g", "nutrients": {} }
+                ]
+            }],
+            "daily_logs": [
+                { "date": "2024-03-26", "ingredient": 1, "amount": 1, "unit": "g" },
+                { "date": "2024-03-26", "ingredient": 2, "amount": 1, "unit": "serving", "serving": 6 }
+            ]
+        }"#;
+        let backup = crate::backup::read(document.as_bytes()).unwrap();
+
+        let mut database = filled_database();
+        database
+            .restore_backup(&backup, RestoreMode::Replace)
+            .unwrap();
+
+        let ingredients = database.get_ingredients().unwrap();
+        assert_eq!(ingredients.len(), 2);
+        assert_eq!(ingredients[0].nutritional_info[0].kilocalories, 389.0);
+        assert_eq!(ingredients[0].nutritional_info[1].name, "Cup");
+        assert_eq!(ingredients[1].nutritional_info[0].kilocalories, 150.0);
+        assert_eq!(ingredients[1].nutritional_info[1].name, "Scoop");
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        let log_entries = database.get_log_entries(&date).unwrap();
+        assert_eq!(log_entries.len(), 2);
+        assert!(
+            matches!(&log_entries[0].item, LogItem::Ingredient(item) if item.id == ingredients[0].id)
+        );
+        assert!(
+            matches!(&log_entries[1].item, LogItem::Ingredient(item) if item.id == ingredients[1].id)
+        );
+        assert_eq!(
+            log_entries[1].serving,
+            Some(ingredients[1].nutritional_info[1].id)
+        );
+    }
+
+    #[test]
+    fn merging_a_backup_twice_adds_nothing_new_and_replacing_drops_the_rest() {
+        let mut database = filled_database();
+        let backup = backup_round_trip(&mut database);
+
+        database
+            .restore_backup(&backup, RestoreMode::Merge)
+            .unwrap();
+        database
+            .restore_backup(&backup, RestoreMode::Merge)
+            .unwrap();
+
+        assert_eq!(database.get_categories().unwrap().len(), 1);
+        assert_eq!(database.get_ingredients().unwrap().len(), 1);
+        assert_eq!(database.get_recipes().unwrap().len(), 1);
+        assert_eq!(database.get_meals().unwrap().len(), 5);
+        let date = NaiveDate::from_ymd_opt(2024, 3, 26).unwrap();
+        assert_eq!(database.get_log_entries(&date).unwrap().len(), 3);
+        assert_eq!(database.get_goals().unwrap().len(), 1);
+
+        // Merging keeps the profile that is set, replacing restores the backup's.
+        let profile = database.get_intake_profile().unwrap();
+        let changed = IntakeProfile {
+            sex: Sex::Female,
+            age: AgeGroup::Years14To18,
+        };
+        database.set_intake_profile(&changed).unwrap();
+        database
+            .restore_backup(&backup, RestoreMode::Merge)
+            .unwrap();
+        assert_eq!(database.get_intake_profile().unwrap(), changed);
+
+        database
+            .insert_ingredient(&test_ingredient("Chocolate", "", Vec::new()))
+            .unwrap();
+        database
+            .restore_backup(&backup, RestoreMode::Replace)
+            .unwrap();
+        let ingredients = database.get_ingredients().unwrap();
+        assert_eq!(ingredients.len(), 1);
+        assert_eq!(ingredients[0].name, "O'Brien's Oats");
+        assert_eq!(database.get_meals().unwrap().len(), 5);
+        assert_eq!(database.get_log_entries(&date).unwrap().len(), 3);
+        assert_eq!(database.get_intake_profile().unwrap(), profile);
     }
 }
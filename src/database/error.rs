@@ -0,0 +1,71 @@
+use rusqlite::{Error as RusqliteError, ErrorCode};
+
+#[derive(Debug)]
+pub enum DatabaseError {
+    Io(RusqliteError),
+    FileSystem(std::io::Error),
+    Constraint(RusqliteError),
+    Parse(String),
+    MissingRow(String),
+    NewerSchema { found: u32, supported: u32 },
+    ServingInUse { serving: String, log_entries: usize },
+    Sqlite(RusqliteError),
+}
+
+impl std::fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DatabaseError::Io(error) => write!(f, "Unable to access the database: {}", error),
+            DatabaseError::FileSystem(error) => {
+                write!(f, "Unable to access the database location: {}", error)
+            }
+            DatabaseError::Constraint(error) => write!(f, "Constraint violation: {}", error),
+            DatabaseError::Parse(message) => write!(f, "Invalid value in database: {}", message),
+            DatabaseError::MissingRow(message) => write!(f, "Not found: {}", message),
+            DatabaseError::NewerSchema { found, supported } => write!(
+                f,
+                "The database uses schema version {} but this build only supports up to version {}. \
+                Please update the application before opening it.",
+                found, supported
+            ),
+            DatabaseError::ServingInUse {
+                serving,
+                log_entries,
+            } => write!(
+                f,
+                "The serving {} is used by {} log entries and cannot be removed.",
+                serving, log_entries
+            ),
+            DatabaseError::Sqlite(error) => write!(f, "Database error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+impl From<RusqliteError> for DatabaseError {
+    fn from(error: RusqliteError) -> Self {
+        match &error {
+            RusqliteError::SqliteFailure(failure, _) => match failure.code {
+                ErrorCode::ConstraintViolation => DatabaseError::Constraint(error),
+                ErrorCode::CannotOpen
+                | ErrorCode::DatabaseBusy
+                | ErrorCode::DatabaseLocked
+                | ErrorCode::DatabaseCorrupt
+                | ErrorCode::NotADatabase
+                | ErrorCode::DiskFull
+                | ErrorCode::ReadOnly
+                | ErrorCode::PermissionDenied
+                | ErrorCode::SystemIoFailure => DatabaseError::Io(error),
+                _ => DatabaseError::Sqlite(error),
+            },
+            RusqliteError::QueryReturnedNoRows => {
+                DatabaseError::MissingRow("query returned no rows".to_owned())
+            }
+            RusqliteError::FromSqlConversionFailure(..)
+            | RusqliteError::IntegralValueOutOfRange(..)
+            | RusqliteError::InvalidColumnType(..) => DatabaseError::Parse(error.to_string()),
+            _ => DatabaseError::Sqlite(error),
+        }
+    }
+}
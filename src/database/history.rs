@@ -0,0 +1,240 @@
+use rusqlite::types::Value;
+use rusqlite::{Connection, Error as RusqliteError};
+
+// Older commands are dropped once the undo stack grows past this.
+const HISTORY_LIMIT: usize = 100;
+
+// The rows of `table` matching the SQL `condition` that a command may touch.
+pub struct Scope {
+    table: &'static str,
+    condition: String,
+}
+
+impl Scope {
+    pub fn new(table: &'static str, condition: String) -> Self {
+        Scope { table, condition }
+    }
+}
+
+// Copy of the rows of one table, each with its rowid.
+struct TableRows {
+    table: &'static str,
+    columns: Vec<String>,
+    rows: Vec<(i64, Vec<Value>)>,
+}
+
+// A command only stores the rows it touched before and after running. Undoing
+// and redoing swap one set for the other, which also brings back rows removed
+// by `ON DELETE CASCADE` as long as their table is part of the scopes.
+pub struct Command {
+    description: String,
+    before: Vec<TableRows>,
+    after: Vec<TableRows>,
+}
+
+// A command whose mutation is still running.
+pub struct PendingCommand {
+    description: String,
+    scopes: Vec<Scope>,
+    before: Vec<TableRows>,
+}
+
+impl Command {
+    // Captures the scopes before a mutation, call `finish` once it succeeded.
+    pub fn begin(
+        connection: &Connection,
+        description: String,
+        scopes: Vec<Scope>,
+    ) -> Result<PendingCommand, RusqliteError> {
+        let before = capture(connection, &scopes)?;
+        Ok(PendingCommand {
+            description,
+            scopes,
+            before,
+        })
+    }
+
+    // For inserts, where the scopes are only known afterwards and were empty before.
+    pub fn inserted(
+        connection: &Connection,
+        description: String,
+        scopes: Vec<Scope>,
+    ) -> Result<Command, RusqliteError> {
+        let before = scopes
+            .iter()
+            .map(|scope| TableRows {
+                table: scope.table,
+                columns: Vec::new(),
+                rows: Vec::new(),
+            })
+            .collect();
+        Ok(Command {
+            description,
+            before,
+            after: capture(connection, &scopes)?,
+        })
+    }
+
+    fn undo(&self, connection: &Connection) -> Result<(), RusqliteError> {
+        restore(connection, &self.after, &self.before)
+    }
+
+    fn redo(&self, connection: &Connection) -> Result<(), RusqliteError> {
+        restore(connection, &self.before, &self.after)
+    }
+}
+
+impl PendingCommand {
+    pub fn finish(self, connection: &Connection) -> Result<Command, RusqliteError> {
+        Ok(Command {
+            description: self.description,
+            before: self.before,
+            after: capture(connection, &self.scopes)?,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<Command>,
+    redo_stack: Vec<Command>,
+}
+
+impl History {
+    pub fn push(&mut self, command: Command) {
+        self.redo_stack.clear();
+        self.undo_stack.push(command);
+        if self.undo_stack.len() > HISTORY_LIMIT {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    pub fn undo_description(&self) -> Option<&str> {
+        self.undo_stack
+            .last()
+            .map(|command| command.description.as_str())
+    }
+
+    pub fn redo_description(&self) -> Option<&str> {
+        self.redo_stack
+            .last()
+            .map(|command| command.description.as_str())
+    }
+
+    // Reverts the latest command and returns its description, if there was one.
+    pub fn undo(&mut self, connection: &Connection) -> Result<Option<String>, RusqliteError> {
+        let Some(command) = self.undo_stack.pop() else {
+            return Ok(None);
+        };
+        if let Err(error) = command.undo(connection) {
+            self.undo_stack.push(command);
+            return Err(error);
+        }
+        let description = command.description.clone();
+        self.redo_stack.push(command);
+        Ok(Some(description))
+    }
+
+    // Applies the latest undone command again and returns its description, if there was one.
+    pub fn redo(&mut self, connection: &Connection) -> Result<Option<String>, RusqliteError> {
+        let Some(command) = self.redo_stack.pop() else {
+            return Ok(None);
+        };
+        if let Err(error) = command.redo(connection) {
+            self.redo_stack.push(command);
+            return Err(error);
+        }
+        let description = command.description.clone();
+        self.undo_stack.push(command);
+        Ok(Some(description))
+    }
+}
+
+fn capture(connection: &Connection, scopes: &[Scope]) -> Result<Vec<TableRows>, RusqliteError> {
+    let mut data: Vec<TableRows> = Vec::new();
+    for scope in scopes {
+        let query = format!(
+            "SELECT rowid, * FROM {} WHERE {};",
+            scope.table, scope.condition
+        );
+        let mut statement = connection.prepare(&query)?;
+        let columns: Vec<String> = statement
+            .column_names()
+            .iter()
+            .skip(1)
+            .map(|name| name.to_string())
+            .collect();
+
+        let mut rows = statement.query([])?;
+        let mut table_rows: Vec<(i64, Vec<Value>)> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut values: Vec<Value> = Vec::with_capacity(columns.len());
+            for index in 0..columns.len() {
+                values.push(row.get(index + 1)?);
+            }
+            table_rows.push((row.get(0)?, values));
+        }
+
+        data.push(TableRows {
+            table: scope.table,
+            columns,
+            rows: table_rows,
+        });
+    }
+
+    Ok(data)
+}
+
+// Replaces the `current` rows with the `target` rows. Foreign keys are switched
+// off meanwhile, the snapshots already contain every row a cascade would touch.
+fn restore(
+    connection: &Connection,
+    current: &[TableRows],
+    target: &[TableRows],
+) -> Result<(), RusqliteError> {
+    connection.execute("PRAGMA foreign_keys = OFF", [])?;
+    let result = replace_rows(connection, current, target);
+    connection.execute("PRAGMA foreign_keys = ON", [])?;
+    result
+}
+
+fn replace_rows(
+    connection: &Connection,
+    current: &[TableRows],
+    target: &[TableRows],
+) -> Result<(), RusqliteError> {
+    let transaction = connection.unchecked_transaction()?;
+
+    for table_rows in current {
+        let query = format!("DELETE FROM {} WHERE rowid = ?1;", table_rows.table);
+        for (rowid, _) in &table_rows.rows {
+            transaction.execute(&query, [rowid])?;
+        }
+    }
+
+    for table_rows in target {
+        // Tables with an `id` column store the rowid in it already.
+        let has_id = table_rows.columns.iter().any(|column| column == "id");
+        let mut columns = table_rows.columns.clone();
+        if !has_id {
+            columns.insert(0, "rowid".to_owned());
+        }
+        let placeholders: Vec<String> = (1..=columns.len())
+            .map(|index| format!("?{}", index))
+            .collect();
+        let query = format!(
+            "INSERT INTO {} ({}) VALUES ({});",
+            table_rows.table,
+            columns.join(", "),
+            placeholders.join(", ")
+        );
+
+        for (rowid, values) in &table_rows.rows {
+            let rowid = Value::Integer(*rowid);
+            let params = (!has_id).then_some(&rowid).into_iter().chain(values);
+            transaction.execute(&query, rusqlite::params_from_iter(params))?;
+        }
+    }
+
+    transaction.commit()
+}
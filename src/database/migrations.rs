@@ -0,0 +1,411 @@
+use super::DatabaseError;
+use rusqlite::{Connection, Error as RusqliteError};
+
+struct Migration {
+    version: u32,
+    description: &'static str,
+    sql: &'static str,
+}
+
+// Migrations are applied in order, each one inside its own transaction.
+// Never edit a migration that has already shipped, append a new one instead.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: "
+        CREATE TABLE IF NOT EXISTS ingredients (
+            id INTEGER PRIMARY KEY,
+            name TEXT,
+            brand TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS categories (
+            id INTEGER PRIMARY KEY,
+            name TEXT,
+            icon_name TEXT,
+            icon_color TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS ingredient_categories (
+            ingredient_id INTEGER,
+            category_id INTEGER,
+            FOREIGN KEY(ingredient_id) REFERENCES ingredients(id) ON DELETE CASCADE
+            FOREIGN KEY(category_id) REFERENCES categories(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS nutritional_info (
+            id INTEGER PRIMARY KEY,
+            default_amount REAL,
+            default_unit INTEGER,
+            kilocalories REAL,
+            ingredient_id INTEGER,
+            FOREIGN KEY(ingredient_id) REFERENCES ingredients(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS protein_sets (
+            id INTEGER PRIMARY KEY,
+            nutrition_info_id INTEGER,
+        --essentials
+            histidine REAL,
+            isoleucine REAL,
+            leucine REAL,
+            lysine REAL,
+            methionine REAL,
+            phenylalanine REAL,
+            threonine REAL,
+            tryptophan REAL,
+            valine REAL,
+        --non-essentials
+            alanine REAL,
+            arginine REAL,
+            asparagine REAL,
+            aspartic_acid REAL,
+            cysteine REAL,
+            glutamic_acid REAL,
+            glutamine REAL,
+            glycine REAL,
+            proline REAL,
+            serine REAL,
+            tyrosine REAL,
+            FOREIGN KEY(nutrition_info_id) REFERENCES nutritional_info(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS fat_sets (
+            id INTEGER PRIMARY KEY,
+            nutrition_info_id INTEGER,
+            saturated REAL,
+            monounsaturated REAL,
+            polyunsaturated REAL,
+            FOREIGN KEY(nutrition_info_id) REFERENCES nutritional_info(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS carbohydrate_sets (
+            id INTEGER PRIMARY KEY,
+            nutrition_info_id INTEGER,
+            starch REAL,
+            fiber REAL,
+            sugars REAL,
+            sugar_alcohols REAL,
+            FOREIGN KEY(nutrition_info_id) REFERENCES nutritional_info(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS vitamin_sets (
+            id INTEGER PRIMARY KEY,
+            nutrition_info_id INTEGER,
+            vitamin_a REAL,
+            vitamin_b1 REAL,
+            vitamin_b2 REAL,
+            vitamin_b3 REAL,
+            vitamin_b5 REAL,
+            vitamin_b6 REAL,
+            vitamin_b9 REAL,
+            vitamin_b12 REAL,
+            vitamin_c REAL,
+            vitamin_d REAL,
+            vitamin_e REAL,
+            vitamin_k REAL,
+            betaine REAL,
+            choline REAL,
+            FOREIGN KEY(nutrition_info_id) REFERENCES nutritional_info(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS mineral_sets (
+            id INTEGER PRIMARY KEY,
+            nutrition_info_id INTEGER,
+            calcium REAL,
+            copper REAL,
+            iron REAL,
+            magnesium REAL,
+            manganese REAL,
+            phosphorus REAL,
+            potassium REAL,
+            selenium REAL,
+            sodium REAL,
+            zinc REAL,
+            FOREIGN KEY(nutrition_info_id) REFERENCES nutritional_info(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS daily_logs (
+            id INTEGER PRIMARY KEY,
+            date TEXT,
+            ingredient_id INTEGER,
+            fraction REAL,
+            FOREIGN KEY(ingredient_id) REFERENCES ingredients(id) ON DELETE CASCADE
+        );
+    ",
+    },
+    Migration {
+        version: 2,
+        description: "daily nutrition goals",
+        sql: "
+        CREATE TABLE goals (
+            id INTEGER PRIMARY KEY,
+            nutrient INTEGER NOT NULL,
+            weekday INTEGER,
+            target REAL NOT NULL
+        );
+
+        CREATE UNIQUE INDEX goals_nutrient_weekday ON goals (nutrient, IFNULL(weekday, -1));
+    ",
+    },
+    Migration {
+        version: 3,
+        description: "meal slots and time of day for log entries",
+        sql: "
+        CREATE TABLE meals (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            position INTEGER NOT NULL
+        );
+
+        INSERT INTO meals (name, position)
+            VALUES ('Breakfast', 0), ('Lunch', 1), ('Dinner', 2), ('Snack', 3);
+
+        ALTER TABLE daily_logs
+            ADD COLUMN meal_id INTEGER REFERENCES meals(id) ON DELETE SET NULL;
+        ALTER TABLE daily_logs ADD COLUMN time TEXT;
+    ",
+    },
+    Migration {
+        version: 4,
+        description: "recipes composed of ingredients",
+        sql: "
+        CREATE TABLE recipes (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            servings REAL NOT NULL
+        );
+
+        CREATE TABLE recipe_items (
+            id INTEGER PRIMARY KEY,
+            recipe_id INTEGER NOT NULL,
+            ingredient_id INTEGER NOT NULL,
+            amount REAL NOT NULL,
+            unit INTEGER NOT NULL,
+            FOREIGN KEY(recipe_id) REFERENCES recipes(id) ON DELETE CASCADE,
+            FOREIGN KEY(ingredient_id) REFERENCES ingredients(id) ON DELETE CASCADE
+        );
+
+        ALTER TABLE daily_logs
+            ADD COLUMN recipe_id INTEGER REFERENCES recipes(id) ON DELETE CASCADE;
+    ",
+    },
+    Migration {
+        version: 5,
+        description: "log entry amounts with units and ingredient conversions",
+        sql: "
+        ALTER TABLE daily_logs RENAME COLUMN fraction TO amount;
+        -- Existing entries are multiples of the default amount, i.e. servings.
+        ALTER TABLE daily_logs ADD COLUMN unit INTEGER NOT NULL DEFAULT 10;
+
+        ALTER TABLE ingredients ADD COLUMN density REAL;
+        ALTER TABLE ingredients ADD COLUMN piece_weight REAL;
+    ",
+    },
+    Migration {
+        version: 6,
+        description: "archived ingredients",
+        sql: "
+        ALTER TABLE ingredients ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
+    ",
+    },
+    Migration {
+        version: 7,
+        description: "ingredient barcodes",
+        sql: "
+        ALTER TABLE ingredients ADD COLUMN barcode TEXT;
+        -- Codes that only differ in leading zeros denote the same product.
+        CREATE UNIQUE INDEX ingredients_barcode ON ingredients (ltrim(barcode, '0'));
+    ",
+    },
+    Migration {
+        version: 8,
+        description: "named servings",
+        sql: "
+        ALTER TABLE nutritional_info ADD COLUMN name TEXT NOT NULL DEFAULT '';
+        -- Entries without a serving refer to the first nutritional info.
+        ALTER TABLE daily_logs ADD COLUMN nutritional_info_id INTEGER
+            REFERENCES nutritional_info(id) ON DELETE SET NULL;
+    ",
+    },
+    Migration {
+        version: 9,
+        description: "intake profile",
+        sql: "
+        -- A single row with the sex and age group the reference intakes are chosen for.
+        CREATE TABLE intake_profile (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            sex INTEGER NOT NULL,
+            age_group INTEGER NOT NULL
+        );
+    ",
+    },
+    Migration {
+        version: 10,
+        description: "unspecified macronutrients",
+        sql: "
+        ALTER TABLE protein_sets ADD COLUMN unspecified_protein REAL NOT NULL DEFAULT 0;
+        ALTER TABLE fat_sets ADD COLUMN unspecified_fat REAL NOT NULL DEFAULT 0;
+        ALTER TABLE carbohydrate_sets ADD COLUMN unspecified_carbohydrates REAL NOT NULL DEFAULT 0;
+    ",
+    },
+];
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+pub fn schema_version(connection: &Connection) -> Result<u32, RusqliteError> {
+    connection.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+pub fn run(connection: &Connection) -> Result<(), DatabaseError> {
+    let current_version = schema_version(connection)?;
+    let latest_version = latest_version();
+
+    if current_version > latest_version {
+        return Err(DatabaseError::NewerSchema {
+            found: current_version,
+            supported: latest_version,
+        });
+    }
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > current_version)
+    {
+        log::info!(
+            "Migrating database to version {} ({})",
+            migration.version,
+            migration.description
+        );
+
+        let transaction = connection.unchecked_transaction()?;
+        transaction.execute_batch(migration.sql)?;
+        transaction.pragma_update(None, "user_version", migration.version)?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baseline_database_is_migrated_with_its_data() {
+        // Databases from before versioning have the initial schema and no version.
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(MIGRATIONS[0].sql).unwrap();
+        connection
+            .execute_batch(
+                "
+                INSERT INTO ingredients (id, name, brand) VALUES (1, 'Oats', 'Mill');
+                INSERT INTO nutritional_info (id, default_amount, default_unit, kilocalories, ingredient_id)
+                    VALUES (1, 40.0, 0, 151.0, 1);
+                INSERT INTO daily_logs (id, date, ingredient_id, fraction)
+                    VALUES (1, '2024-03-01', 1, 1.5);
+                ",
+            )
+            .unwrap();
+        assert_eq!(schema_version(&connection).unwrap(), 0);
+
+        run(&connection).unwrap();
+        assert_eq!(schema_version(&connection).unwrap(), latest_version());
+
+        let (name, brand, archived): (String, String, bool) = connection
+            .query_row(
+                "SELECT name, brand, archived FROM ingredients WHERE id = 1;",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (name.as_str(), brand.as_str(), archived),
+            ("Oats", "Mill", false)
+        );
+
+        let kilocalories: f32 = connection
+            .query_row(
+                "SELECT kilocalories FROM nutritional_info WHERE ingredient_id = 1;",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(kilocalories, 151.0);
+
+        // Old fractions are multiples of the default amount, i.e. servings.
+        let (date, amount, unit): (String, f32, u32) = connection
+            .query_row(
+                "SELECT date, amount, unit FROM daily_logs WHERE id = 1;",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((date.as_str(), amount, unit), ("2024-03-01", 1.5, 10));
+    }
+
+    #[test]
+    fn existing_breakdowns_are_kept_when_adding_unspecified_parts() {
+        let connection = Connection::open_in_memory().unwrap();
+        for migration in MIGRATIONS.iter().filter(|migration| migration.version < 10) {
+            connection.execute_batch(migration.sql).unwrap();
+        }
+        connection.pragma_update(None, "user_version", 9).unwrap();
+        connection
+            .execute_batch(
+                "
+                INSERT INTO ingredients (id, name, brand) VALUES (1, 'Oats', '');
+                INSERT INTO nutritional_info (id, default_amount, default_unit, kilocalories, ingredient_id)
+                    VALUES (1, 100.0, 0, 0, 1), (2, 100.0, 0, 0, 1);
+                INSERT INTO protein_sets VALUES
+                    (1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 12.0, 0, 0, 0, 0, 0),
+                    (2, 2, 0, 0, 1.0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2.0, 0, 0, 0, 0, 0);
+                INSERT INTO fat_sets VALUES (1, 1, 0, 9.0, 0), (2, 2, 1.0, 9.0, 0);
+                INSERT INTO carbohydrate_sets VALUES (1, 1, 60.0, 5.0, 0, 0), (2, 2, 60.0, 5.0, 2.0, 0);
+                ",
+            )
+            .unwrap();
+
+        run(&connection).unwrap();
+
+        // A hand-entered pure starch food or an oil logged only as monounsaturated
+        // looks the same as an old import, so nothing is moved.
+        let parts = |query: &str| -> Vec<(f32, f32)> {
+            let mut statement = connection.prepare(query).unwrap();
+            let rows = statement
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap();
+            rows.map(Result::unwrap).collect()
+        };
+        assert_eq!(
+            parts("SELECT glutamic_acid, unspecified_protein FROM protein_sets ORDER BY id;"),
+            [(12.0, 0.0), (2.0, 0.0)]
+        );
+        assert_eq!(
+            parts("SELECT monounsaturated, unspecified_fat FROM fat_sets ORDER BY id;"),
+            [(9.0, 0.0), (9.0, 0.0)]
+        );
+        assert_eq!(
+            parts("SELECT starch, unspecified_carbohydrates FROM carbohydrate_sets ORDER BY id;"),
+            [(60.0, 0.0), (60.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn newer_schema_is_rejected() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+
+        let result = run(&connection);
+        assert!(matches!(
+            result,
+            Err(DatabaseError::NewerSchema { found, supported })
+                if found == latest_version() + 1 && supported == latest_version()
+        ));
+    }
+}
@@ -1,7 +1,7 @@
 use super::popup::DatePickerPopup;
 use chrono::NaiveDate;
-use egui::{Area, Button, Frame, InnerResponse, Key, Order, RichText, Ui, Vec2, Widget};
-use std::collections::HashSet;
+use egui::{Area, Button, Color32, Frame, InnerResponse, Key, Order, RichText, Ui, Vec2, Widget};
+use std::collections::{HashMap, HashSet};
 
 #[derive(Default, Clone)]
 pub(crate) struct DatePickerButtonState {
@@ -20,7 +20,7 @@ pub struct DatePickerButton<'a> {
     format: String,
     highlight_weekends: bool,
     min_size: Vec2,
-    data_available: HashSet<NaiveDate>,
+    data_available: HashMap<NaiveDate, Color32>,
 }
 
 impl<'a> DatePickerButton<'a> {
@@ -36,7 +36,7 @@ impl<'a> DatePickerButton<'a> {
             format: "%Y-%m-%d".to_owned(),
             highlight_weekends: true,
             min_size: Vec2::ZERO,
-            data_available: HashSet::new(),
+            data_available: HashMap::new(),
         }
     }
 
@@ -48,41 +48,6 @@ impl<'a> DatePickerButton<'a> {
         self
     }
 
-    /// Show combo boxes in date picker popup. (Default: true)
-    #[inline]
-    pub fn combo_boxes(mut self, combo_boxes: bool) -> Self {
-        self.combo_boxes = combo_boxes;
-        self
-    }
-
-    /// Show arrows in date picker popup. (Default: true)
-    #[inline]
-    pub fn arrows(mut self, arrows: bool) -> Self {
-        self.arrows = arrows;
-        self
-    }
-
-    /// Show calendar in date picker popup. (Default: true)
-    #[inline]
-    pub fn calendar(mut self, calendar: bool) -> Self {
-        self.calendar = calendar;
-        self
-    }
-
-    /// Show calendar week in date picker popup. (Default: true)
-    #[inline]
-    pub fn calendar_week(mut self, week: bool) -> Self {
-        self.calendar_week = week;
-        self
-    }
-
-    /// Show the calendar icon on the button. (Default: true)
-    #[inline]
-    pub fn show_icon(mut self, show_icon: bool) -> Self {
-        self.show_icon = show_icon;
-        self
-    }
-
     /// Change the format shown on the button. (Default: %Y-%m-%d)
     /// See [`chrono::format::strftime`] for valid formats.
     #[inline]
@@ -105,10 +70,19 @@ impl<'a> DatePickerButton<'a> {
         self
     }
 
-    /// Set available data.
+    /// Set available data. Days with data are marked with a green dot.
     #[inline]
     pub fn with_data(mut self, data: &HashSet<NaiveDate>) -> Self {
-        self.data_available = data.clone();
+        self.data_available
+            .extend(data.iter().map(|day| (*day, Color32::GREEN)));
+        self
+    }
+
+    /// Set available data with an individual marker color per day,
+    /// overriding the color of days already passed to `with_data`.
+    #[inline]
+    pub fn with_data_colors(mut self, data: &HashMap<NaiveDate, Color32>) -> Self {
+        self.data_available.extend(data);
         self
     }
 }
@@ -123,7 +97,7 @@ impl<'a> Widget for DatePickerButton<'a> {
         let mut text = if self.show_icon {
             RichText::new(format!("{} 📆", self.selection.format(&self.format)))
         } else {
-            RichText::new(format!("{} 📆", self.selection.format(&self.format)))
+            RichText::new(self.selection.format(&self.format).to_string())
         };
         let visuals = ui.visuals().widgets.open;
         if button_state.picker_visible {
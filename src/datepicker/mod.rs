@@ -2,7 +2,7 @@ mod button;
 mod popup;
 
 pub use button::DatePickerButton;
-use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use chrono::{Datelike, Days, NaiveDate, Weekday};
 
 #[derive(Debug)]
 struct Week {
@@ -14,7 +14,7 @@ fn month_data(year: i32, month: u32) -> Vec<Week> {
     let first = NaiveDate::from_ymd_opt(year, month, 1).expect("Could not create NaiveDate");
     let mut start = first;
     while start.weekday() != Weekday::Mon {
-        start = start.checked_sub_signed(Duration::days(1)).unwrap();
+        start = start.checked_sub_days(Days::new(1)).unwrap();
     }
     let mut weeks = vec![];
     let mut week = vec![];
@@ -27,8 +27,17 @@ fn month_data(year: i32, month: u32) -> Vec<Week> {
                 days: std::mem::take(&mut week),
             });
         }
-        start = start.checked_add_signed(Duration::days(1)).unwrap();
+        start = start.checked_add_days(Days::new(1)).unwrap();
     }
 
     weeks
 }
+
+// The first and last day the calendar shows for the month of `date`, from the
+// Monday before the first of the month to the Sunday after its last day.
+pub fn month_range(date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let weeks = month_data(date.year(), date.month());
+    let first = weeks.first().and_then(|week| week.days.first()).copied();
+    let last = weeks.last().and_then(|week| week.days.last()).copied();
+    (first.unwrap_or(date), last.unwrap_or(date))
+}
@@ -2,7 +2,7 @@ use chrono::{Datelike, NaiveDate, Weekday};
 
 use egui::{Align, Button, Color32, ComboBox, Direction, Id, Layout, RichText, Ui, Vec2};
 use egui_extras::{Size, StripBuilder};
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 use super::{button::DatePickerButtonState, month_data};
 
@@ -36,7 +36,7 @@ pub(crate) struct DatePickerPopup<'a> {
     pub calendar: bool,
     pub calendar_week: bool,
     pub highlight_weekends: bool,
-    pub data_available: HashSet<NaiveDate>,
+    pub data_available: HashMap<NaiveDate, Color32>,
 }
 
 impl<'a> DatePickerPopup<'a> {
@@ -358,7 +358,9 @@ impl<'a> DatePickerPopup<'a> {
                                                             );
                                                         }
 
-                                                        if self.data_available.contains(&day) {
+                                                        if let Some(data_color) =
+                                                            self.data_available.get(&day)
+                                                        {
                                                             ui.painter().circle_filled(
                                                                 egui::pos2(
                                                                     button_response.rect.center().x
@@ -369,7 +371,7 @@ impl<'a> DatePickerPopup<'a> {
                                                                     button_response.rect.center().y,
                                                                 ),
                                                                 2.0,
-                                                                Color32::GREEN,
+                                                                *data_color,
                                                             );
                                                         }
 
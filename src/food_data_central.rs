@@ -0,0 +1,611 @@
+// Reading foods from USDA FoodData Central downloads. Both the JSON files
+// (Foundation, SR Legacy, Survey and Branded foods) and the CSV downloads,
+// a directory with food.csv, nutrient.csv and food_nutrient.csv, are supported.
+//
+// Amounts are given per 100 g in the unit of each nutrient, which is converted
+// to the units of `NUTRIENT_FIELDS`. Energy is taken in kcal when available,
+// otherwise from the Atwater factors or kJ.
+//
+// The downloads are several GB large, so foods are read one at a time and only
+// the ones matching the `ProductQuery` are kept.
+
+use crate::barcode;
+use crate::ingredients::*;
+use crate::open_food_facts::{Dump, Product, ProductQuery};
+use serde::de::{
+    DeserializeSeed, Deserializer, Error as _, IgnoredAny, MapAccess, SeqAccess, Visitor,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+// FoodData Central nutrient IDs and the nutrient they map to. Where several IDs
+// map to the same nutrient the first one present wins.
+const NUTRIENT_IDS: [(u32, &str); 54] = [
+    (1221, "histidine"),
+    (1212, "isoleucine"),
+    (1213, "leucine"),
+    (1214, "lysine"),
+    (1215, "methionine"),
+    (1217, "phenylalanine"),
+    (1211, "threonine"),
+    (1210, "tryptophan"),
+    (1219, "valine"),
+    (1222, "alanine"),
+    (1220, "arginine"),
+    (1223, "aspartic_acid"),
+    (1232, "cysteine"),
+    (1216, "cysteine"),
+    (1224, "glutamic_acid"),
+    (1225, "glycine"),
+    (1226, "proline"),
+    (1227, "serine"),
+    (1218, "tyrosine"),
+    (1258, "saturated"),
+    (1292, "monounsaturated"),
+    (1293, "polyunsaturated"),
+    (1009, "starch"),
+    (1079, "fiber"),
+    (2000, "sugars"),
+    (1063, "sugars"),
+    (1106, "vitamin_a"),
+    (1165, "vitamin_b1"),
+    (1166, "vitamin_b2"),
+    (1167, "vitamin_b3"),
+    (1170, "vitamin_b5"),
+    (1175, "vitamin_b6"),
+    (1177, "vitamin_b9"),
+    (1190, "vitamin_b9"),
+    (1178, "vitamin_b12"),
+    (1162, "vitamin_c"),
+    (1114, "vitamin_d"),
+    (1109, "vitamin_e"),
+    (1185, "vitamin_k"),
+    (1198, "betaine"),
+    (1180, "choline"),
+    (1087, "calcium"),
+    (1098, "copper"),
+    (1089, "iron"),
+    (1090, "magnesium"),
+    (1101, "manganese"),
+    (1091, "phosphorus"),
+    (1092, "potassium"),
+    (1103, "selenium"),
+    (1093, "sodium"),
+    (1095, "zinc"),
+    // Sugar alcohols are only listed one by one: sorbitol, xylitol and mannitol.
+    (1056, "sugar_alcohols"),
+    (1078, "sugar_alcohols"),
+    (1055, "sugar_alcohols"),
+];
+
+const ENERGY_KILOCALORIES: [u32; 3] = [1008, 2048, 2047];
+const ENERGY_KILOJOULES: u32 = 1062;
+const PROTEIN: u32 = 1003;
+const FAT: u32 = 1004;
+const CARBOHYDRATES: u32 = 1005;
+const FIBER: u32 = 1079;
+
+const KILOJOULES_PER_KILOCALORIE: f32 = 4.184;
+
+#[derive(Debug)]
+pub enum FoodDataError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Csv(csv::Error),
+    MissingColumn(&'static str, &'static str),
+}
+
+impl std::fmt::Display for FoodDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FoodDataError::Io(error) => write!(f, "{}", error),
+            FoodDataError::Json(error) => write!(f, "Invalid JSON: {}", error),
+            FoodDataError::Csv(error) => write!(f, "Invalid CSV: {}", error),
+            FoodDataError::MissingColumn(file, column) => {
+                write!(f, "{} has no column {}", file, column)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FoodDataError {}
+
+impl From<std::io::Error> for FoodDataError {
+    fn from(error: std::io::Error) -> Self {
+        FoodDataError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for FoodDataError {
+    fn from(error: serde_json::Error) -> Self {
+        FoodDataError::Json(error)
+    }
+}
+
+impl From<csv::Error> for FoodDataError {
+    fn from(error: csv::Error) -> Self {
+        FoodDataError::Csv(error)
+    }
+}
+
+// Amount and unit name of every nutrient of a food, by nutrient ID.
+type Nutrients = HashMap<u32, (f32, String)>;
+
+// A food of the JSON downloads, with only the fields that are imported.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Food {
+    #[serde(default)]
+    description: String,
+    brand_name: Option<String>,
+    brand_owner: Option<String>,
+    gtin_upc: Option<String>,
+    #[serde(default)]
+    food_nutrients: Vec<FoodNutrient>,
+}
+
+#[derive(Deserialize)]
+struct FoodNutrient {
+    nutrient: Option<NutrientInfo>,
+    amount: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NutrientInfo {
+    id: Option<u32>,
+    unit_name: Option<String>,
+}
+
+// Reading ends with this error once enough foods were found, serde has no
+// other way to leave a document half way.
+const STOPPED: &str = "reading stopped";
+
+// Keeps the foods matching `query` while they are deserialized one by one.
+struct FoodReader<'a> {
+    query: &'a ProductQuery,
+    dump: Dump,
+    stopped: bool,
+}
+
+impl FoodReader<'_> {
+    // Returns whether reading should go on.
+    fn read(&mut self, food: Food) -> bool {
+        if self.query.is_cancelled() {
+            return false;
+        }
+        let brand = food
+            .brand_name
+            .as_deref()
+            .filter(|brand| !brand.is_empty())
+            .or(food.brand_owner.as_deref())
+            .unwrap_or("");
+        let code = food.gtin_upc.as_deref().unwrap_or("");
+        if !self.query.matches(code, &food.description, brand) {
+            return true;
+        }
+
+        let nutrients: Nutrients = food
+            .food_nutrients
+            .into_iter()
+            .filter_map(|food_nutrient| {
+                let nutrient = food_nutrient.nutrient?;
+                Some((nutrient.id?, (food_nutrient.amount?, nutrient.unit_name?)))
+            })
+            .collect();
+        self.dump.add(
+            product(code, &food.description, brand, &nutrients),
+            self.query,
+        )
+    }
+}
+
+// The top level object, each value is a list of foods.
+impl<'de> Visitor<'de> for &mut FoodReader<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("lists of foods by data type")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while map.next_key::<IgnoredAny>()?.is_some() {
+            map.next_value_seed(FoodList(&mut *self))?;
+        }
+        Ok(())
+    }
+}
+
+struct FoodList<'a, 'b>(&'a mut FoodReader<'b>);
+
+impl<'de> DeserializeSeed<'de> for FoodList<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FoodList<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a list of foods")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(food) = seq.next_element::<Food>()? {
+            if !self.0.read(food) {
+                self.0.stopped = true;
+                return Err(A::Error::custom(STOPPED));
+            }
+        }
+        Ok(())
+    }
+}
+
+// The JSON downloads hold one list of foods, named after the data type.
+pub fn read_json(reader: impl Read, query: &ProductQuery) -> Result<Dump, FoodDataError> {
+    let mut food_reader = FoodReader {
+        query,
+        dump: Dump::default(),
+        stopped: false,
+    };
+    let result = serde_json::Deserializer::from_reader(reader).deserialize_map(&mut food_reader);
+    if !food_reader.stopped {
+        result?;
+    }
+    Ok(food_reader.dump)
+}
+
+// Reads the CSV files in `directory`. Brands and barcodes come from
+// branded_food.csv, if the download has one. The nutrients are only kept for
+// the foods matching `query`.
+pub fn read_csv(directory: &Path, query: &ProductQuery) -> Result<Dump, FoodDataError> {
+    let open = |name: &'static str| -> Result<_, FoodDataError> {
+        let reader = csv::Reader::from_path(directory.join(name))?;
+        Ok(reader)
+    };
+    let column = |reader: &mut csv::Reader<std::fs::File>,
+                  file: &'static str,
+                  name: &'static str|
+     -> Result<usize, FoodDataError> {
+        reader
+            .headers()?
+            .iter()
+            .position(|header| header == name)
+            .ok_or(FoodDataError::MissingColumn(file, name))
+    };
+
+    // Brand and barcode by food.
+    let mut brands: HashMap<u32, (String, String)> = HashMap::new();
+    if directory.join("branded_food.csv").exists() {
+        let mut reader = open("branded_food.csv")?;
+        let (fdc_id, brand_owner, gtin_upc) = (
+            column(&mut reader, "branded_food.csv", "fdc_id")?,
+            column(&mut reader, "branded_food.csv", "brand_owner")?,
+            column(&mut reader, "branded_food.csv", "gtin_upc")?,
+        );
+        for record in reader.records() {
+            let record = record?;
+            if let Some(id) = record.get(fdc_id).and_then(|id| id.parse::<u32>().ok()) {
+                let text = |index: usize| record.get(index).unwrap_or("").to_owned();
+                brands.insert(id, (text(brand_owner), text(gtin_upc)));
+            }
+        }
+    }
+
+    // One more food than the limit tells whether there are further matches.
+    let mut foods: Vec<(u32, String)> = Vec::new();
+    let mut reader = open("food.csv")?;
+    let (fdc_id, description) = (
+        column(&mut reader, "food.csv", "fdc_id")?,
+        column(&mut reader, "food.csv", "description")?,
+    );
+    for record in reader.records() {
+        if query.is_cancelled() || foods.len() > query.limit() {
+            break;
+        }
+        let record = record?;
+        let Some(id) = record.get(fdc_id).and_then(|id| id.parse::<u32>().ok()) else {
+            continue;
+        };
+        let (brand, code) = brands
+            .get(&id)
+            .map_or(("", ""), |(brand, code)| (brand.as_str(), code.as_str()));
+        let name = record.get(description).unwrap_or("");
+        if query.matches(code, name, brand) {
+            foods.push((id, name.to_owned()));
+        }
+    }
+
+    let mut units: HashMap<u32, String> = HashMap::new();
+    let mut reader = open("nutrient.csv")?;
+    let (id, unit) = (
+        column(&mut reader, "nutrient.csv", "id")?,
+        column(&mut reader, "nutrient.csv", "unit_name")?,
+    );
+    for record in reader.records() {
+        let record = record?;
+        if let (Some(id), Some(unit)) = (
+            record.get(id).and_then(|id| id.parse::<u32>().ok()),
+            record.get(unit),
+        ) {
+            units.insert(id, unit.to_owned());
+        }
+    }
+
+    let mut nutrients: HashMap<u32, Nutrients> = foods
+        .iter()
+        .map(|(id, _)| (*id, Nutrients::new()))
+        .collect();
+    let mut reader = open("food_nutrient.csv")?;
+    let (fdc_id, nutrient_id, amount) = (
+        column(&mut reader, "food_nutrient.csv", "fdc_id")?,
+        column(&mut reader, "food_nutrient.csv", "nutrient_id")?,
+        column(&mut reader, "food_nutrient.csv", "amount")?,
+    );
+    for record in reader.records() {
+        if query.is_cancelled() {
+            break;
+        }
+        let record = record?;
+        let Some(food_nutrients) = record
+            .get(fdc_id)
+            .and_then(|id| id.parse::<u32>().ok())
+            .and_then(|id| nutrients.get_mut(&id))
+        else {
+            continue;
+        };
+        let number = |index: usize| record.get(index).and_then(|cell| cell.parse::<f32>().ok());
+        if let (Some(nutrient_id), Some(amount)) = (
+            record
+                .get(nutrient_id)
+                .and_then(|id| id.parse::<u32>().ok()),
+            number(amount),
+        ) {
+            if let Some(unit) = units.get(&nutrient_id) {
+                food_nutrients.insert(nutrient_id, (amount, unit.clone()));
+            }
+        }
+    }
+
+    let mut dump = Dump::default();
+    for (id, name) in &foods {
+        let (brand, code) = brands
+            .get(id)
+            .map_or(("", ""), |(brand, code)| (brand.as_str(), code.as_str()));
+        if !dump.add(product(code, name, brand, &nutrients[id]), query) {
+            break;
+        }
+    }
+
+    Ok(dump)
+}
+
+fn grams_per_unit(unit: &str) -> Option<f32> {
+    match unit.to_lowercase().as_str() {
+        "g" => Some(1.0),
+        "mg" => Some(1e-3),
+        "ug" | "µg" | "mcg" => Some(1e-6),
+        _ => None,
+    }
+}
+
+// Builds the ingredient of one food, `None` without a name or nutrients.
+fn product(code: &str, name: &str, brand: &str, nutrients: &Nutrients) -> Option<Product> {
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let grams = |id: u32| {
+        nutrients
+            .get(&id)
+            .and_then(|(amount, unit)| Some(amount * grams_per_unit(unit)?))
+            .filter(|grams| grams.is_finite() && *grams >= 0.0)
+    };
+
+    let mut nutritional_info = NutritionalInfo {
+        default_amount: 100.0,
+        default_unit: Unit::Grams,
+        ..Default::default()
+    };
+    let mut found = false;
+
+    let kilocalories = ENERGY_KILOCALORIES
+        .iter()
+        .find_map(|id| {
+            nutrients
+                .get(id)
+                .filter(|(_, unit)| unit.eq_ignore_ascii_case("kcal"))
+        })
+        .map(|(amount, _)| *amount)
+        .or_else(|| {
+            nutrients
+                .get(&ENERGY_KILOJOULES)
+                .map(|(amount, _)| amount / KILOJOULES_PER_KILOCALORIE)
+        });
+    if let Some(kilocalories) = kilocalories {
+        nutritional_info.kilocalories = kilocalories;
+        found = true;
+    }
+
+    let mut set_fields: Vec<&str> = Vec::new();
+    for (id, field) in NUTRIENT_IDS {
+        let Some(grams) = grams(id) else {
+            continue;
+        };
+        // The sugar alcohols are summed up, everything else is set once.
+        if field != "sugar_alcohols" && set_fields.contains(&field) {
+            continue;
+        }
+        if let (Some(target), Some(units_per_gram)) = (
+            nutritional_info.nutrient_mut(field),
+            nutrient_units_per_gram(field),
+        ) {
+            *target += grams * units_per_gram;
+            set_fields.push(field);
+            found = true;
+        }
+    }
+
+    // Carbohydrates by difference include the fiber.
+    let carbohydrates = grams(CARBOHYDRATES)
+        .map(|carbohydrates| (carbohydrates - grams(FIBER).unwrap_or(0.0)).max(0.0));
+    found |= grams(PROTEIN).is_some() || grams(FAT).is_some() || carbohydrates.is_some();
+    nutritional_info.complete_totals(grams(PROTEIN), grams(FAT), carbohydrates);
+
+    if !found {
+        return None;
+    }
+
+    Some(Product {
+        code: code.trim().to_owned(),
+        ingredient: Ingredient {
+            id: 0,
+            name: name.to_owned(),
+            brand: brand.trim().to_owned(),
+            barcode: barcode::normalize(code).ok(),
+            categories: Vec::new(),
+            nutritional_info: vec![nutritional_info],
+            conversions: Conversions::default(),
+            archived: false,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3 * expected.abs().max(1.0),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn json_foods_map_nutrient_ids_and_units() {
+        let document = r#"{
+            "FoundationFoods": [{
+                "fdcId": 2346396,
+                "description": "Egg, whole, raw",
+                "foodNutrients": [
+                    { "nutrient": { "id": 1003, "unitName": "g" }, "amount": 12.4 },
+                    { "nutrient": { "id": 1004, "unitName": "g" }, "amount": 9.96 },
+                    { "nutrient": { "id": 1005, "unitName": "g" }, "amount": 0.96 },
+                    { "nutrient": { "id": 2048, "unitName": "kcal" }, "amount": 148 },
+                    { "nutrient": { "id": 1062, "unitName": "kJ" }, "amount": 600 },
+                    { "nutrient": { "id": 1213, "unitName": "g" }, "amount": 1.07 },
+                    { "nutrient": { "id": 1216, "unitName": "g" }, "amount": 0.27 },
+                    { "nutrient": { "id": 1093, "unitName": "mg" }, "amount": 129 },
+                    { "nutrient": { "id": 1178, "unitName": "µg" }, "amount": 1.02 },
+                    { "nutrient": { "id": 1103, "unitName": "UG" }, "amount": 31.1 },
+                    { "nutrient": { "id": 1110, "unitName": "IU" }, "amount": 82 },
+                    { "nutrient": { "id": 1180, "unitName": "mg" }, "amount": 335 }
+                ]
+            }, {
+                "fdcId": 2,
+                "description": "Chewing gum, sugar free",
+                "foodNutrients": [
+                    { "nutrient": { "id": 1056, "unitName": "g" }, "amount": 40.0 },
+                    { "nutrient": { "id": 1078, "unitName": "g" }, "amount": 20.0 },
+                    { "nutrient": { "id": 1055, "unitName": "g" }, "amount": 1.5 }
+                ]
+            }, {
+                "fdcId": 1,
+                "description": "No nutrients",
+                "foodNutrients": []
+            }]
+        }"#;
+        let dump = read_json(document.as_bytes(), &ProductQuery::new("", 10)).unwrap();
+        assert_eq!(dump.skipped, 1);
+        assert_eq!(dump.products.len(), 2);
+
+        let info = &dump.products[0].ingredient.nutritional_info[0];
+        assert_eq!(info.kilocalories, 148.0);
+        let proteins = &info.macronutrients.proteins;
+        assert_close(proteins.essential_amino_acids.leucine, 1.07);
+        assert_close(proteins.non_essential_amino_acids.cysteine, 0.27);
+        assert_close(proteins.total_proteins(), 12.4);
+        // The rest of the protein is not made up of any amino acid.
+        assert_eq!(proteins.non_essential_amino_acids.glutamic_acid, 0.0);
+        assert_close(proteins.unspecified, 12.4 - 1.07 - 0.27);
+        assert_close(info.macronutrients.fats.total_fats(), 9.96);
+        assert_close(info.macronutrients.carbohydrates.net_carbs(), 0.96);
+        let micronutrients = &info.micronutrients;
+        assert_close(micronutrients.minerals.sodium, 129.0);
+        assert_close(micronutrients.vitamins.vitamin_b12, 1.02);
+        assert_close(micronutrients.minerals.selenium, 31.1);
+        assert_eq!(micronutrients.vitamins.vitamin_d, 0.0);
+        assert_close(micronutrients.vitamins.choline, 335.0);
+
+        // Sorbitol, xylitol and mannitol add up.
+        let gum = &dump.products[1].ingredient.nutritional_info[0];
+        assert_close(gum.macronutrients.carbohydrates.sugar_alcohols, 61.5);
+    }
+
+    #[test]
+    fn json_foods_are_filtered_while_reading_and_stop_at_the_limit() {
+        let food = |description: &str| {
+            format!(
+                r#"{{ "description": "{}", "foodNutrients": [{{ "nutrient": {{ "id": 1003, "unitName": "g" }}, "amount": 3.0 }}] }}"#,
+                description
+            )
+        };
+        // The document is cut off after the foods that are needed.
+        let document = format!(
+            r#"{{ "SRLegacyFoods": [{}, {}, {}, {}, "#,
+            food("Milk, whole"),
+            food("Bread, white"),
+            food("Milk, skim"),
+            food("Milk, buttermilk"),
+        );
+
+        let dump = read_json(document.as_bytes(), &ProductQuery::new("MILK", 2)).unwrap();
+        let names: Vec<&str> = dump
+            .products
+            .iter()
+            .map(|product| product.ingredient.name.as_str())
+            .collect();
+        assert_eq!(names, ["Milk, whole", "Milk, skim"]);
+        assert!(dump.truncated);
+
+        assert!(read_json(document.as_bytes(), &ProductQuery::new("milk", 5)).is_err());
+    }
+
+    #[test]
+    fn csv_download_joins_foods_nutrients_and_brands() {
+        let directory =
+            std::env::temp_dir().join(format!("sophross-food-data-central-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let files = [
+            ("nutrient.csv", "\"id\",\"name\",\"unit_name\",\"nutrient_nbr\"\n\"1003\",\"Protein\",\"G\",\"203\"\n\"1008\",\"Energy\",\"KCAL\",\"208\"\n\"1087\",\"Calcium, Ca\",\"MG\",\"301\"\n"),
+            ("food_nutrient.csv", "\"id\",\"fdc_id\",\"nutrient_id\",\"amount\"\n\"1\",\"10\",\"1003\",\"3.3\"\n\"2\",\"10\",\"1008\",\"61\"\n\"3\",\"10\",\"1087\",\"113\"\n"),
+            ("food.csv", "\"fdc_id\",\"data_type\",\"description\"\n\"10\",\"branded_food\",\"Whole milk\"\n\"11\",\"branded_food\",\"Unknown\"\n"),
+            ("branded_food.csv", "\"fdc_id\",\"brand_owner\",\"gtin_upc\"\n\"10\",\"Dairy Co\",\"012345678905\"\n"),
+        ];
+        for (name, content) in files {
+            std::fs::write(directory.join(name), content).unwrap();
+        }
+
+        let dump = read_csv(&directory, &ProductQuery::new("", 10));
+        std::fs::remove_dir_all(&directory).unwrap();
+        let dump = dump.unwrap();
+
+        assert_eq!(dump.skipped, 1);
+        let product = &dump.products[0];
+        assert_eq!(product.code, "012345678905");
+        assert_eq!(product.ingredient.brand, "Dairy Co");
+        assert_eq!(product.ingredient.barcode.as_deref(), Some("012345678905"));
+        let info = &product.ingredient.nutritional_info[0];
+        assert_eq!(info.kilocalories, 61.0);
+        assert_close(info.macronutrients.proteins.total_proteins(), 3.3);
+        assert_close(info.micronutrients.minerals.calcium, 113.0);
+    }
+}
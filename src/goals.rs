@@ -0,0 +1,258 @@
+use crate::ingredients::NutrientTotals;
+use chrono::{Datelike, NaiveDate, Weekday};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+
+// How far the logged amount may stray from a target and still count as on target.
+pub const GOAL_TOLERANCE: f32 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GoalNutrient {
+    Kilocalories,
+    Protein,
+    Fat,
+    Carbohydrates,
+    Fiber,
+    Sodium,
+    VitaminA,
+    VitaminB12,
+    VitaminC,
+    VitaminD,
+    Calcium,
+    Iron,
+    Magnesium,
+    Potassium,
+    Zinc,
+}
+
+impl ToSql for GoalNutrient {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(Value::Integer(*self as i64)))
+    }
+}
+
+impl FromSql for GoalNutrient {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let input = value.as_i64()?;
+        u32::try_from(input)
+            .ok()
+            .and_then(GoalNutrient::from_uint)
+            .ok_or(FromSqlError::OutOfRange(input))
+    }
+}
+
+impl GoalNutrient {
+    pub const ALL: [GoalNutrient; 15] = [
+        Self::Kilocalories,
+        Self::Protein,
+        Self::Fat,
+        Self::Carbohydrates,
+        Self::Fiber,
+        Self::Sodium,
+        Self::VitaminA,
+        Self::VitaminB12,
+        Self::VitaminC,
+        Self::VitaminD,
+        Self::Calcium,
+        Self::Iron,
+        Self::Magnesium,
+        Self::Potassium,
+        Self::Zinc,
+    ];
+
+    pub fn from_uint(input: u32) -> Option<Self> {
+        Self::ALL.get(input as usize).copied()
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Self::Kilocalories => "kcal",
+            Self::Protein | Self::Fat | Self::Carbohydrates | Self::Fiber => "g",
+            Self::VitaminA | Self::VitaminB12 | Self::VitaminD => "µg",
+            Self::Sodium
+            | Self::VitaminC
+            | Self::Calcium
+            | Self::Iron
+            | Self::Magnesium
+            | Self::Potassium
+            | Self::Zinc => "mg",
+        }
+    }
+
+    // Starting point for a newly set goal, taken from the adult daily values
+    // used on US nutrition labels (with the fiber taken out of the carbohydrates).
+    pub fn default_target(&self) -> f32 {
+        match self {
+            Self::Kilocalories => 2000.0,
+            Self::Protein => 50.0,
+            Self::Fat => 78.0,
+            Self::Carbohydrates => 247.0,
+            Self::Fiber => 28.0,
+            Self::Sodium => 2300.0,
+            Self::VitaminA => 900.0,
+            Self::VitaminB12 => 2.4,
+            Self::VitaminC => 90.0,
+            Self::VitaminD => 20.0,
+            Self::Calcium => 1300.0,
+            Self::Iron => 18.0,
+            Self::Magnesium => 420.0,
+            Self::Potassium => 4700.0,
+            Self::Zinc => 11.0,
+        }
+    }
+
+    // The nutrient's name in `NUTRIENT_FIELDS`, `None` for the protein, fat and
+    // carbohydrate totals, which are summed from several fields.
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            Self::Kilocalories => Some("kilocalories"),
+            Self::Protein | Self::Fat | Self::Carbohydrates => None,
+            Self::Fiber => Some("fiber"),
+            Self::Sodium => Some("sodium"),
+            Self::VitaminA => Some("vitamin_a"),
+            Self::VitaminB12 => Some("vitamin_b12"),
+            Self::VitaminC => Some("vitamin_c"),
+            Self::VitaminD => Some("vitamin_d"),
+            Self::Calcium => Some("calcium"),
+            Self::Iron => Some("iron"),
+            Self::Magnesium => Some("magnesium"),
+            Self::Potassium => Some("potassium"),
+            Self::Zinc => Some("zinc"),
+        }
+    }
+
+    pub fn amount(&self, totals: &NutrientTotals) -> f32 {
+        let macronutrients = &totals.macronutrients;
+        let vitamins = &totals.micronutrients.vitamins;
+        let minerals = &totals.micronutrients.minerals;
+        match self {
+            Self::Kilocalories => totals.kilocalories,
+            Self::Protein => macronutrients.proteins.total_proteins(),
+            Self::Fat => macronutrients.fats.total_fats(),
+            Self::Carbohydrates => macronutrients.carbohydrates.net_carbs(),
+            Self::Fiber => macronutrients.carbohydrates.fiber,
+            Self::Sodium => minerals.sodium,
+            Self::VitaminA => vitamins.vitamin_a,
+            Self::VitaminB12 => vitamins.vitamin_b12,
+            Self::VitaminC => vitamins.vitamin_c,
+            Self::VitaminD => vitamins.vitamin_d,
+            Self::Calcium => minerals.calcium,
+            Self::Iron => minerals.iron,
+            Self::Magnesium => minerals.magnesium,
+            Self::Potassium => minerals.potassium,
+            Self::Zinc => minerals.zinc,
+        }
+    }
+}
+
+impl std::fmt::Display for GoalNutrient {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Kilocalories => write!(f, "Calories"),
+            Self::Protein => write!(f, "Protein"),
+            Self::Fat => write!(f, "Fat"),
+            Self::Carbohydrates => write!(f, "Carbohydrates (net)"),
+            Self::Fiber => write!(f, "Fiber"),
+            Self::Sodium => write!(f, "Sodium"),
+            Self::VitaminA => write!(f, "Vitamin A"),
+            Self::VitaminB12 => write!(f, "Vitamin B12"),
+            Self::VitaminC => write!(f, "Vitamin C"),
+            Self::VitaminD => write!(f, "Vitamin D"),
+            Self::Calcium => write!(f, "Calcium"),
+            Self::Iron => write!(f, "Iron"),
+            Self::Magnesium => write!(f, "Magnesium"),
+            Self::Potassium => write!(f, "Potassium"),
+            Self::Zinc => write!(f, "Zinc"),
+        }
+    }
+}
+
+// A daily target for one nutrient. Goals without a weekday apply to every day,
+// unless a goal for the same nutrient and the specific weekday overrides them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Goal {
+    pub nutrient: GoalNutrient,
+    pub weekday: Option<Weekday>,
+    pub target: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoalStatus {
+    Under,
+    On,
+    Over,
+}
+
+impl GoalStatus {
+    pub fn new(amount: f32, target: f32) -> Self {
+        if amount < target * (1.0 - GOAL_TOLERANCE) {
+            Self::Under
+        } else if amount > target * (1.0 + GOAL_TOLERANCE) {
+            Self::Over
+        } else {
+            Self::On
+        }
+    }
+}
+
+// Returns the goal that applies to `nutrient` on `date`, if any.
+pub fn goal_for_day(goals: &[Goal], nutrient: GoalNutrient, date: NaiveDate) -> Option<&Goal> {
+    let weekday = date.weekday();
+    goals
+        .iter()
+        .filter(|goal| goal.nutrient == nutrient)
+        .find(|goal| goal.weekday == Some(weekday))
+        .or_else(|| {
+            goals
+                .iter()
+                .find(|goal| goal.nutrient == nutrient && goal.weekday.is_none())
+        })
+}
+
+// Returns all goals that apply on `date`, in the order of `GoalNutrient::ALL`.
+pub fn goals_for_day(goals: &[Goal], date: NaiveDate) -> Vec<&Goal> {
+    GoalNutrient::ALL
+        .iter()
+        .filter_map(|nutrient| goal_for_day(goals, *nutrient, date))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goal(nutrient: GoalNutrient, weekday: Option<Weekday>, target: f32) -> Goal {
+        Goal {
+            nutrient,
+            weekday,
+            target,
+        }
+    }
+
+    #[test]
+    fn weekday_goal_overrides_every_day_goal() {
+        let goals = [
+            goal(GoalNutrient::Kilocalories, None, 2000.0),
+            goal(GoalNutrient::Kilocalories, Some(Weekday::Sat), 2500.0),
+            goal(GoalNutrient::Protein, None, 120.0),
+        ];
+        let friday = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let saturday = NaiveDate::from_ymd_opt(2024, 3, 2).unwrap();
+
+        let targets = |date| {
+            goals_for_day(&goals, date)
+                .iter()
+                .map(|goal| goal.target)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(targets(friday), vec![2000.0, 120.0]);
+        assert_eq!(targets(saturday), vec![2500.0, 120.0]);
+    }
+
+    #[test]
+    fn status_allows_tolerance_around_target() {
+        assert_eq!(GoalStatus::new(1700.0, 2000.0), GoalStatus::Under);
+        assert_eq!(GoalStatus::new(1850.0, 2000.0), GoalStatus::On);
+        assert_eq!(GoalStatus::new(2150.0, 2000.0), GoalStatus::On);
+        assert_eq!(GoalStatus::new(2300.0, 2000.0), GoalStatus::Over);
+    }
+}
@@ -0,0 +1,550 @@
+// Import and export of ingredients as CSV files.
+//
+// Every row holds one nutritional info of an ingredient. Rows sharing the same
+// name and brand belong to the same ingredient and have to agree on its barcode,
+// categories, density and piece weight. The header row names the columns:
+//
+//   name            required
+//   brand           may be empty
+//   barcode         optional, an EAN-8, UPC-A, EAN-13 or GTIN-14 code
+//   categories      category names separated by ";", they have to exist already
+//   serving         optional name of the serving, e.g. "1 slice"
+//   default_amount  required, the amount all nutrient values refer to
+//   default_unit    required, a unit symbol: g, oz, lb, ml, l, tsp, Tbsp, cup, fl oz or pc
+//   density         optional, grams per milliliter
+//   piece_weight    optional, grams per piece
+//
+// followed by one column per nutrient, named and measured as in `NUTRIENT_FIELDS`:
+// kilocalories (kcal), the amino acids, fats and carbohydrates (g), each group
+// followed by the unspecified amount that is not broken down, then the vitamins
+// and minerals (mg or µg). Missing or empty nutrient cells count as 0.
+
+use crate::barcode;
+use crate::ingredients::*;
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::rc::Rc;
+
+pub const BASE_COLUMNS: [&str; 9] = [
+    "name",
+    "brand",
+    "barcode",
+    "categories",
+    "serving",
+    "default_amount",
+    "default_unit",
+    "density",
+    "piece_weight",
+];
+
+const CATEGORY_SEPARATOR: char = ';';
+
+// All columns of the schema, in the order they are exported.
+pub fn columns() -> Vec<&'static str> {
+    BASE_COLUMNS
+        .iter()
+        .copied()
+        .chain(NUTRIENT_FIELDS.iter().map(|(name, _)| *name))
+        .collect()
+}
+
+pub fn export(ingredients: &[Rc<Ingredient>], writer: impl Write) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(columns())?;
+
+    for ingredient in ingredients {
+        let categories = ingredient
+            .categories
+            .iter()
+            .map(|category| category.name.as_str())
+            .collect::<Vec<_>>()
+            .join(&CATEGORY_SEPARATOR.to_string());
+        let optional = |value: Option<f32>| value.map_or(String::new(), |value| value.to_string());
+
+        for nutritional_info in &ingredient.nutritional_info {
+            let mut record = vec![
+                ingredient.name.clone(),
+                ingredient.brand.clone(),
+                ingredient.barcode.clone().unwrap_or_default(),
+                categories.clone(),
+                nutritional_info.name.clone(),
+                nutritional_info.default_amount.to_string(),
+                nutritional_info.default_unit.to_string(),
+                optional(ingredient.conversions.density),
+                optional(ingredient.conversions.piece_weight),
+            ];
+            for (name, _) in NUTRIENT_FIELDS {
+                record.push(nutritional_info.nutrient(name).unwrap_or(0.0).to_string());
+            }
+            writer.write_record(record)?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+// The raw content of a CSV file, before it is mapped onto the schema.
+pub struct CsvTable {
+    pub headers: Vec<String>,
+    // Line number in the file and the cells of each row.
+    pub rows: Vec<(u64, Vec<String>)>,
+}
+
+pub fn read_table(reader: impl Read) -> Result<CsvTable, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let headers = reader
+        .headers()?
+        .iter()
+        .map(|header| header.trim().to_owned())
+        .collect();
+
+    let mut rows: Vec<(u64, Vec<String>)> = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+        rows.push((line, record.iter().map(str::to_owned).collect()));
+    }
+
+    Ok(CsvTable { headers, rows })
+}
+
+// For each column of `columns()`, the index of the file column that provides it.
+// Headers matching a column name are picked automatically.
+pub fn auto_mapping(headers: &[String]) -> Vec<Option<usize>> {
+    columns()
+        .iter()
+        .map(|column| {
+            headers
+                .iter()
+                .position(|header| header.eq_ignore_ascii_case(column))
+        })
+        .collect()
+}
+
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+pub struct ImportedIngredient {
+    pub ingredient: Ingredient,
+    // Whether an ingredient with the same name and brand or the same barcode
+    // exists already.
+    pub duplicate: bool,
+}
+
+pub struct ImportPreview {
+    pub ingredients: Vec<ImportedIngredient>,
+    pub errors: Vec<RowError>,
+}
+
+// Turns the rows of `table` into ingredients. Rows that cannot be read are
+// reported and left out, the remaining rows are still imported.
+pub fn preview(
+    table: &CsvTable,
+    mapping: &[Option<usize>],
+    categories: &[Category],
+    existing: &[Rc<Ingredient>],
+) -> ImportPreview {
+    let existing_keys: HashSet<(String, String)> = existing
+        .iter()
+        .map(|ingredient| duplicate_key(&ingredient.name, &ingredient.brand))
+        .collect();
+    let existing_barcodes: HashSet<&str> = existing
+        .iter()
+        .filter_map(|ingredient| ingredient.barcode.as_deref())
+        .map(barcode_key)
+        .collect();
+
+    let mut ingredients: Vec<ImportedIngredient> = Vec::new();
+    let mut errors: Vec<RowError> = Vec::new();
+    for (line, cells) in &table.rows {
+        if cells.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+        if let Err(message) = add_row(
+            &mut ingredients,
+            cells,
+            mapping,
+            categories,
+            &existing_keys,
+            &existing_barcodes,
+        ) {
+            errors.push(RowError {
+                line: *line,
+                message,
+            });
+        }
+    }
+
+    ImportPreview {
+        ingredients,
+        errors,
+    }
+}
+
+// Adds a row to the ingredients read so far, either as a new ingredient or as
+// another nutritional info of an earlier one.
+fn add_row(
+    ingredients: &mut Vec<ImportedIngredient>,
+    cells: &[String],
+    mapping: &[Option<usize>],
+    categories: &[Category],
+    existing_keys: &HashSet<(String, String)>,
+    existing_barcodes: &HashSet<&str>,
+) -> Result<(), String> {
+    let ingredient = parse_row(cells, mapping, categories)?;
+    let key = duplicate_key(&ingredient.name, &ingredient.brand);
+
+    if let Some(imported) = ingredients.iter_mut().find(|imported| {
+        duplicate_key(&imported.ingredient.name, &imported.ingredient.brand) == key
+    }) {
+        let differences = differences(&imported.ingredient, &ingredient);
+        if !differences.is_empty() {
+            return Err(format!(
+                "conflicts with an earlier row of \"{}\" in {}",
+                ingredient.name,
+                differences.join(", ")
+            ));
+        }
+        imported
+            .ingredient
+            .nutritional_info
+            .extend(ingredient.nutritional_info);
+        return Ok(());
+    }
+
+    let barcode = ingredient.barcode.as_deref().map(barcode_key);
+    if let Some(other) = ingredients.iter().find(|imported| {
+        barcode.is_some() && imported.ingredient.barcode.as_deref().map(barcode_key) == barcode
+    }) {
+        return Err(format!(
+            "barcode \"{}\" is already used by \"{}\"",
+            ingredient.barcode.unwrap_or_default(),
+            other.ingredient.name
+        ));
+    }
+
+    ingredients.push(ImportedIngredient {
+        duplicate: existing_keys.contains(&key)
+            || barcode.is_some_and(|barcode| existing_barcodes.contains(barcode)),
+        ingredient,
+    });
+    Ok(())
+}
+
+fn duplicate_key(name: &str, brand: &str) -> (String, String) {
+    (name.trim().to_lowercase(), brand.trim().to_lowercase())
+}
+
+// Codes that only differ in leading zeros denote the same product.
+fn barcode_key(code: &str) -> &str {
+    code.trim_start_matches('0')
+}
+
+// The columns in which two rows of the same ingredient disagree.
+fn differences(first: &Ingredient, other: &Ingredient) -> Vec<&'static str> {
+    let category_ids = |ingredient: &Ingredient| -> HashSet<u32> {
+        ingredient
+            .categories
+            .iter()
+            .map(|category| category.id)
+            .collect()
+    };
+
+    let mut differences = Vec::new();
+    if first.barcode.as_deref().map(barcode_key) != other.barcode.as_deref().map(barcode_key) {
+        differences.push("barcode");
+    }
+    if category_ids(first) != category_ids(other) {
+        differences.push("categories");
+    }
+    if first.conversions.density != other.conversions.density {
+        differences.push("density");
+    }
+    if first.conversions.piece_weight != other.conversions.piece_weight {
+        differences.push("piece_weight");
+    }
+    differences
+}
+
+fn parse_row(
+    cells: &[String],
+    mapping: &[Option<usize>],
+    categories: &[Category],
+) -> Result<Ingredient, String> {
+    let cell = |column: &str| -> &str {
+        columns()
+            .iter()
+            .position(|name| *name == column)
+            .and_then(|index| mapping.get(index).copied().flatten())
+            .and_then(|index| cells.get(index))
+            .map_or("", |cell| cell.trim())
+    };
+    let number = |column: &str| -> Result<Option<f32>, String> {
+        let value = cell(column);
+        if value.is_empty() {
+            return Ok(None);
+        }
+        match value.parse::<f32>() {
+            Ok(number) if number.is_finite() && number >= 0.0 => Ok(Some(number)),
+            _ => Err(format!("invalid number \"{}\" in column {}", value, column)),
+        }
+    };
+
+    let name = cell("name");
+    if name.is_empty() {
+        return Err("name is missing".to_owned());
+    }
+
+    let barcode = match cell("barcode") {
+        "" => None,
+        code => Some(
+            barcode::normalize(code)
+                .map_err(|error| format!("invalid barcode \"{}\": {}", code, error))?,
+        ),
+    };
+
+    let mut ingredient_categories: Vec<Category> = Vec::new();
+    for category_name in cell("categories")
+        .split(CATEGORY_SEPARATOR)
+        .map(str::trim)
+        .filter(|category_name| !category_name.is_empty())
+    {
+        match categories
+            .iter()
+            .find(|category| category.name.eq_ignore_ascii_case(category_name))
+        {
+            Some(category) => ingredient_categories.push(category.clone()),
+            None => return Err(format!("unknown category \"{}\"", category_name)),
+        }
+    }
+
+    let default_amount = match number("default_amount")? {
+        Some(amount) if amount > 0.0 => amount,
+        _ => return Err("default_amount has to be greater than 0".to_owned()),
+    };
+    let default_unit = match Unit::from_symbol(cell("default_unit")) {
+        Some(Unit::Servings) | None => {
+            return Err(format!("unknown unit \"{}\"", cell("default_unit")));
+        }
+        Some(unit) => unit,
+    };
+
+    let mut nutritional_info = NutritionalInfo {
+        name: cell("serving").to_owned(),
+        default_amount,
+        default_unit,
+        ..Default::default()
+    };
+    for (field, _) in NUTRIENT_FIELDS {
+        if let (Some(value), Some(target)) = (number(field)?, nutritional_info.nutrient_mut(field))
+        {
+            *target = value;
+        }
+    }
+
+    Ok(Ingredient {
+        id: 0,
+        name: name.to_owned(),
+        brand: cell("brand").to_owned(),
+        barcode,
+        categories: ingredient_categories,
+        nutritional_info: vec![nutritional_info],
+        conversions: Conversions {
+            density: number("density")?,
+            piece_weight: number("piece_weight")?,
+        },
+        archived: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::epaint::Color32;
+
+    fn category(id: u32, name: &str) -> Category {
+        Category {
+            id,
+            name: name.to_owned(),
+            icon_name: "apple".to_owned(),
+            icon_color: Color32::WHITE,
+        }
+    }
+
+    #[test]
+    fn exported_ingredients_import_unchanged() {
+        let mut nutritional_info = NutritionalInfo {
+            default_amount: 100.0,
+            default_unit: Unit::Grams,
+            kilocalories: 389.0,
+            ..Default::default()
+        };
+        nutritional_info
+            .macronutrients
+            .proteins
+            .essential_amino_acids
+            .leucine = 1.284;
+        nutritional_info.micronutrients.vitamins.vitamin_b12 = 0.0307;
+        nutritional_info.micronutrients.minerals.selenium = 28.9;
+        let per_cup = NutritionalInfo {
+            name: "Bowl".to_owned(),
+            default_amount: 1.0,
+            default_unit: Unit::Cups,
+            kilocalories: 307.0,
+            ..Default::default()
+        };
+        let oats = Rc::new(Ingredient {
+            id: 7,
+            brand: "O'Brien's".to_owned(),
+            barcode: Some("4006381333931".to_owned()),
+            categories: vec![category(1, "Grains"), category(2, "Breakfast")],
+            nutritional_info: vec![nutritional_info, per_cup],
+            conversions: Conversions {
+                density: Some(0.34),
+                piece_weight: None,
+            },
+            ..Ingredient::test("Oats, \"rolled\"", 0.0)
+        });
+
+        let mut file: Vec<u8> = Vec::new();
+        export(std::slice::from_ref(&oats), &mut file).unwrap();
+        let table = read_table(file.as_slice()).unwrap();
+        assert_eq!(table.rows.len(), 2);
+
+        let categories = [category(1, "Grains"), category(2, "Breakfast")];
+        let preview = preview(&table, &auto_mapping(&table.headers), &categories, &[]);
+        assert!(preview.errors.is_empty());
+        assert_eq!(preview.ingredients.len(), 1);
+
+        let imported = &preview.ingredients[0].ingredient;
+        assert_eq!(imported.name, oats.name);
+        assert_eq!(imported.brand, oats.brand);
+        assert_eq!(imported.barcode, oats.barcode);
+        assert_eq!(imported.categories.len(), 2);
+        assert_eq!(imported.conversions, oats.conversions);
+        assert_eq!(imported.nutritional_info.len(), 2);
+        for (name, _) in NUTRIENT_FIELDS {
+            assert_eq!(
+                imported.nutritional_info[0].nutrient(name),
+                oats.nutritional_info[0].nutrient(name),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(imported.nutritional_info[1].default_unit, Unit::Cups);
+        assert_eq!(imported.nutritional_info[1].name, "Bowl");
+    }
+
+    #[test]
+    fn invalid_rows_are_reported_and_duplicates_flagged() {
+        let file = "\
+Name,Brand,Categories,Default_Amount,Default_Unit,Kcal,fiber,barcode
+Oats,,Grains,100,g,389,10.6,
+,,,100,g,1,,
+Rice,,Grains,100,handful,130,,
+Beans,,Legumes,100,g,347,,
+Bread,Baker,,one,g,265,,
+Apple,,,1,pc,52,2.4,
+Milk,,,1,l,640,,4006381333932
+";
+        let table = read_table(file.as_bytes()).unwrap();
+        let mut mapping = auto_mapping(&table.headers);
+        let kilocalories = columns()
+            .iter()
+            .position(|column| *column == "kilocalories")
+            .unwrap();
+        assert_eq!(mapping[kilocalories], None);
+        mapping[kilocalories] = Some(5);
+
+        let existing = [Rc::new(Ingredient {
+            id: 1,
+            ..Ingredient::test("oats", 389.0)
+        })];
+        let preview = preview(&table, &mapping, &[category(1, "Grains")], &existing);
+
+        let errors: Vec<(u64, &str)> = preview
+            .errors
+            .iter()
+            .map(|error| (error.line, error.message.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (3, "name is missing"),
+                (4, "unknown unit \"handful\""),
+                (5, "unknown category \"Legumes\""),
+                (6, "invalid number \"one\" in column default_amount"),
+                (
+                    8,
+                    "invalid barcode \"4006381333932\": wrong check digit, expected 1"
+                ),
+            ]
+        );
+
+        assert_eq!(preview.ingredients.len(), 2);
+        assert!(preview.ingredients[0].duplicate);
+        assert_eq!(
+            preview.ingredients[0].ingredient.nutritional_info[0].kilocalories,
+            389.0
+        );
+        assert!(!preview.ingredients[1].duplicate);
+        assert_eq!(
+            preview.ingredients[1].ingredient.nutritional_info[0]
+                .macronutrients
+                .carbohydrates
+                .fiber,
+            2.4
+        );
+    }
+
+    #[test]
+    fn barcodes_are_unique_and_merged_rows_have_to_agree() {
+        let file = "\
+Name,Brand,Barcode,Categories,Default_Amount,Default_Unit,Density
+Whole milk,,04006381333931,,100,ml,
+Oats,,0012345678905,Grains,100,g,
+Oat flakes,,012345678905,,100,g,
+Oats,,0012345678905,Grains,1,cup,0.34
+Oats,,,,40,g,
+Oats,,0012345678905,Grains,40,g,
+";
+        let table = read_table(file.as_bytes()).unwrap();
+        let existing = [Rc::new(Ingredient {
+            id: 1,
+            barcode: Some("4006381333931".to_owned()),
+            ..Ingredient::test("Milk", 64.0)
+        })];
+        let preview = preview(
+            &table,
+            &auto_mapping(&table.headers),
+            &[category(1, "Grains")],
+            &existing,
+        );
+
+        let errors: Vec<(u64, &str)> = preview
+            .errors
+            .iter()
+            .map(|error| (error.line, error.message.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (4, "barcode \"012345678905\" is already used by \"Oats\""),
+                (5, "conflicts with an earlier row of \"Oats\" in density"),
+                (
+                    6,
+                    "conflicts with an earlier row of \"Oats\" in barcode, categories"
+                ),
+            ]
+        );
+
+        assert_eq!(preview.ingredients.len(), 2);
+        // The same product as the existing milk, only with a leading zero.
+        assert!(preview.ingredients[0].duplicate);
+        assert!(!preview.ingredients[1].duplicate);
+        assert_eq!(preview.ingredients[1].ingredient.nutritional_info.len(), 2);
+    }
+}
@@ -1,44 +1,29 @@
-use rusqlite::types::{ToSql, ToSqlOutput, Value};
+pub use crate::units::{Conversions, Unit};
+use chrono::NaiveTime;
+use serde_json::{json, Value};
+use std::ops::{AddAssign, Mul};
 use std::rc::Rc;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Unit {
-    Grams,
-    Teaspoons,
-    Tablespoons,
-    Pieces,
-    Cups,
-}
+// Implements `+=` and scaling by a factor for a struct of nutrient amounts.
+// Fields may be plain f32 values or other structs this macro was applied to.
+macro_rules! impl_nutrient_arithmetic {
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        impl AddAssign<&$name> for $name {
+            fn add_assign(&mut self, other: &$name) {
+                $(self.$field += &other.$field;)*
+            }
+        }
 
-impl ToSql for Unit {
-    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
-        Ok(ToSqlOutput::Owned(Value::Integer(*self as i64)))
-    }
-}
+        impl Mul<f32> for &$name {
+            type Output = $name;
 
-impl Unit {
-    pub fn from_uint(input: u32) -> Self {
-        match input {
-            0 => Self::Grams,
-            1 => Self::Teaspoons,
-            2 => Self::Tablespoons,
-            3 => Self::Pieces,
-            4 => Self::Cups,
-            _ => panic!("{} is not a valid Unit!", input),
+            fn mul(self, factor: f32) -> $name {
+                $name {
+                    $($field: &self.$field * factor,)*
+                }
+            }
         }
-    }
-}
-
-impl std::fmt::Display for Unit {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            Unit::Grams => write!(f, "g"),
-            Unit::Teaspoons => write!(f, "tsp"),
-            Unit::Tablespoons => write!(f, "Tbsp"),
-            Unit::Pieces => write!(f, "pc"),
-            Unit::Cups => write!(f, "cup"),
-        }
-    }
+    };
 }
 
 #[derive(Clone)]
@@ -54,8 +39,101 @@ pub struct Ingredient {
     pub id: u32,
     pub name: String,
     pub brand: String,
+    // EAN/UPC code of packaged foods, validated with `barcode::normalize`.
+    pub barcode: Option<String>,
     pub categories: Vec<Category>,
     pub nutritional_info: Vec<NutritionalInfo>,
+    pub conversions: Conversions,
+    // Archived ingredients are hidden from lists but still resolve for old log entries.
+    pub archived: bool,
+}
+
+// What a hard delete of an ingredient would take with it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IngredientUsage {
+    pub log_entries: usize,
+    pub recipes: usize,
+}
+
+impl Ingredient {
+    // Picks the nutritional info to use for `amount` of `unit` and the factor to scale it by.
+    // Nutritional info given in the same unit is preferred over one that needs a conversion.
+    pub fn scale(&self, amount: f32, unit: Unit) -> Option<(&NutritionalInfo, f32)> {
+        if unit == Unit::Servings {
+            return self
+                .nutritional_info
+                .first()
+                .map(|nutritional_info| (nutritional_info, amount));
+        }
+
+        let usable = || {
+            self.nutritional_info
+                .iter()
+                .filter(|nutritional_info| nutritional_info.default_amount > 0.0)
+        };
+        usable()
+            .filter(|nutritional_info| nutritional_info.default_unit == unit)
+            .chain(usable())
+            .find_map(|nutritional_info| {
+                self.conversions
+                    .convert(amount, unit, nutritional_info.default_unit)
+                    .map(|converted| {
+                        (
+                            nutritional_info,
+                            converted / nutritional_info.default_amount,
+                        )
+                    })
+            })
+    }
+
+    // Like `scale`, but servings refer to the nutritional info with the id
+    // `serving`. Servings that no longer exist fall back to the first one.
+    pub fn scale_serving(
+        &self,
+        amount: f32,
+        unit: Unit,
+        serving: Option<u32>,
+    ) -> Option<(&NutritionalInfo, f32)> {
+        if unit == Unit::Servings {
+            if let Some(nutritional_info) = serving.and_then(|id| {
+                self.nutritional_info
+                    .iter()
+                    .find(|nutritional_info| nutritional_info.id == id)
+            }) {
+                return Some((nutritional_info, amount));
+            }
+        }
+        self.scale(amount, unit)
+    }
+
+    // Units an amount of this ingredient can be given in.
+    pub fn available_units(&self) -> Vec<Unit> {
+        Unit::MEASURES
+            .into_iter()
+            .chain([Unit::Servings])
+            .filter(|unit| self.scale(1.0, *unit).is_some())
+            .collect()
+    }
+
+    // An ingredient with a single nutritional info of `kilocalories` per 100 g.
+    #[cfg(test)]
+    pub fn test(name: &str, kilocalories: f32) -> Self {
+        Ingredient {
+            id: 0,
+            name: name.to_owned(),
+            brand: String::new(),
+            barcode: None,
+            categories: Vec::new(),
+            nutritional_info: vec![NutritionalInfo {
+                default_amount: 100.0,
+                default_unit: Unit::Grams,
+                kilocalories,
+                ..Default::default()
+            }],
+            conversions: Conversions::default(),
+            archived: false,
+        }
+    }
 }
 
 impl PartialEq for Ingredient {
@@ -64,21 +142,142 @@ impl PartialEq for Ingredient {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Meal {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Clone)]
+pub struct RecipeItem {
+    pub ingredient: Rc<Ingredient>,
+    pub amount: f32,
+    pub unit: Unit,
+}
+
+#[derive(Clone)]
+pub struct Recipe {
+    pub id: u32,
+    pub name: String,
+    pub servings: f32,
+    pub items: Vec<RecipeItem>,
+}
+
+impl PartialEq for Recipe {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Recipe {
+    pub fn total_nutrients(&self) -> NutrientTotals {
+        let mut totals = NutrientTotals::default();
+        for item in &self.items {
+            if let Some((nutritional_info, factor)) = item.ingredient.scale(item.amount, item.unit)
+            {
+                totals.add_scaled(nutritional_info, factor);
+            }
+        }
+        totals
+    }
+
+    pub fn nutrients_per_serving(&self) -> NutrientTotals {
+        if self.servings > 0.0 {
+            &self.total_nutrients() * (1.0 / self.servings)
+        } else {
+            NutrientTotals::default()
+        }
+    }
+}
+
+// What a log entry refers to: a single ingredient or servings of a recipe.
+#[derive(Clone, PartialEq)]
+pub enum LogItem {
+    Ingredient(Rc<Ingredient>),
+    Recipe(Rc<Recipe>),
+}
+
+impl LogItem {
+    pub fn name(&self) -> &str {
+        match self {
+            LogItem::Ingredient(ingredient) => &ingredient.name,
+            LogItem::Recipe(recipe) => &recipe.name,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct LogEntry {
     pub id: u32,
-    pub ingredient: Rc<Ingredient>,
-    pub fraction: f32,
+    pub item: LogItem,
+    // For recipes the unit is always `Unit::Servings`.
+    pub amount: f32,
+    pub unit: Unit,
+    // Id of the ingredient's nutritional info an amount in `Unit::Servings` refers to.
+    pub serving: Option<u32>,
+    pub meal: Option<Meal>,
+    pub time: Option<NaiveTime>,
 }
 
 impl LogEntry {
-    pub fn calculate_calories(&self, idx: usize) -> f32 {
-        self.ingredient.nutritional_info[idx].kilocalories * self.fraction
+    pub fn nutrients(&self) -> NutrientTotals {
+        let mut totals = NutrientTotals::default();
+        totals.add_log_entry(self);
+        totals
     }
 }
 
-#[derive(Clone)]
+// Nutrient amounts summed over several servings, e.g. all log entries of a day.
+#[derive(Clone, Default)]
+pub struct NutrientTotals {
+    pub kilocalories: f32,
+    pub macronutrients: Macronutrients,
+    pub micronutrients: Micronutrients,
+}
+
+impl_nutrient_arithmetic!(NutrientTotals {
+    kilocalories,
+    macronutrients,
+    micronutrients
+});
+
+impl NutrientTotals {
+    pub fn add_scaled(&mut self, nutritional_info: &NutritionalInfo, factor: f32) {
+        self.kilocalories += nutritional_info.kilocalories * factor;
+        self.macronutrients += &(&nutritional_info.macronutrients * factor);
+        self.micronutrients += &(&nutritional_info.micronutrients * factor);
+    }
+
+    pub fn add_log_entry(&mut self, log_entry: &LogEntry) {
+        match &log_entry.item {
+            LogItem::Ingredient(ingredient) => {
+                if let Some((nutritional_info, factor)) =
+                    ingredient.scale_serving(log_entry.amount, log_entry.unit, log_entry.serving)
+                {
+                    self.add_scaled(nutritional_info, factor);
+                }
+            }
+            LogItem::Recipe(recipe) => {
+                *self += &(&recipe.nutrients_per_serving() * log_entry.amount)
+            }
+        }
+    }
+
+    pub fn from_log_entries(log_entries: &[LogEntry]) -> Self {
+        let mut totals = NutrientTotals::default();
+        for log_entry in log_entries {
+            totals.add_log_entry(log_entry);
+        }
+        totals
+    }
+}
+
+// One serving of an ingredient, e.g. "1 slice" or "100 g", with its nutrients.
+#[derive(Clone, Default)]
 pub struct NutritionalInfo {
+    pub id: u32,
+    // May be empty, the amount and unit describe the serving then.
+    pub name: String,
     pub default_amount: f32,
     pub default_unit: Unit,
 
@@ -88,39 +287,96 @@ pub struct NutritionalInfo {
 }
 
 impl NutritionalInfo {
+    pub fn serving_name(&self) -> String {
+        let amount = format!("{} {}", self.default_amount, self.default_unit);
+        match self.name.trim() {
+            "" => amount,
+            name => format!("{} ({})", name, amount),
+        }
+    }
+
+    // Takes over the nutrients of `other` scaled by `factor`, keeping the name and size.
+    pub fn copy_nutrients_scaled(&mut self, other: &NutritionalInfo, factor: f32) {
+        self.kilocalories = other.kilocalories * factor;
+        self.macronutrients = &other.macronutrients * factor;
+        self.micronutrients = &other.micronutrients * factor;
+    }
+
     pub fn estimate_calories(&self) -> f32 {
         self.macronutrients.estimate_calories()
     }
+
+    // Food databases mostly list macronutrient totals without a full breakdown.
+    // Whatever the parts set so far do not explain is kept as unspecified
+    // protein, fat and carbohydrates other than fiber, so the totals match.
+    pub fn complete_totals(
+        &mut self,
+        proteins: Option<f32>,
+        fats: Option<f32>,
+        carbohydrates_without_fiber: Option<f32>,
+    ) {
+        let macronutrients = &mut self.macronutrients;
+        if let Some(proteins) = proteins {
+            let remainder = proteins - macronutrients.proteins.total_proteins();
+            macronutrients.proteins.unspecified += remainder.max(0.0);
+        }
+        if let Some(fats) = fats {
+            let remainder = fats - macronutrients.fats.total_fats();
+            macronutrients.fats.unspecified += remainder.max(0.0);
+        }
+        if let Some(carbohydrates) = carbohydrates_without_fiber {
+            let parts = &macronutrients.carbohydrates;
+            let remainder = carbohydrates - (parts.total_carbs() - parts.fiber);
+            macronutrients.carbohydrates.unspecified += remainder.max(0.0);
+        }
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Macronutrients {
     pub proteins: Proteins,
     pub fats: Fats,
     pub carbohydrates: Carbohydrates,
 }
 
+impl_nutrient_arithmetic!(Macronutrients {
+    proteins,
+    fats,
+    carbohydrates
+});
+
 impl Macronutrients {
-    fn estimate_calories(&self) -> f32 {
+    pub fn estimate_calories(&self) -> f32 {
         self.proteins.total_proteins() * 4.0
             + self.fats.total_fats() * 9.0
             + self.carbohydrates.net_carbs() * 4.0
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Proteins {
     pub essential_amino_acids: EssentialAminoAcids,
     pub non_essential_amino_acids: NonEssentialAminoAcids,
+    // Protein without a known amino acid, e.g. from a food database that only
+    // lists the total.
+    pub unspecified: f32,
 }
 
+impl_nutrient_arithmetic!(Proteins {
+    essential_amino_acids,
+    non_essential_amino_acids,
+    unspecified
+});
+
 impl Proteins {
     pub fn total_proteins(&self) -> f32 {
-        self.essential_amino_acids.total() + self.non_essential_amino_acids.total()
+        self.essential_amino_acids.total()
+            + self.non_essential_amino_acids.total()
+            + self.unspecified
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct EssentialAminoAcids {
     pub histidine: f32,
     pub isoleucine: f32,
@@ -133,8 +389,20 @@ pub struct EssentialAminoAcids {
     pub valine: f32,
 }
 
+impl_nutrient_arithmetic!(EssentialAminoAcids {
+    histidine,
+    isoleucine,
+    leucine,
+    lysine,
+    methionine,
+    phenylalanine,
+    threonine,
+    tryptophan,
+    valine
+});
+
 impl EssentialAminoAcids {
-    fn total(&self) -> f32 {
+    pub fn total(&self) -> f32 {
         self.histidine
             + self.isoleucine
             + self.leucine
@@ -147,7 +415,7 @@ impl EssentialAminoAcids {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct NonEssentialAminoAcids {
     pub alanine: f32,
     pub arginine: f32,
@@ -162,8 +430,22 @@ pub struct NonEssentialAminoAcids {
     pub tyrosine: f32,
 }
 
+impl_nutrient_arithmetic!(NonEssentialAminoAcids {
+    alanine,
+    arginine,
+    asparagine,
+    aspartic_acid,
+    cysteine,
+    glutamic_acid,
+    glutamine,
+    glycine,
+    proline,
+    serine,
+    tyrosine
+});
+
 impl NonEssentialAminoAcids {
-    fn total(&self) -> f32 {
+    pub fn total(&self) -> f32 {
         self.alanine
             + self.arginine
             + self.asparagine
@@ -178,43 +460,64 @@ impl NonEssentialAminoAcids {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Fats {
     pub saturated: f32,
     pub monounsaturated: f32,
     pub polyunsaturated: f32,
+    // Fat of unknown saturation.
+    pub unspecified: f32,
 }
 
+impl_nutrient_arithmetic!(Fats {
+    saturated,
+    monounsaturated,
+    polyunsaturated,
+    unspecified
+});
+
 impl Fats {
     pub fn total_fats(&self) -> f32 {
-        self.saturated + self.monounsaturated + self.polyunsaturated
+        self.saturated + self.monounsaturated + self.polyunsaturated + self.unspecified
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Carbohydrates {
     pub starch: f32,
     pub fiber: f32,
     pub sugars: f32,
     pub sugar_alcohols: f32,
+    // Carbohydrates other than fiber that are not broken down further.
+    pub unspecified: f32,
 }
 
+impl_nutrient_arithmetic!(Carbohydrates {
+    starch,
+    fiber,
+    sugars,
+    sugar_alcohols,
+    unspecified
+});
+
 impl Carbohydrates {
     pub fn total_carbs(&self) -> f32 {
-        self.starch + self.fiber + self.sugars + self.sugar_alcohols
+        self.starch + self.fiber + self.sugars + self.sugar_alcohols + self.unspecified
     }
     pub fn net_carbs(&self) -> f32 {
-        self.starch + self.sugars + 0.5 * self.sugar_alcohols
+        self.starch + self.sugars + 0.5 * self.sugar_alcohols + self.unspecified
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Micronutrients {
     pub vitamins: Vitamins,
     pub minerals: Minerals,
 }
 
-#[derive(Clone)]
+impl_nutrient_arithmetic!(Micronutrients { vitamins, minerals });
+
+#[derive(Clone, Default)]
 pub struct Vitamins {
     pub vitamin_a: f32,
     pub vitamin_b1: f32,
@@ -232,7 +535,24 @@ pub struct Vitamins {
     pub choline: f32,
 }
 
-#[derive(Clone)]
+impl_nutrient_arithmetic!(Vitamins {
+    vitamin_a,
+    vitamin_b1,
+    vitamin_b2,
+    vitamin_b3,
+    vitamin_b5,
+    vitamin_b6,
+    vitamin_b9,
+    vitamin_b12,
+    vitamin_c,
+    vitamin_d,
+    vitamin_e,
+    vitamin_k,
+    betaine,
+    choline
+});
+
+#[derive(Clone, Default)]
 pub struct Minerals {
     pub calcium: f32,
     pub copper: f32,
@@ -245,3 +565,293 @@ pub struct Minerals {
     pub sodium: f32,
     pub zinc: f32,
 }
+
+impl_nutrient_arithmetic!(Minerals {
+    calcium,
+    copper,
+    iron,
+    magnesium,
+    manganese,
+    phosphorus,
+    potassium,
+    selenium,
+    sodium,
+    zinc
+});
+
+// Gives every nutrient amount of `NutritionalInfo` and `NutrientTotals` a name, so importers and
+// exporters can address the fields without spelling out the struct paths.
+macro_rules! nutrient_fields {
+    ($($name:literal, $unit:literal => $($path:ident).+;)*) => {
+        // Name and unit of every nutrient amount, in the order of the structs.
+        pub const NUTRIENT_FIELDS: &[(&str, &str)] = &[$(($name, $unit)),*];
+
+        impl NutritionalInfo {
+            pub fn nutrient(&self, name: &str) -> Option<f32> {
+                match name {
+                    $($name => Some(self.$($path).+),)*
+                    _ => None,
+                }
+            }
+
+            pub fn nutrient_mut(&mut self, name: &str) -> Option<&mut f32> {
+                match name {
+                    $($name => Some(&mut self.$($path).+),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl NutrientTotals {
+            pub fn nutrient(&self, name: &str) -> Option<f32> {
+                match name {
+                    $($name => Some(self.$($path).+),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+nutrient_fields! {
+    "kilocalories", "kcal" => kilocalories;
+    "histidine", "g" => macronutrients.proteins.essential_amino_acids.histidine;
+    "isoleucine", "g" => macronutrients.proteins.essential_amino_acids.isoleucine;
+    "leucine", "g" => macronutrients.proteins.essential_amino_acids.leucine;
+    "lysine", "g" => macronutrients.proteins.essential_amino_acids.lysine;
+    "methionine", "g" => macronutrients.proteins.essential_amino_acids.methionine;
+    "phenylalanine", "g" => macronutrients.proteins.essential_amino_acids.phenylalanine;
+    "threonine", "g" => macronutrients.proteins.essential_amino_acids.threonine;
+    "tryptophan", "g" => macronutrients.proteins.essential_amino_acids.tryptophan;
+    "valine", "g" => macronutrients.proteins.essential_amino_acids.valine;
+    "alanine", "g" => macronutrients.proteins.non_essential_amino_acids.alanine;
+    "arginine", "g" => macronutrients.proteins.non_essential_amino_acids.arginine;
+    "asparagine", "g" => macronutrients.proteins.non_essential_amino_acids.asparagine;
+    "aspartic_acid", "g" => macronutrients.proteins.non_essential_amino_acids.aspartic_acid;
+    "cysteine", "g" => macronutrients.proteins.non_essential_amino_acids.cysteine;
+    "glutamic_acid", "g" => macronutrients.proteins.non_essential_amino_acids.glutamic_acid;
+    "glutamine", "g" => macronutrients.proteins.non_essential_amino_acids.glutamine;
+    "glycine", "g" => macronutrients.proteins.non_essential_amino_acids.glycine;
+    "proline", "g" => macronutrients.proteins.non_essential_amino_acids.proline;
+    "serine", "g" => macronutrients.proteins.non_essential_amino_acids.serine;
+    "tyrosine", "g" => macronutrients.proteins.non_essential_amino_acids.tyrosine;
+    "unspecified_protein", "g" => macronutrients.proteins.unspecified;
+    "saturated", "g" => macronutrients.fats.saturated;
+    "monounsaturated", "g" => macronutrients.fats.monounsaturated;
+    "polyunsaturated", "g" => macronutrients.fats.polyunsaturated;
+    "unspecified_fat", "g" => macronutrients.fats.unspecified;
+    "starch", "g" => macronutrients.carbohydrates.starch;
+    "fiber", "g" => macronutrients.carbohydrates.fiber;
+    "sugars", "g" => macronutrients.carbohydrates.sugars;
+    "sugar_alcohols", "g" => macronutrients.carbohydrates.sugar_alcohols;
+    "unspecified_carbohydrates", "g" => macronutrients.carbohydrates.unspecified;
+    "vitamin_a", "µg" => micronutrients.vitamins.vitamin_a;
+    "vitamin_b1", "mg" => micronutrients.vitamins.vitamin_b1;
+    "vitamin_b2", "mg" => micronutrients.vitamins.vitamin_b2;
+    "vitamin_b3", "mg" => micronutrients.vitamins.vitamin_b3;
+    "vitamin_b5", "mg" => micronutrients.vitamins.vitamin_b5;
+    "vitamin_b6", "mg" => micronutrients.vitamins.vitamin_b6;
+    "vitamin_b9", "µg" => micronutrients.vitamins.vitamin_b9;
+    "vitamin_b12", "µg" => micronutrients.vitamins.vitamin_b12;
+    "vitamin_c", "mg" => micronutrients.vitamins.vitamin_c;
+    "vitamin_d", "µg" => micronutrients.vitamins.vitamin_d;
+    "vitamin_e", "mg" => micronutrients.vitamins.vitamin_e;
+    "vitamin_k", "µg" => micronutrients.vitamins.vitamin_k;
+    "betaine", "mg" => micronutrients.vitamins.betaine;
+    "choline", "mg" => micronutrients.vitamins.choline;
+    "calcium", "mg" => micronutrients.minerals.calcium;
+    "copper", "mg" => micronutrients.minerals.copper;
+    "iron", "mg" => micronutrients.minerals.iron;
+    "magnesium", "mg" => micronutrients.minerals.magnesium;
+    "manganese", "mg" => micronutrients.minerals.manganese;
+    "phosphorus", "mg" => micronutrients.minerals.phosphorus;
+    "potassium", "mg" => micronutrients.minerals.potassium;
+    "selenium", "µg" => micronutrients.minerals.selenium;
+    "sodium", "mg" => micronutrients.minerals.sodium;
+    "zinc", "mg" => micronutrients.minerals.zinc;
+}
+
+// How many of the unit nutrient `name` is measured in make up a gram, e.g. 1000
+// for mg. `None` for unknown nutrients and the energy.
+pub fn nutrient_units_per_gram(name: &str) -> Option<f32> {
+    match NUTRIENT_FIELDS.iter().find(|(field, _)| *field == name)? {
+        (_, "g") => Some(1.0),
+        (_, "mg") => Some(1e3),
+        (_, "µg") => Some(1e6),
+        _ => None,
+    }
+}
+
+// The vitamins and minerals of `NUTRIENT_FIELDS`.
+pub fn micronutrient_fields() -> impl Iterator<Item = &'static str> {
+    NUTRIENT_FIELDS
+        .iter()
+        .map(|(name, _)| *name)
+        .skip_while(|name| *name != "vitamin_a")
+}
+
+// The unit nutrient `name` is measured in, empty for unknown nutrients.
+pub fn nutrient_unit(name: &str) -> &'static str {
+    NUTRIENT_FIELDS
+        .iter()
+        .find(|(field, _)| *field == name)
+        .map_or("", |(_, unit)| unit)
+}
+
+// Turns a nutrient name like "vitamin_b12" into "Vitamin B12".
+pub fn nutrient_label(name: &str) -> String {
+    let label = name.replace('_', " ");
+    match label.strip_prefix("vitamin ") {
+        Some(letter) => format!("Vitamin {}", letter.to_uppercase()),
+        None => {
+            let mut characters = label.chars();
+            characters
+                .next()
+                .map(|first| first.to_uppercase().chain(characters).collect())
+                .unwrap_or_default()
+        }
+    }
+}
+
+// Writes `value` with as few digits as `f32` needs, 0.1 instead of 0.10000000149011612.
+pub fn json_number(value: f32) -> Value {
+    value
+        .to_string()
+        .parse::<f64>()
+        .map_or(Value::Null, |value| json!(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nutrient_totals_scale_each_log_entry() {
+        let mut ingredient = Ingredient {
+            id: 1,
+            ..Ingredient::test("Oats", 200.0)
+        };
+        let nutritional_info = &mut ingredient.nutritional_info[0];
+        nutritional_info.macronutrients.fats.saturated = 10.0;
+        nutritional_info.macronutrients.carbohydrates.fiber = 4.0;
+        nutritional_info.micronutrients.minerals.sodium = 50.0;
+        let ingredient = Rc::new(ingredient);
+        let log_entries = [0.5, 1.5].map(|servings| LogEntry {
+            id: 0,
+            item: LogItem::Ingredient(ingredient.clone()),
+            amount: servings,
+            unit: Unit::Servings,
+            serving: None,
+            meal: None,
+            time: None,
+        });
+
+        let totals = NutrientTotals::from_log_entries(&log_entries);
+
+        assert_eq!(totals.kilocalories, 400.0);
+        assert_eq!(totals.macronutrients.fats.total_fats(), 20.0);
+        assert_eq!(totals.macronutrients.carbohydrates.total_carbs(), 8.0);
+        assert_eq!(totals.micronutrients.minerals.sodium, 100.0);
+    }
+
+    #[test]
+    fn micronutrients_are_labeled_with_their_units() {
+        assert_eq!(micronutrient_fields().next(), Some("vitamin_a"));
+        assert!(micronutrient_fields().all(|field| field != "fiber"));
+        assert_eq!(micronutrient_fields().count(), 24);
+        assert_eq!(nutrient_label("vitamin_b12"), "Vitamin B12");
+        assert_eq!(nutrient_label("calcium"), "Calcium");
+        assert_eq!(nutrient_unit("selenium"), "µg");
+    }
+
+    #[test]
+    fn recipe_nutrients_are_split_into_servings() {
+        let ingredient = |name: &str, kilocalories, default_amount, default_unit| {
+            let mut ingredient = Ingredient::test(name, kilocalories);
+            ingredient.nutritional_info[0].default_amount = default_amount;
+            ingredient.nutritional_info[0].default_unit = default_unit;
+            Rc::new(ingredient)
+        };
+        let beans = ingredient("Beans", 120.0, 100.0, Unit::Grams);
+        let tomato = ingredient("Tomato", 20.0, 1.0, Unit::Pieces);
+        let item = |ingredient: &Rc<Ingredient>, amount, unit| RecipeItem {
+            ingredient: ingredient.clone(),
+            amount,
+            unit,
+        };
+        let recipe = Rc::new(Recipe {
+            id: 1,
+            name: "Chili".to_owned(),
+            servings: 4.0,
+            items: vec![
+                item(&beans, 400.0, Unit::Grams),
+                item(&tomato, 3.0, Unit::Pieces),
+                // Without nutritional info in grams this item cannot be counted.
+                item(&tomato, 50.0, Unit::Grams),
+            ],
+        });
+
+        assert_eq!(recipe.total_nutrients().kilocalories, 540.0);
+        assert_eq!(recipe.nutrients_per_serving().kilocalories, 135.0);
+
+        let log_entry = LogEntry {
+            id: 0,
+            item: LogItem::Recipe(recipe),
+            amount: 1.5,
+            unit: Unit::Servings,
+            serving: None,
+            meal: None,
+            time: None,
+        };
+        assert_eq!(log_entry.nutrients().kilocalories, 202.5);
+    }
+
+    #[test]
+    fn ingredient_scales_amounts_in_other_units() {
+        let per_100_ml = NutritionalInfo {
+            default_amount: 100.0,
+            default_unit: Unit::Milliliters,
+            kilocalories: 64.0,
+            ..Default::default()
+        };
+        let per_cup = NutritionalInfo {
+            id: 2,
+            name: "Glass".to_owned(),
+            default_amount: 1.0,
+            default_unit: Unit::Cups,
+            kilocalories: 150.0,
+            ..Default::default()
+        };
+        assert_eq!(per_cup.serving_name(), "Glass (1 cup)");
+        let milk = Ingredient {
+            id: 1,
+            nutritional_info: vec![per_100_ml, per_cup],
+            conversions: Conversions {
+                density: Some(1.03),
+                piece_weight: None,
+            },
+            ..Ingredient::test("Milk", 64.0)
+        };
+
+        let (nutritional_info, factor) = milk.scale(206.0, Unit::Grams).unwrap();
+        assert_eq!(nutritional_info.default_unit, Unit::Milliliters);
+        assert!((factor - 2.0).abs() < 1e-4);
+
+        let (nutritional_info, factor) = milk.scale(2.0, Unit::Cups).unwrap();
+        assert_eq!(nutritional_info.default_unit, Unit::Cups);
+        assert_eq!(factor, 2.0);
+
+        let (nutritional_info, factor) = milk.scale(1.5, Unit::Servings).unwrap();
+        assert_eq!(nutritional_info.default_unit, Unit::Milliliters);
+        assert_eq!(factor, 1.5);
+
+        let (nutritional_info, factor) = milk.scale_serving(1.5, Unit::Servings, Some(2)).unwrap();
+        assert_eq!(nutritional_info.default_unit, Unit::Cups);
+        assert_eq!(factor, 1.5);
+        let (nutritional_info, _) = milk.scale_serving(1.0, Unit::Servings, Some(9)).unwrap();
+        assert_eq!(nutritional_info.default_unit, Unit::Milliliters);
+
+        assert!(milk.scale(1.0, Unit::Pieces).is_none());
+        assert!(!milk.available_units().contains(&Unit::Pieces));
+    }
+}
@@ -0,0 +1,349 @@
+// Export of daily log entries with their nutrient totals as CSV or JSON.
+//
+// Per entry, every row describes one log entry: date, time, meal, kind
+// ("ingredient" or "recipe"), name, brand, amount, unit and, for servings of an
+// ingredient, the serving that was logged. Per day, every row sums all entries
+// of one date and counts them. Both are followed by one value per nutrient,
+// named and measured as in `NUTRIENT_FIELDS`. Days without any entries are left
+// out.
+//
+// JSON files hold an object with the exported range, the granularity, the unit
+// of each nutrient and the rows under "entries" or "days", each with its
+// nutrients in a nested "nutrients" object.
+
+use crate::ingredients::*;
+use chrono::NaiveDate;
+use serde_json::{json, Map, Value};
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Granularity {
+    Entry,
+    Day,
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ExportFormat::Csv => write!(f, "CSV"),
+            ExportFormat::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+impl std::fmt::Display for Granularity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Granularity::Entry => write!(f, "Per entry"),
+            Granularity::Day => write!(f, "Per day"),
+        }
+    }
+}
+
+const ENTRY_COLUMNS: [&str; 9] = [
+    "date", "time", "meal", "kind", "name", "brand", "amount", "unit", "serving",
+];
+const DAY_COLUMNS: [&str; 2] = ["date", "entries"];
+
+// A row of the export before it is written in either format.
+struct Row {
+    fields: Vec<(&'static str, Value)>,
+    nutrients: NutrientTotals,
+}
+
+// Writes the `log_entries` from `start` to `end`, which have to be sorted by date.
+pub fn export(
+    log_entries: &[(NaiveDate, LogEntry)],
+    (start, end): (NaiveDate, NaiveDate),
+    format: ExportFormat,
+    granularity: Granularity,
+    mut writer: impl Write,
+) -> std::io::Result<()> {
+    let rows = match granularity {
+        Granularity::Entry => entry_rows(log_entries),
+        Granularity::Day => day_rows(log_entries),
+    };
+
+    match format {
+        ExportFormat::Csv => {
+            let columns: &[&str] = match granularity {
+                Granularity::Entry => &ENTRY_COLUMNS,
+                Granularity::Day => &DAY_COLUMNS,
+            };
+            write_csv(&rows, columns, writer)
+        }
+        ExportFormat::Json => {
+            let units: Map<String, Value> = NUTRIENT_FIELDS
+                .iter()
+                .map(|(name, unit)| (name.to_string(), json!(unit)))
+                .collect();
+            let rows: Vec<Value> = rows.iter().map(json_row).collect();
+            let key = match granularity {
+                Granularity::Entry => "entries",
+                Granularity::Day => "days",
+            };
+            let mut document = json!({
+                "start": start.to_string(),
+                "end": end.to_string(),
+                "granularity": key,
+                "nutrient_units": units,
+            });
+            document[key] = Value::Array(rows);
+            serde_json::to_writer_pretty(&mut writer, &document)?;
+            writeln!(writer)
+        }
+    }
+}
+
+fn entry_rows(log_entries: &[(NaiveDate, LogEntry)]) -> Vec<Row> {
+    log_entries
+        .iter()
+        .map(|(date, log_entry)| {
+            let (kind, brand, serving) = match &log_entry.item {
+                LogItem::Ingredient(ingredient) => (
+                    "ingredient",
+                    ingredient.brand.as_str(),
+                    ingredient
+                        .scale_serving(log_entry.amount, log_entry.unit, log_entry.serving)
+                        .filter(|_| log_entry.unit == Unit::Servings)
+                        .map(|(nutritional_info, _)| nutritional_info.serving_name()),
+                ),
+                LogItem::Recipe(_) => ("recipe", "", None),
+            };
+            Row {
+                fields: vec![
+                    ("date", json!(date.to_string())),
+                    (
+                        "time",
+                        log_entry
+                            .time
+                            .map_or(Value::Null, |time| json!(time.format("%H:%M").to_string())),
+                    ),
+                    (
+                        "meal",
+                        log_entry
+                            .meal
+                            .as_ref()
+                            .map_or(Value::Null, |meal| json!(meal.name)),
+                    ),
+                    ("kind", json!(kind)),
+                    ("name", json!(log_entry.item.name())),
+                    ("brand", json!(brand)),
+                    ("amount", json_number(log_entry.amount)),
+                    ("unit", json!(log_entry.unit.to_string())),
+                    (
+                        "serving",
+                        serving.map_or(Value::Null, |serving| json!(serving)),
+                    ),
+                ],
+                nutrients: log_entry.nutrients(),
+            }
+        })
+        .collect()
+}
+
+fn day_rows(log_entries: &[(NaiveDate, LogEntry)]) -> Vec<Row> {
+    let mut rows: Vec<Row> = Vec::new();
+    for day in log_entries.chunk_by(|(a, _), (b, _)| a == b) {
+        let entries: Vec<LogEntry> = day.iter().map(|(_, entry)| entry.clone()).collect();
+        rows.push(Row {
+            fields: vec![
+                ("date", json!(day[0].0.to_string())),
+                ("entries", json!(entries.len())),
+            ],
+            nutrients: NutrientTotals::from_log_entries(&entries),
+        });
+    }
+    rows
+}
+
+fn write_csv(rows: &[Row], columns: &[&str], writer: impl Write) -> std::io::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(
+        columns
+            .iter()
+            .chain(NUTRIENT_FIELDS.iter().map(|(name, _)| name)),
+    )?;
+
+    for row in rows {
+        let mut record: Vec<String> = row
+            .fields
+            .iter()
+            .map(|(_, value)| match value {
+                Value::Null => String::new(),
+                Value::String(text) => text.clone(),
+                Value::Number(number) => number.as_f64().unwrap_or_default().to_string(),
+                value => value.to_string(),
+            })
+            .collect();
+        for (name, _) in NUTRIENT_FIELDS {
+            record.push(row.nutrients.nutrient(name).unwrap_or(0.0).to_string());
+        }
+        writer.write_record(record)?;
+    }
+
+    writer.flush()
+}
+
+fn json_row(row: &Row) -> Value {
+    let mut object: Map<String, Value> = row
+        .fields
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.clone()))
+        .collect();
+    let nutrients: Map<String, Value> = NUTRIENT_FIELDS
+        .iter()
+        .map(|(name, _)| {
+            (
+                name.to_string(),
+                json_number(row.nutrients.nutrient(name).unwrap_or(0.0)),
+            )
+        })
+        .collect();
+    object.insert("nutrients".to_owned(), Value::Object(nutrients));
+    Value::Object(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+    use std::rc::Rc;
+
+    fn log_entries() -> Vec<(NaiveDate, LogEntry)> {
+        let oats = Rc::new(Ingredient {
+            id: 1,
+            brand: "Mill, Inc.".to_owned(),
+            nutritional_info: vec![
+                NutritionalInfo {
+                    id: 1,
+                    default_amount: 100.0,
+                    default_unit: Unit::Grams,
+                    kilocalories: 400.0,
+                    ..Default::default()
+                },
+                NutritionalInfo {
+                    id: 2,
+                    name: "Bowl".to_owned(),
+                    default_amount: 100.0,
+                    default_unit: Unit::Grams,
+                    kilocalories: 400.0,
+                    ..Default::default()
+                },
+            ],
+            ..Ingredient::test("Oats", 400.0)
+        });
+        let entry = |amount: f32, unit: Unit, time: Option<NaiveTime>| LogEntry {
+            id: 0,
+            item: LogItem::Ingredient(oats.clone()),
+            amount,
+            unit,
+            serving: (unit == Unit::Servings).then_some(2),
+            meal: Some(Meal {
+                id: 1,
+                name: "Breakfast".to_owned(),
+            }),
+            time,
+        };
+        let first = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let second = NaiveDate::from_ymd_opt(2024, 3, 3).unwrap();
+        vec![
+            (
+                first,
+                entry(50.0, Unit::Grams, NaiveTime::from_hms_opt(7, 30, 0)),
+            ),
+            (first, entry(25.0, Unit::Grams, None)),
+            (second, entry(1.0, Unit::Servings, None)),
+        ]
+    }
+
+    fn range() -> (NaiveDate, NaiveDate) {
+        (
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 3, 7).unwrap(),
+        )
+    }
+
+    #[test]
+    fn csv_export_has_a_row_per_entry_or_day() {
+        let mut file: Vec<u8> = Vec::new();
+        export(
+            &log_entries(),
+            range(),
+            ExportFormat::Csv,
+            Granularity::Entry,
+            &mut file,
+        )
+        .unwrap();
+        let text = String::from_utf8(file).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0]
+            .starts_with("date,time,meal,kind,name,brand,amount,unit,serving,kilocalories,"));
+        assert!(lines[1]
+            .starts_with("2024-03-01,07:30,Breakfast,ingredient,Oats,\"Mill, Inc.\",50,g,,200,"));
+        assert!(lines[2].starts_with("2024-03-01,,Breakfast,ingredient,Oats,"));
+        assert!(lines[3].contains(",1,serving,Bowl (100 g),400,"));
+
+        let mut file: Vec<u8> = Vec::new();
+        export(
+            &log_entries(),
+            range(),
+            ExportFormat::Csv,
+            Granularity::Day,
+            &mut file,
+        )
+        .unwrap();
+        let text = String::from_utf8(file).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("date,entries,kilocalories,histidine,"));
+        assert!(lines[1].starts_with("2024-03-01,2,300,0,"));
+        assert!(lines[2].starts_with("2024-03-03,1,400,0,"));
+    }
+
+    #[test]
+    fn json_export_nests_nutrients() {
+        let mut file: Vec<u8> = Vec::new();
+        export(
+            &log_entries(),
+            range(),
+            ExportFormat::Json,
+            Granularity::Day,
+            &mut file,
+        )
+        .unwrap();
+        let document: Value = serde_json::from_slice(&file).unwrap();
+        assert_eq!(document["start"], "2024-03-01");
+        assert_eq!(document["end"], "2024-03-07");
+        assert_eq!(document["nutrient_units"]["vitamin_b12"], "µg");
+        let days = document["days"].as_array().unwrap();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0]["entries"], 2);
+        assert_eq!(days[0]["nutrients"]["kilocalories"], 300.0);
+
+        let mut file: Vec<u8> = Vec::new();
+        export(
+            &log_entries(),
+            range(),
+            ExportFormat::Json,
+            Granularity::Entry,
+            &mut file,
+        )
+        .unwrap();
+        let document: Value = serde_json::from_slice(&file).unwrap();
+        let entries = document["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0]["time"], "07:30");
+        assert_eq!(entries[1]["time"], Value::Null);
+        assert_eq!(entries[1]["serving"], Value::Null);
+        assert_eq!(entries[2]["serving"], "Bowl (100 g)");
+        assert_eq!(entries[2]["nutrients"]["kilocalories"], 400.0);
+    }
+}
@@ -1,25 +1,52 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
+mod amino_acid_score;
+mod backup;
+mod barcode;
+mod chart;
 mod database;
 mod datepicker;
+mod food_data_central;
+mod goals;
+mod ingredient_csv;
 mod ingredients;
+mod log_export;
+mod notifications;
+mod open_food_facts;
 mod pie_chart;
+mod profiles;
+mod reference_intakes;
 mod toggle_image;
-
+mod trends;
+mod units;
+
+use amino_acid_score::{AminoAcidScore, ScoredProteins};
+use backup::RestoreMode;
+use barcode::ScanBuffer;
+use chart::{ChartSeries, ChartStyle};
+use food_data_central::FoodDataError;
+use goals::{Goal, GoalNutrient, GoalStatus};
+use ingredient_csv::{CsvTable, ImportPreview};
 use ingredients::*;
-use pie_chart::{pie_chart, PieChartSlice};
-use toggle_image::toggle_image;
-
-use std::collections::HashSet;
+use log_export::{ExportFormat, Granularity};
+use open_food_facts::{Dump, Product, ProductQuery};
+use pie_chart::PieChartSlice;
+use reference_intakes::{AgeGroup, IntakeProfile, IntakeStatus, Sex};
+use trends::{TrendRange, ROLLING_AVERAGE_DAYS};
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Duration;
 
 use eframe::epaint::textures::TextureFilter;
 use eframe::{egui, NativeOptions};
 use egui::{
     color_picker::{color_edit_button_srgba, Alpha},
-    vec2, Align, CentralPanel, Color32, ComboBox, CursorIcon, Direction, Frame, ImageSource,
-    Layout, Rounding, SizeHint, Slider, Stroke, Style as BaseStyle, TextureOptions,
-    TextureWrapMode, TopBottomPanel, Ui, Vec2, ViewportBuilder, Visuals, WidgetText,
+    vec2, Align, CentralPanel, Color32, ComboBox, Frame, ImageSource, Key, KeyboardShortcut,
+    Modifiers, Rounding, Slider, Style as BaseStyle, TextureOptions, TextureWrapMode,
+    TopBottomPanel, Ui, ViewportBuilder, Visuals, WidgetText,
 };
 
 use egui_dock::{
@@ -30,10 +57,9 @@ use egui_dock::{
 use datepicker::DatePickerButton;
 use egui_extras::{Column, TableBuilder};
 
-use rusqlite::{params, Connection};
-
 use crate::database::Database;
-use chrono::NaiveDate;
+use crate::notifications::Notifications;
+use chrono::{NaiveDate, NaiveTime, Timelike, Weekday};
 
 macro_rules! labeled_widget {
     ($ui:expr, $x:expr, $l:expr) => {
@@ -72,7 +98,17 @@ macro_rules! unit_slider {
 
 const CATEGORY_ICON_NAMES: [&str; 6] = ["apple", "bean", "bread", "candy", "drink", "drop"];
 
-fn get_icon_image_source(id: &str) -> ImageSource {
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+
+// Dumps hold millions of products, only this many search results are listed.
+const PRODUCT_IMPORT_RESULT_LIMIT: usize = 200;
+
+// Reads the products matching a query from a dump, on a background thread.
+type ProductReader = fn(&Path, &ProductQuery) -> Result<Dump, String>;
+
+fn get_icon_image_source(id: &str) -> ImageSource<'_> {
     match id {
         "apple" => egui::include_image!("../icons/categories/apple.png"),
         "bean" => egui::include_image!("../icons/categories/bean.png"),
@@ -89,8 +125,225 @@ fn get_icon_image_source(id: &str) -> ImageSource {
     }
 }
 
+// Builds the slices of a macronutrient pie chart and of the ring breaking each
+// macronutrient down, either by weight or by energy (4/9/4 kcal per gram).
+fn macronutrient_slices(
+    macronutrients: &Macronutrients,
+    by_energy: bool,
+) -> (Vec<PieChartSlice>, Vec<PieChartSlice>) {
+    let Macronutrients {
+        proteins,
+        fats,
+        carbohydrates,
+    } = macronutrients;
+    let (unit, protein_factor, fat_factor, carbohydrate_factor) = if by_energy {
+        ("kcal", 4.0, 9.0, 4.0)
+    } else {
+        ("g", 1.0, 1.0, 1.0)
+    };
+
+    let groups = [
+        (
+            "Protein",
+            Color32::LIGHT_GREEN,
+            vec![
+                (
+                    "Essential amino acids",
+                    proteins.essential_amino_acids.total() * protein_factor,
+                ),
+                (
+                    "Non-essential amino acids",
+                    proteins.non_essential_amino_acids.total() * protein_factor,
+                ),
+                ("Unspecified protein", proteins.unspecified * protein_factor),
+            ],
+        ),
+        (
+            "Fat",
+            Color32::LIGHT_BLUE,
+            vec![
+                ("Saturated fat", fats.saturated * fat_factor),
+                ("Monounsaturated fat", fats.monounsaturated * fat_factor),
+                ("Polyunsaturated fat", fats.polyunsaturated * fat_factor),
+                ("Unspecified fat", fats.unspecified * fat_factor),
+            ],
+        ),
+        (
+            "Carbohydrates",
+            Color32::LIGHT_RED,
+            // Fiber provides no energy and sugar alcohols half, as in Carbohydrates::net_carbs.
+            vec![
+                ("Starch", carbohydrates.starch * carbohydrate_factor),
+                ("Sugars", carbohydrates.sugars * carbohydrate_factor),
+                (
+                    "Unspecified carbohydrates",
+                    carbohydrates.unspecified * carbohydrate_factor,
+                ),
+                (
+                    "Sugar alcohols",
+                    carbohydrates.sugar_alcohols
+                        * if by_energy {
+                            0.5 * carbohydrate_factor
+                        } else {
+                            1.0
+                        },
+                ),
+                ("Fiber", if by_energy { 0.0 } else { carbohydrates.fiber }),
+            ],
+        ),
+    ];
+
+    let total: f32 = groups
+        .iter()
+        .flat_map(|(_, _, parts)| parts.iter().map(|(_, value)| value))
+        .sum();
+    let mut slices = Vec::new();
+    let mut ring_slices = Vec::new();
+    if total <= 0.0 {
+        return (slices, ring_slices);
+    }
+
+    for (name, color, parts) in groups {
+        let value: f32 = parts.iter().map(|(_, value)| value).sum();
+        if value <= 0.0 {
+            continue;
+        }
+        slices.push(PieChartSlice {
+            fraction: value / total,
+            color,
+            tooltip: format!("{}: {:.1} {}", name, value, unit),
+        });
+        for (step, (part_name, part_value)) in parts.into_iter().enumerate() {
+            if part_value <= 0.0 {
+                continue;
+            }
+            let shade = 1.0 - 0.2 * step as f32;
+            ring_slices.push(PieChartSlice {
+                fraction: part_value / total,
+                color: Color32::from_rgb(
+                    (color.r() as f32 * shade) as u8,
+                    (color.g() as f32 * shade) as u8,
+                    (color.b() as f32 * shade) as u8,
+                ),
+                tooltip: format!("{}: {:.1} {}", part_name, part_value, unit),
+            });
+        }
+    }
+
+    (slices, ring_slices)
+}
+
+fn log_entry_amount_text(log_entry: &LogEntry) -> String {
+    match (&log_entry.item, log_entry.unit) {
+        (LogItem::Ingredient(ingredient), Unit::Servings) => {
+            match ingredient.scale_serving(1.0, Unit::Servings, log_entry.serving) {
+                Some((nutritional_info, _)) => {
+                    format!("{} x {}", log_entry.amount, nutritional_info.serving_name())
+                }
+                None => format!("{} servings", log_entry.amount),
+            }
+        }
+        (LogItem::Recipe(_), _) => format!(
+            "{} {}",
+            log_entry.amount,
+            if log_entry.amount == 1.0 {
+                "serving"
+            } else {
+                "servings"
+            }
+        ),
+        (LogItem::Ingredient(_), unit) => format!("{} {}", log_entry.amount, unit),
+    }
+}
+
+// Shows the amino acid score of `proteins` and names the limiting amino acid,
+// along with how much of the protein was scored if not all of it has amino
+// acid data. Hovering lists every amino acid relative to the reference pattern.
+fn amino_acid_score_label(ui: &mut Ui, proteins: &ScoredProteins) {
+    let Some(score) = AminoAcidScore::new(&proteins.profiled) else {
+        ui.label(egui::RichText::new("Amino acid score: no amino acid data").weak());
+        return;
+    };
+    let (limiting, ratio) = score.limiting();
+    let mut text = if ratio < 1.0 {
+        format!(
+            "Amino acid score: {:.0}%, limited by {} ({:.0}% short of the reference)",
+            score.score() * 100.0,
+            limiting.to_string().to_lowercase(),
+            (1.0 - ratio) * 100.0
+        )
+    } else {
+        "Amino acid score: 100%, no limiting amino acid".to_owned()
+    };
+    let share = proteins.profiled_share();
+    if share < 0.995 {
+        text += &format!(
+            ", from the {:.0}% of protein with amino acid data",
+            share * 100.0
+        );
+    }
+    ui.label(text).on_hover_ui(|ui| {
+        egui::Grid::new("amino_acid_ratios")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (amino_acid, ratio) in &score.ratios {
+                    ui.label(amino_acid.to_string());
+                    ui.label(format!("{:.0}% of reference", ratio * 100.0));
+                    ui.end_row();
+                }
+            });
+    });
+}
+
+fn goal_status_color(status: GoalStatus) -> Color32 {
+    match status {
+        GoalStatus::Under => Color32::YELLOW,
+        GoalStatus::On => Color32::GREEN,
+        GoalStatus::Over => Color32::RED,
+    }
+}
+
+fn read_open_food_facts(path: &Path, query: &ProductQuery) -> Result<Dump, String> {
+    let jsonl = path
+        .extension()
+        .is_some_and(|extension| extension == "jsonl" || extension == "json");
+    std::fs::File::open(path)
+        .and_then(|file| {
+            let reader = std::io::BufReader::new(file);
+            if jsonl {
+                open_food_facts::read_jsonl(reader, query)
+            } else {
+                open_food_facts::read_csv(reader, query).map_err(std::io::Error::from)
+            }
+        })
+        .map_err(|error| format!("Unable to read {}: {}", path.display(), error))
+}
+
+// Either a JSON download or a directory with the CSV download.
+fn read_food_data_central(path: &Path, query: &ProductQuery) -> Result<Dump, String> {
+    if path.is_dir() {
+        food_data_central::read_csv(path, query)
+    } else {
+        std::fs::File::open(path)
+            .map_err(FoodDataError::from)
+            .and_then(|file| food_data_central::read_json(std::io::BufReader::new(file), query))
+    }
+    .map_err(|error| format!("Unable to read {}: {}", path.display(), error))
+}
+
 fn main() -> eframe::Result<()> {
     std::env::set_var("RUST_BACKTRACE", "1");
+    env_logger::init();
+
+    let database_location =
+        match profiles::resolve_location(std::env::args().skip(1), |key| std::env::var(key).ok()) {
+            Ok(location) => location,
+            Err(message) => {
+                log::error!("{}", message);
+                std::process::exit(2);
+            }
+        };
+    log::info!("Using database at {}", database_location.path().display());
     let options = NativeOptions {
         viewport: ViewportBuilder::default()
             //.with_inner_size(vec2(1200.0, 800.0))
@@ -156,13 +409,13 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "SophrOSS",
         options,
-        Box::new(|creation_context| {
+        Box::new(move |creation_context| {
             let style = BaseStyle {
                 visuals: Visuals::dark(),
                 ..BaseStyle::default()
             };
             creation_context.egui_ctx.set_style(style);
-            Box::<MyApp>::default()
+            Box::new(MyApp::new(database_location.path()))
         }),
     )
 }
@@ -172,12 +425,13 @@ struct MyApp {
     tree: DockState<String>,
 }
 
-impl Default for MyApp {
-    fn default() -> Self {
+impl MyApp {
+    fn new(database_path: PathBuf) -> Self {
         let phi: f32 = (1.0 + 5.0_f32.sqrt()) / 2.0;
         let mut dock_state = DockState::new(vec![
             "Ingredients".to_owned(),
             "Categories".to_owned(),
+            "Recipes".to_owned(),
             "Style Editor".to_owned(),
         ]);
         dock_state.translations.tab_context_menu.eject_button = "Undock".to_owned();
@@ -190,10 +444,16 @@ impl Default for MyApp {
             dock_state
                 .main_surface_mut()
                 .split_below(a, 1.0 / phi, vec!["Details".to_owned()]);
-        let [_, _] =
-            dock_state
-                .main_surface_mut()
-                .split_below(b, 0.5, vec!["Statistics".to_owned()]);
+        let [_, _] = dock_state.main_surface_mut().split_below(
+            b,
+            0.5,
+            vec![
+                "Statistics".to_owned(),
+                "Goals".to_owned(),
+                "Trends".to_owned(),
+                "Micronutrients".to_owned(),
+            ],
+        );
 
         let mut open_tabs = HashSet::new();
 
@@ -210,6 +470,7 @@ impl Default for MyApp {
             open_tabs,
 
             date: None,
+            focus_tab: None,
 
             show_window_close: true,
             show_window_collapse: true,
@@ -219,22 +480,57 @@ impl Default for MyApp {
             show_tab_name_on_hover: false,
             allowed_splits: AllowedSplits::default(),
 
-            database: Database::new(),
+            database: Database::new(database_path),
+            notifications: Notifications::new(),
+
+            barcode_scan_buffer: ScanBuffer::default(),
+            barcode_scan_unknown: None,
+
+            profiles_list: profiles::list_profiles(),
+            new_profile_name: String::from(""),
+            open_database_path: String::from(""),
+            ingredient_csv_path: String::from(""),
+            backup_path: String::from(""),
+            confirm_backup_replace: false,
 
             ingredients_list: Vec::new(),
             show_new_ingredient_dialog: false,
             update_ingredients: true,
             selected_ingredient: None,
             selected_ingredient_nutrition_info: None,
+            details_macronutrients_by_energy: true,
 
             new_ingredient_name: String::from(""),
             new_ingredient_name_was_empty: false,
             new_ingredient_brand: String::from(""),
+            new_ingredient_barcode: String::from(""),
             new_ingredient_amount: 1.0,
             new_ingredient_unit: Unit::Grams,
+            new_ingredient_servings: Vec::new(),
+            new_ingredient_serving: 0,
+            new_ingredient_serving_name: String::from(""),
+            new_ingredient_serving_usage: HashMap::new(),
             new_ingredient_calories: 0.0,
             new_ingredient_selected_categories: Vec::new(),
             new_ingredient_nutritional_info: None,
+            new_ingredient_conversions: Conversions::default(),
+            editing_ingredient: None,
+            ingredient_delete_usage: None,
+            archived_ingredients_list: Vec::new(),
+            import_csv_table: None,
+            import_csv_mapping: Vec::new(),
+            import_csv_preview: None,
+            open_food_facts_path: String::from(""),
+            food_data_central_path: String::from(""),
+            product_import_source: "",
+            product_import_path: None,
+            product_import_reader: read_open_food_facts,
+            product_import_loading: None,
+            product_import_products: Vec::new(),
+            product_import_truncated: false,
+            product_import_search: String::from(""),
+            product_import_selected: HashSet::new(),
+            product_import_existing: HashSet::new(),
 
             categories_list: Vec::new(),
             show_new_category_dialog: false,
@@ -252,14 +548,60 @@ impl Default for MyApp {
             log_entry_dates: HashSet::new(),
             show_new_log_entry_dialog: false,
             update_log_entries: true,
-            selected_log_entry: None,
-            selected_log_entry_nutrition_info: None,
-
-            new_log_entry_fraction: 1.0,
+            selected_log_entries: Vec::new(),
+            editing_log_entry: None,
+
+            show_log_export_dialog: false,
+            log_export_start: chrono::offset::Utc::now().date_naive(),
+            log_export_end: chrono::offset::Utc::now().date_naive(),
+            log_export_format: ExportFormat::Csv,
+            log_export_granularity: Granularity::Entry,
+            log_export_path: String::from(""),
+
+            new_log_entry_date: chrono::offset::Utc::now().date_naive(),
+            new_log_entry_amount: 1.0,
+            new_log_entry_unit: Unit::Servings,
+            new_log_entry_serving: None,
             new_log_entry_ingredient_search: String::from(""),
             new_log_entry_ingredient_previous_search: String::from(""),
-            new_log_entry_filtered_ingredients: Vec::new(),
-            new_log_entry_selected_ingredient: None,
+            new_log_entry_filtered_items: Vec::new(),
+            new_log_entry_selected_item: None,
+            new_log_entry_meal: None,
+            new_log_entry_new_meal_name: String::from(""),
+            new_log_entry_time_enabled: false,
+            new_log_entry_time: NaiveTime::MIN,
+
+            meals_list: Vec::new(),
+
+            recipes_list: Vec::new(),
+            update_recipes: true,
+            selected_recipe: None,
+            editing_recipe: None,
+            recipe_ingredient_search: String::from(""),
+
+            goals_list: Vec::new(),
+            update_goals: true,
+            goals_selected_weekday: None,
+            goal_day_colors: HashMap::new(),
+
+            trends_range: TrendRange::Month,
+            trends_start: chrono::offset::Utc::now().date_naive(),
+            trends_end: chrono::offset::Utc::now().date_naive(),
+            trends_style: ChartStyle::Line,
+            trends_rolling_average: true,
+            trends_micronutrient: "vitamin_c",
+            update_trends: true,
+            trends_loaded_range: None,
+            trends_totals: HashMap::new(),
+
+            intake_profile: IntakeProfile::default(),
+            update_intake_profile: true,
+            intakes_range_enabled: false,
+            intakes_start: chrono::offset::Utc::now().date_naive(),
+            intakes_end: chrono::offset::Utc::now().date_naive(),
+            update_intakes: true,
+            intakes_loaded_range: None,
+            intakes_range_totals: (NutrientTotals::default(), 0),
         };
 
         Self {
@@ -276,8 +618,274 @@ impl eframe::App for MyApp {
             .get_or_insert_with(|| chrono::offset::Utc::now().date_naive());
 
         egui_extras::install_image_loaders(ctx);
+        // Text fields keep their own undo while they have focus.
+        if !ctx.wants_keyboard_input() {
+            if ctx.input_mut(|input| input.consume_shortcut(&REDO_SHORTCUT)) {
+                self.context.redo();
+            } else if ctx.input_mut(|input| input.consume_shortcut(&UNDO_SHORTCUT)) {
+                self.context.undo();
+            }
+
+            let scan = ctx.input(|input| {
+                let mut scan = None;
+                for event in &input.events {
+                    match event {
+                        egui::Event::Text(text) => {
+                            self.context.barcode_scan_buffer.push(text, input.time);
+                        }
+                        egui::Event::Key {
+                            key: Key::Enter,
+                            pressed: true,
+                            ..
+                        } => scan = self.context.barcode_scan_buffer.finish(input.time),
+                        _ => {}
+                    }
+                }
+                scan
+            });
+            if let Some(code) = scan {
+                self.context.scan_barcode(&code);
+            }
+        }
+        if let Some(tab) = self.context.focus_tab.take() {
+            if let Some(location) = self.tree.find_tab(&tab.to_owned()) {
+                self.tree.set_active_tab(location);
+                self.tree
+                    .set_focused_node_and_surface((location.0, location.1));
+            }
+        }
+
         TopBottomPanel::top("egui_dock::MenuBar").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    ui.menu_button("Switch profile", |ui| {
+                        let current_profile = profiles::profile_name(self.context.database.path());
+                        for profile in self.context.profiles_list.clone() {
+                            if ui
+                                .selectable_label(
+                                    current_profile.as_ref() == Some(&profile),
+                                    &profile,
+                                )
+                                .clicked()
+                            {
+                                self.context
+                                    .switch_database(profiles::profile_path(&profile));
+                                ui.close_menu();
+                            }
+                        }
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.context.new_profile_name)
+                                    .hint_text("New profile"),
+                            );
+                            if ui
+                                .add_enabled(
+                                    profiles::is_valid_profile_name(&self.context.new_profile_name),
+                                    egui::Button::new("Create"),
+                                )
+                                .clicked()
+                            {
+                                let path = profiles::profile_path(&self.context.new_profile_name);
+                                self.context.new_profile_name.clear();
+                                self.context.switch_database(path);
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    ui.menu_button("Open database file", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.context.open_database_path)
+                                    .hint_text("path/to/data.db"),
+                            );
+                            if ui
+                                .add_enabled(
+                                    !self.context.open_database_path.trim().is_empty(),
+                                    egui::Button::new("Open"),
+                                )
+                                .clicked()
+                            {
+                                let path = PathBuf::from(self.context.open_database_path.trim());
+                                self.context.open_database_path.clear();
+                                self.context.switch_database(path);
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    ui.separator();
+                    ui.menu_button("Import ingredients from CSV", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.context.ingredient_csv_path)
+                                    .hint_text("path/to/ingredients.csv"),
+                            );
+                            if ui
+                                .add_enabled(
+                                    !self.context.ingredient_csv_path.trim().is_empty(),
+                                    egui::Button::new("Import"),
+                                )
+                                .clicked()
+                            {
+                                self.context.read_ingredient_csv();
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    ui.menu_button("Import from Open Food Facts", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.context.open_food_facts_path)
+                                    .hint_text("path/to/products.csv or .jsonl"),
+                            );
+                            if ui
+                                .add_enabled(
+                                    !self.context.open_food_facts_path.trim().is_empty(),
+                                    egui::Button::new("Load"),
+                                )
+                                .clicked()
+                            {
+                                self.context.load_open_food_facts();
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    ui.menu_button("Import from USDA FoodData Central", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(
+                                    &mut self.context.food_data_central_path,
+                                )
+                                .hint_text("path/to/foods.json or CSV directory"),
+                            );
+                            if ui
+                                .add_enabled(
+                                    !self.context.food_data_central_path.trim().is_empty(),
+                                    egui::Button::new("Load"),
+                                )
+                                .clicked()
+                            {
+                                self.context.load_food_data_central();
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    ui.menu_button("Export ingredients to CSV", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.context.ingredient_csv_path)
+                                    .hint_text("path/to/ingredients.csv"),
+                            );
+                            if ui
+                                .add_enabled(
+                                    !self.context.ingredient_csv_path.trim().is_empty(),
+                                    egui::Button::new("Export"),
+                                )
+                                .clicked()
+                            {
+                                self.context.write_ingredient_csv();
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    ui.separator();
+                    ui.menu_button("Back up database", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.context.backup_path)
+                                    .hint_text("path/to/backup.json"),
+                            );
+                            if ui
+                                .add_enabled(
+                                    !self.context.backup_path.trim().is_empty(),
+                                    egui::Button::new("Back up"),
+                                )
+                                .clicked()
+                            {
+                                self.context.write_backup();
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    let restore_menu = ui.menu_button("Restore backup", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.context.backup_path)
+                                    .hint_text("path/to/backup.json"),
+                            );
+                            let valid_path = !self.context.backup_path.trim().is_empty();
+                            if ui
+                                .add_enabled(valid_path, egui::Button::new("Merge"))
+                                .on_hover_text("Add the backup to the current data.")
+                                .clicked()
+                            {
+                                self.context.restore_backup(RestoreMode::Merge);
+                                ui.close_menu();
+                            }
+                            if ui
+                                .add_enabled(valid_path, egui::Button::new("Replace"))
+                                .on_hover_text("Remove the current data and restore the backup.")
+                                .clicked()
+                            {
+                                self.context.confirm_backup_replace = true;
+                            }
+                        });
+                        if self.context.confirm_backup_replace {
+                            ui.horizontal(|ui| {
+                                ui.colored_label(
+                                    Color32::from_rgb(192, 32, 16),
+                                    "This will remove all current data.",
+                                );
+                                if ui.button("Replace anyway").clicked() {
+                                    self.context.confirm_backup_replace = false;
+                                    self.context.restore_backup(RestoreMode::Replace);
+                                    ui.close_menu();
+                                }
+                                if ui.button("Cancel").clicked() {
+                                    self.context.confirm_backup_replace = false;
+                                }
+                            });
+                        }
+                    });
+                    // An unanswered confirmation is dropped once the menu closes.
+                    if restore_menu.inner.is_none() {
+                        self.context.confirm_backup_replace = false;
+                    }
+                });
+                ui.menu_button("Edit", |ui| {
+                    let undo_description = self.context.database.undo_description();
+                    if ui
+                        .add_enabled(
+                            undo_description.is_some(),
+                            egui::Button::new(
+                                undo_description.map_or("Undo".to_owned(), |description| {
+                                    format!("Undo {}", description)
+                                }),
+                            )
+                            .shortcut_text(ctx.format_shortcut(&UNDO_SHORTCUT)),
+                        )
+                        .clicked()
+                    {
+                        self.context.undo();
+                        ui.close_menu();
+                    }
+                    let redo_description = self.context.database.redo_description();
+                    if ui
+                        .add_enabled(
+                            redo_description.is_some(),
+                            egui::Button::new(
+                                redo_description.map_or("Redo".to_owned(), |description| {
+                                    format!("Redo {}", description)
+                                }),
+                            )
+                            .shortcut_text(ctx.format_shortcut(&REDO_SHORTCUT)),
+                        )
+                        .clicked()
+                    {
+                        self.context.redo();
+                        ui.close_menu();
+                    }
+                });
                 ui.menu_button("View", |ui| {
                     // allow certain tabs to be toggled
                     for tab in &["Style Editor"] {
@@ -297,8 +905,24 @@ impl eframe::App for MyApp {
                         }
                     }
                 });
+                ui.with_layout(egui::Layout::right_to_left(Align::Center), |ui| {
+                    let database_path = self.context.database.path();
+                    ui.label(
+                        egui::RichText::new(
+                            profiles::profile_name(database_path)
+                                .unwrap_or_else(|| database_path.display().to_string()),
+                        )
+                        .weak(),
+                    )
+                    .on_hover_text(database_path.display().to_string());
+                });
             })
         });
+        if !self.context.notifications.is_empty() {
+            TopBottomPanel::bottom("notifications").show(ctx, |ui| {
+                self.context.notifications.show(ui);
+            });
+        }
         CentralPanel::default()
             // When displaying a DockArea in another UI, it looks better
             // to set inner margins to 0.
@@ -333,6 +957,8 @@ struct MyContext {
     open_tabs: HashSet<String>,
 
     date: Option<NaiveDate>,
+    // Tab to bring to the front on the next frame.
+    focus_tab: Option<&'static str>,
 
     show_close_buttons: bool,
     show_add_buttons: bool,
@@ -343,21 +969,66 @@ struct MyContext {
     show_window_collapse: bool,
 
     database: Database,
+    notifications: Notifications,
+
+    barcode_scan_buffer: ScanBuffer,
+    // A scanned barcode no ingredient has yet.
+    barcode_scan_unknown: Option<String>,
+
+    profiles_list: Vec<String>,
+    new_profile_name: String,
+    open_database_path: String,
+    ingredient_csv_path: String,
+    backup_path: String,
+    // Replacing removes all current data, it has to be confirmed first.
+    confirm_backup_replace: bool,
 
     ingredients_list: Vec<Rc<Ingredient>>,
     show_new_ingredient_dialog: bool,
     update_ingredients: bool,
     selected_ingredient: Option<usize>,
     selected_ingredient_nutrition_info: Option<usize>,
+    details_macronutrients_by_energy: bool,
 
     new_ingredient_name: String,
     new_ingredient_name_was_empty: bool,
     new_ingredient_brand: String,
+    new_ingredient_barcode: String,
     new_ingredient_amount: f32,
     new_ingredient_unit: Unit,
+    // All servings of the dialog, the fields above edit the one at `new_ingredient_serving`.
+    new_ingredient_servings: Vec<NutritionalInfo>,
+    new_ingredient_serving: usize,
+    new_ingredient_serving_name: String,
+    // Log entries by serving id, logged servings cannot be removed.
+    new_ingredient_serving_usage: HashMap<u32, usize>,
     new_ingredient_calories: f32,
-    new_ingredient_selected_categories: Vec<usize>,
+    // Ids rather than indices, `categories_list` is reloaded after undo and redo.
+    new_ingredient_selected_categories: Vec<u32>,
     new_ingredient_nutritional_info: Option<NutritionalInfo>,
+    new_ingredient_conversions: Conversions,
+    editing_ingredient: Option<Rc<Ingredient>>,
+    ingredient_delete_usage: Option<(u32, IngredientUsage)>,
+    archived_ingredients_list: Vec<Rc<Ingredient>>,
+    import_csv_table: Option<CsvTable>,
+    import_csv_mapping: Vec<Option<usize>>,
+    import_csv_preview: Option<ImportPreview>,
+    open_food_facts_path: String,
+    food_data_central_path: String,
+    // Where the loaded products come from, shown as the panel heading.
+    product_import_source: &'static str,
+    // The dump that is searched, the panel is shown while there is one.
+    product_import_path: Option<PathBuf>,
+    product_import_reader: ProductReader,
+    // The search running on a background thread and where its result arrives.
+    product_import_loading: Option<(ProductQuery, Receiver<Result<Dump, String>>)>,
+    product_import_products: Vec<Product>,
+    // Whether more products match than are listed.
+    product_import_truncated: bool,
+    product_import_search: String,
+    product_import_selected: HashSet<usize>,
+    // Products that were not imported because an ingredient for them exists already.
+    product_import_existing: HashSet<usize>,
 
     categories_list: Vec<Category>,
     show_new_category_dialog: bool,
@@ -375,80 +1046,376 @@ struct MyContext {
     log_entry_dates: HashSet<NaiveDate>,
     show_new_log_entry_dialog: bool,
     update_log_entries: bool,
-    selected_log_entry: Option<usize>,
-    selected_log_entry_nutrition_info: Option<usize>,
-
-    new_log_entry_fraction: f32,
+    selected_log_entries: Vec<usize>,
+    editing_log_entry: Option<LogEntry>,
+
+    show_log_export_dialog: bool,
+    log_export_start: NaiveDate,
+    log_export_end: NaiveDate,
+    log_export_format: ExportFormat,
+    log_export_granularity: Granularity,
+    log_export_path: String,
+
+    new_log_entry_date: NaiveDate,
+    new_log_entry_amount: f32,
+    new_log_entry_unit: Unit,
+    // The serving of the selected ingredient, `None` for its first one.
+    new_log_entry_serving: Option<u32>,
     new_log_entry_ingredient_search: String,
     new_log_entry_ingredient_previous_search: String,
-    new_log_entry_filtered_ingredients: Vec<Rc<Ingredient>>,
-    new_log_entry_selected_ingredient: Option<Rc<Ingredient>>,
+    new_log_entry_filtered_items: Vec<LogItem>,
+    new_log_entry_selected_item: Option<LogItem>,
+    new_log_entry_meal: Option<Meal>,
+    new_log_entry_new_meal_name: String,
+    new_log_entry_time_enabled: bool,
+    new_log_entry_time: NaiveTime,
+
+    meals_list: Vec<Meal>,
+
+    recipes_list: Vec<Rc<Recipe>>,
+    update_recipes: bool,
+    selected_recipe: Option<usize>,
+    editing_recipe: Option<Recipe>,
+    recipe_ingredient_search: String,
+
+    goals_list: Vec<Goal>,
+    update_goals: bool,
+    goals_selected_weekday: Option<Weekday>,
+    goal_day_colors: HashMap<NaiveDate, Color32>,
+
+    trends_range: TrendRange,
+    trends_start: NaiveDate,
+    trends_end: NaiveDate,
+    trends_style: ChartStyle,
+    trends_rolling_average: bool,
+    // A field of `NUTRIENT_FIELDS`.
+    trends_micronutrient: &'static str,
+    update_trends: bool,
+    trends_loaded_range: Option<(NaiveDate, NaiveDate)>,
+    trends_totals: HashMap<NaiveDate, NutrientTotals>,
+
+    intake_profile: IntakeProfile,
+    update_intake_profile: bool,
+    // Whether the report covers the date range instead of the shown day.
+    intakes_range_enabled: bool,
+    intakes_start: NaiveDate,
+    intakes_end: NaiveDate,
+    update_intakes: bool,
+    intakes_loaded_range: Option<(NaiveDate, NaiveDate)>,
+    // The nutrients summed over the range and the number of logged days.
+    intakes_range_totals: (NutrientTotals, usize),
 }
 
 impl MyContext {
+    fn switch_database(&mut self, path: PathBuf) {
+        self.database = Database::new(path);
+        self.profiles_list = profiles::list_profiles();
+
+        self.ingredients_list.clear();
+        self.archived_ingredients_list.clear();
+        self.ingredient_delete_usage = None;
+        self.import_csv_table = None;
+        self.import_csv_preview = None;
+        self.product_import_selected.clear();
+        self.product_import_existing.clear();
+        self.categories_list.clear();
+        self.log_entry_list.clear();
+        self.log_entry_dates.clear();
+        self.goals_list.clear();
+        self.goal_day_colors.clear();
+        self.trends_totals.clear();
+        self.meals_list.clear();
+        self.recipes_list.clear();
+        self.selected_recipe = None;
+        self.editing_recipe = None;
+
+        self.selected_ingredient = None;
+        self.selected_ingredient_nutrition_info = None;
+        self.selected_category = None;
+        self.selected_log_entries.clear();
+
+        self.show_new_ingredient_dialog = false;
+        self.show_new_category_dialog = false;
+        self.show_new_log_entry_dialog = false;
+        self.show_log_export_dialog = false;
+        self.editing_ingredient = None;
+        self.editing_log_entry = None;
+
+        // The log entry dialog and scans hold items of the previous database.
+        self.new_log_entry_amount = 1.0;
+        self.new_log_entry_unit = Unit::Servings;
+        self.new_log_entry_serving = None;
+        self.new_log_entry_ingredient_search = String::from("");
+        self.new_log_entry_ingredient_previous_search = String::from("");
+        self.new_log_entry_filtered_items.clear();
+        self.new_log_entry_selected_item = None;
+        self.new_log_entry_meal = None;
+        self.new_log_entry_new_meal_name = String::from("");
+        self.new_log_entry_time_enabled = false;
+        self.barcode_scan_buffer = ScanBuffer::default();
+        self.barcode_scan_unknown = None;
+
+        self.update_ingredients = true;
+        self.update_categories = true;
+        self.update_log_entries = true;
+        self.update_goals = true;
+        self.update_trends = true;
+        self.update_intake_profile = true;
+        self.update_recipes = true;
+    }
+
+    fn write_backup(&mut self) {
+        let Some(backup) = self.notifications.report(self.database.get_backup()) else {
+            return;
+        };
+        let path = PathBuf::from(self.backup_path.trim());
+        let result = std::fs::File::create(&path)
+            .map_err(backup::BackupError::from)
+            .and_then(|file| backup::write(&backup, std::io::BufWriter::new(file)))
+            .map_err(|error| format!("Unable to write {}: {}", path.display(), error));
+        self.notifications.report(result);
+    }
+
+    fn restore_backup(&mut self, mode: RestoreMode) {
+        let path = PathBuf::from(self.backup_path.trim());
+        let backup = std::fs::File::open(&path)
+            .map_err(backup::BackupError::from)
+            .and_then(|file| backup::read(std::io::BufReader::new(file)))
+            .map_err(|error| format!("Unable to read {}: {}", path.display(), error));
+        let Some(backup) = self.notifications.report(backup) else {
+            return;
+        };
+        if self
+            .notifications
+            .report(self.database.restore_backup(&backup, mode))
+            .is_some()
+        {
+            self.reload_after_history_change();
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(Some(_)) = self.notifications.report(self.database.undo()) {
+            self.reload_after_history_change();
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(Some(_)) = self.notifications.report(self.database.redo()) {
+            self.reload_after_history_change();
+        }
+    }
+
+    // Any list may have changed, and selected indices can point at other rows now.
+    // Open edit dialogs are closed, the row they edit may no longer exist.
+    fn reload_after_history_change(&mut self) {
+        self.selected_ingredient = None;
+        self.selected_ingredient_nutrition_info = None;
+        self.selected_category = None;
+        self.selected_log_entries.clear();
+        self.ingredient_delete_usage = None;
+
+        if self.editing_ingredient.take().is_some() {
+            self.clear_new_ingredient();
+            self.show_new_ingredient_dialog = false;
+        }
+        if self.editing_log_entry.take().is_some() {
+            self.clear_new_log_entry();
+            self.show_new_log_entry_dialog = false;
+        }
+
+        self.update_ingredients = true;
+        self.update_categories = true;
+        self.update_log_entries = true;
+        self.update_goals = true;
+        self.update_intake_profile = true;
+        self.update_recipes = true;
+    }
+
+    fn clear_new_ingredient(&mut self) {
+        self.new_ingredient_name.clear();
+        self.new_ingredient_name_was_empty = false;
+        self.new_ingredient_brand.clear();
+        self.new_ingredient_barcode.clear();
+        self.new_ingredient_amount = 1.0;
+        self.new_ingredient_servings.clear();
+        self.new_ingredient_serving = 0;
+        self.new_ingredient_serving_name.clear();
+        self.new_ingredient_serving_usage.clear();
+        self.new_ingredient_selected_categories.clear();
+        self.new_ingredient_calories = 0.0;
+        self.new_ingredient_conversions = Conversions::default();
+        self.new_ingredient_nutritional_info = Some(NutritionalInfo {
+            default_amount: 1.0,
+            ..Default::default()
+        });
+    }
+
     fn new_ingredient(&mut self, ui: &mut Ui) {
+        fn nutrient_input(ui: &mut Ui, label: &str, value: &mut f32, unit: &str) {
+            ui.label(label);
+            ui.add(
+                egui::DragValue::new(value)
+                    .clamp_range(0.0..=f32::MAX)
+                    .speed(0.1)
+                    .max_decimals(3),
+            );
+            ui.label(unit);
+            ui.end_row();
+        }
+
+        fn nutritional_info_input(ui: &mut Ui, nutritional_info: &mut NutritionalInfo) {
+            ui.collapsing("Proteins", |ui| {
+                let proteins = &mut nutritional_info.macronutrients.proteins;
+                ui.label(format!("Total: {:.1} g", proteins.total_proteins()));
+                ui.collapsing("Essential amino acids", |ui| {
+                    let amino_acids = &mut proteins.essential_amino_acids;
+                    egui::Grid::new("new_ingredient_essential_amino_acids").show(ui, |ui| {
+                        nutrient_input(ui, "Histidine", &mut amino_acids.histidine, "g");
+                        nutrient_input(ui, "Isoleucine", &mut amino_acids.isoleucine, "g");
+                        nutrient_input(ui, "Leucine", &mut amino_acids.leucine, "g");
+                        nutrient_input(ui, "Lysine", &mut amino_acids.lysine, "g");
+                        nutrient_input(ui, "Methionine", &mut amino_acids.methionine, "g");
+                        nutrient_input(ui, "Phenylalanine", &mut amino_acids.phenylalanine, "g");
+                        nutrient_input(ui, "Threonine", &mut amino_acids.threonine, "g");
+                        nutrient_input(ui, "Tryptophan", &mut amino_acids.tryptophan, "g");
+                        nutrient_input(ui, "Valine", &mut amino_acids.valine, "g");
+                    });
+                });
+                ui.collapsing("Non-essential amino acids", |ui| {
+                    let amino_acids = &mut proteins.non_essential_amino_acids;
+                    egui::Grid::new("new_ingredient_non_essential_amino_acids").show(ui, |ui| {
+                        nutrient_input(ui, "Alanine", &mut amino_acids.alanine, "g");
+                        nutrient_input(ui, "Arginine", &mut amino_acids.arginine, "g");
+                        nutrient_input(ui, "Asparagine", &mut amino_acids.asparagine, "g");
+                        nutrient_input(ui, "Aspartic acid", &mut amino_acids.aspartic_acid, "g");
+                        nutrient_input(ui, "Cysteine", &mut amino_acids.cysteine, "g");
+                        nutrient_input(ui, "Glutamic acid", &mut amino_acids.glutamic_acid, "g");
+                        nutrient_input(ui, "Glutamine", &mut amino_acids.glutamine, "g");
+                        nutrient_input(ui, "Glycine", &mut amino_acids.glycine, "g");
+                        nutrient_input(ui, "Proline", &mut amino_acids.proline, "g");
+                        nutrient_input(ui, "Serine", &mut amino_acids.serine, "g");
+                        nutrient_input(ui, "Tyrosine", &mut amino_acids.tyrosine, "g");
+                    });
+                });
+                egui::Grid::new("new_ingredient_unspecified_protein").show(ui, |ui| {
+                    nutrient_input(ui, "Unspecified", &mut proteins.unspecified, "g");
+                });
+            });
+            ui.collapsing("Fats", |ui| {
+                let fats = &mut nutritional_info.macronutrients.fats;
+                egui::Grid::new("new_ingredient_fats").show(ui, |ui| {
+                    nutrient_input(ui, "Saturated", &mut fats.saturated, "g");
+                    nutrient_input(ui, "Monounsaturated", &mut fats.monounsaturated, "g");
+                    nutrient_input(ui, "Polyunsaturated", &mut fats.polyunsaturated, "g");
+                    nutrient_input(ui, "Unspecified", &mut fats.unspecified, "g");
+                });
+            });
+            ui.collapsing("Carbohydrates", |ui| {
+                let carbohydrates = &mut nutritional_info.macronutrients.carbohydrates;
+                egui::Grid::new("new_ingredient_carbohydrates").show(ui, |ui| {
+                    nutrient_input(ui, "Starch", &mut carbohydrates.starch, "g");
+                    nutrient_input(ui, "Fiber", &mut carbohydrates.fiber, "g");
+                    nutrient_input(ui, "Sugars", &mut carbohydrates.sugars, "g");
+                    nutrient_input(ui, "Sugar alcohols", &mut carbohydrates.sugar_alcohols, "g");
+                    nutrient_input(ui, "Unspecified", &mut carbohydrates.unspecified, "g");
+                });
+            });
+            ui.collapsing("Vitamins", |ui| {
+                let vitamins = &mut nutritional_info.micronutrients.vitamins;
+                egui::Grid::new("new_ingredient_vitamins").show(ui, |ui| {
+                    nutrient_input(ui, "Vitamin A", &mut vitamins.vitamin_a, "µg");
+                    nutrient_input(ui, "Vitamin B1", &mut vitamins.vitamin_b1, "mg");
+                    nutrient_input(ui, "Vitamin B2", &mut vitamins.vitamin_b2, "mg");
+                    nutrient_input(ui, "Vitamin B3", &mut vitamins.vitamin_b3, "mg");
+                    nutrient_input(ui, "Vitamin B5", &mut vitamins.vitamin_b5, "mg");
+                    nutrient_input(ui, "Vitamin B6", &mut vitamins.vitamin_b6, "mg");
+                    nutrient_input(ui, "Vitamin B9", &mut vitamins.vitamin_b9, "µg");
+                    nutrient_input(ui, "Vitamin B12", &mut vitamins.vitamin_b12, "µg");
+                    nutrient_input(ui, "Vitamin C", &mut vitamins.vitamin_c, "mg");
+                    nutrient_input(ui, "Vitamin D", &mut vitamins.vitamin_d, "µg");
+                    nutrient_input(ui, "Vitamin E", &mut vitamins.vitamin_e, "mg");
+                    nutrient_input(ui, "Vitamin K", &mut vitamins.vitamin_k, "µg");
+                    nutrient_input(ui, "Betaine", &mut vitamins.betaine, "mg");
+                    nutrient_input(ui, "Choline", &mut vitamins.choline, "mg");
+                });
+            });
+            ui.collapsing("Minerals", |ui| {
+                let minerals = &mut nutritional_info.micronutrients.minerals;
+                egui::Grid::new("new_ingredient_minerals").show(ui, |ui| {
+                    nutrient_input(ui, "Calcium", &mut minerals.calcium, "mg");
+                    nutrient_input(ui, "Copper", &mut minerals.copper, "mg");
+                    nutrient_input(ui, "Iron", &mut minerals.iron, "mg");
+                    nutrient_input(ui, "Magnesium", &mut minerals.magnesium, "mg");
+                    nutrient_input(ui, "Manganese", &mut minerals.manganese, "mg");
+                    nutrient_input(ui, "Phosphorus", &mut minerals.phosphorus, "mg");
+                    nutrient_input(ui, "Potassium", &mut minerals.potassium, "mg");
+                    nutrient_input(ui, "Selenium", &mut minerals.selenium, "µg");
+                    nutrient_input(ui, "Sodium", &mut minerals.sodium, "mg");
+                    nutrient_input(ui, "Zinc", &mut minerals.zinc, "mg");
+                });
+            });
+        }
+
         macro_rules! create {
             () => {
-                if self.new_ingredient_name.len() == 0 {
+                let barcode = if self.new_ingredient_name.len() == 0 {
                     self.new_ingredient_name_was_empty = true;
+                    None
                 } else {
-                    self.new_ingredient_nutritional_info
-                        .as_mut()
-                        .unwrap()
-                        .default_amount = self.new_ingredient_amount;
-                    self.new_ingredient_nutritional_info
-                        .as_mut()
-                        .unwrap()
-                        .default_unit = self.new_ingredient_unit;
-                    self.new_ingredient_nutritional_info
-                        .as_mut()
-                        .unwrap()
-                        .kilocalories = self.new_ingredient_calories;
+                    let barcode = self.new_ingredient_barcode();
+                    self.notifications.report(barcode)
+                };
+                if let Some(barcode) = barcode {
+                    self.store_new_ingredient_serving();
+                    let nutritional_info = self.new_ingredient_servings.clone();
 
                     let new_ingredient = Ingredient {
-                        id: 0,
+                        id: self.editing_ingredient.as_ref().map_or(0, |n| n.id),
                         name: self.new_ingredient_name.clone(),
                         brand: self.new_ingredient_brand.clone(),
+                        barcode,
                         categories: self
-                            .new_ingredient_selected_categories
+                            .categories_list
                             .iter()
-                            .map(|n| self.categories_list[*n].clone())
+                            .filter(|n| self.new_ingredient_selected_categories.contains(&n.id))
+                            .cloned()
                             .collect(),
-                        nutritional_info: vec![self
-                            .new_ingredient_nutritional_info
-                            .clone()
-                            .unwrap()],
+                        nutritional_info,
+                        conversions: self.new_ingredient_conversions,
+                        archived: self.editing_ingredient.as_ref().is_some_and(|n| n.archived),
                     };
 
-                    let _ = self.database.insert_ingredient(&new_ingredient);
+                    let result = if self.editing_ingredient.is_some() {
+                        self.database.update_ingredient(&new_ingredient)
+                    } else {
+                        self.database.insert_ingredient(&new_ingredient).map(|_| ())
+                    };
 
-                    self.update_ingredients = true;
-                    cancel!();
+                    if self.notifications.report(result).is_some() {
+                        self.update_ingredients = true;
+                        cancel!();
+                    }
                 }
             };
         }
-        macro_rules! clear {
-            () => {
-                self.new_ingredient_name.clear();
-                self.new_ingredient_name_was_empty = false;
-                self.new_ingredient_brand.clear();
-                self.new_ingredient_amount = 1.0;
-                self.new_ingredient_selected_categories.clear();
-                self.new_ingredient_calories = 0.0;
-            };
-        }
         macro_rules! cancel {
             () => {
-                clear!();
+                self.clear_new_ingredient();
+                self.editing_ingredient = None;
                 self.show_new_ingredient_dialog = false;
             };
         }
 
-        ui.heading("Create new ingredient");
+        ui.heading(if self.editing_ingredient.is_some() {
+            "Edit ingredient"
+        } else {
+            "Create new ingredient"
+        });
         ui.horizontal(|ui| {
             ui.label("Name: ");
             let result = ui.text_edit_singleline(&mut self.new_ingredient_name);
-            if result.changed() && self.new_ingredient_name.len() > 0 {
+            if result.changed() && !self.new_ingredient_name.is_empty() {
                 self.new_ingredient_name_was_empty = false;
             }
             if self.new_ingredient_name_was_empty {
@@ -462,39 +1429,192 @@ impl MyContext {
             ui.label("Brand: ");
             let _ = ui.text_edit_singleline(&mut self.new_ingredient_brand);
         });
+        ui.horizontal(|ui| {
+            ui.label("Barcode: ");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_ingredient_barcode)
+                    .hint_text("EAN or UPC"),
+            );
+            if !self.new_ingredient_barcode.trim().is_empty() {
+                if let Err(error) = barcode::normalize(&self.new_ingredient_barcode) {
+                    ui.colored_label(Color32::from_rgb(192, 32, 16), error.to_string());
+                }
+            }
+        });
+        // Keeps the serving list up to date with the fields for its labels.
+        self.store_new_ingredient_serving();
+        let mut selected_serving: Option<usize> = None;
+        let mut add_serving = false;
+        let mut remove_serving = false;
+        let mut scale_serving: Option<f32> = None;
+        ui.horizontal(|ui| {
+            ui.label("Serving: ");
+            ComboBox::from_id_source("new_ingredient_serving")
+                .selected_text(
+                    self.new_ingredient_servings
+                        .get(self.new_ingredient_serving)
+                        .map_or(String::new(), NutritionalInfo::serving_name),
+                )
+                .show_ui(ui, |ui| {
+                    for (index, serving) in self.new_ingredient_servings.iter().enumerate() {
+                        if ui
+                            .selectable_label(
+                                index == self.new_ingredient_serving,
+                                serving.serving_name(),
+                            )
+                            .clicked()
+                        {
+                            selected_serving = Some(index);
+                        }
+                    }
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_ingredient_serving_name)
+                    .desired_width(96.0)
+                    .hint_text("Name, e.g. slice"),
+            );
+            add_serving = ui.button("Add serving").clicked();
+            let log_entries = self
+                .new_ingredient_servings
+                .get(self.new_ingredient_serving)
+                .and_then(|serving| self.new_ingredient_serving_usage.get(&serving.id))
+                .copied()
+                .unwrap_or(0);
+            remove_serving = ui
+                .add_enabled(
+                    self.new_ingredient_servings.len() > 1 && log_entries == 0,
+                    egui::Button::new("Remove"),
+                )
+                .on_disabled_hover_text(if log_entries > 0 {
+                    format!("Used by {} log entries.", log_entries)
+                } else {
+                    "An ingredient needs at least one serving.".to_owned()
+                })
+                .clicked();
+            if self.new_ingredient_serving > 0 {
+                let first = &self.new_ingredient_servings[0];
+                let factor = self
+                    .new_ingredient_conversions
+                    .convert(
+                        self.new_ingredient_amount,
+                        self.new_ingredient_unit,
+                        first.default_unit,
+                    )
+                    .filter(|_| first.default_amount > 0.0)
+                    .map(|amount| amount / first.default_amount);
+                if ui
+                    .add_enabled(
+                        factor.is_some(),
+                        egui::Button::new(format!("Scale from {}", first.serving_name())),
+                    )
+                    .on_hover_text("Calculates the nutrients from the first serving.")
+                    .on_disabled_hover_text(
+                        "Needs the density or piece weight to convert between the units.",
+                    )
+                    .clicked()
+                {
+                    scale_serving = factor;
+                }
+            }
+        });
+        if let Some(index) = selected_serving {
+            self.load_new_ingredient_serving(index);
+        } else if add_serving && !self.new_ingredient_servings.is_empty() {
+            let mut serving = self.new_ingredient_servings[self.new_ingredient_serving].clone();
+            serving.id = 0;
+            serving.name.clear();
+            self.new_ingredient_servings.push(serving);
+            self.load_new_ingredient_serving(self.new_ingredient_servings.len() - 1);
+        } else if remove_serving {
+            self.new_ingredient_servings
+                .remove(self.new_ingredient_serving);
+            self.load_new_ingredient_serving(
+                self.new_ingredient_serving
+                    .min(self.new_ingredient_servings.len() - 1),
+            );
+        } else if let Some(factor) = scale_serving {
+            let first = self.new_ingredient_servings[0].clone();
+            if let Some(nutritional_info) = self.new_ingredient_nutritional_info.as_mut() {
+                nutritional_info.copy_nutrients_scaled(&first, factor);
+            }
+            self.new_ingredient_calories = first.kilocalories * factor;
+        }
         ui.horizontal(|ui| {
             ui.label("Amount: ");
-            ui.add(egui::DragValue::new(&mut self.new_ingredient_amount).clamp_range(0..=9999));
+            ui.add(
+                egui::DragValue::new(&mut self.new_ingredient_amount).clamp_range(0.0..=f32::MAX),
+            );
             ComboBox::from_label("Default unit")
                 .selected_text(self.new_ingredient_unit.to_string())
                 .show_ui(ui, |ui| {
-                    for unit in [
-                        Unit::Grams,
-                        Unit::Teaspoons,
-                        Unit::Tablespoons,
-                        Unit::Pieces,
-                    ] {
+                    for unit in Unit::MEASURES {
                         ui.selectable_value(&mut self.new_ingredient_unit, unit, unit.to_string());
                     }
                 });
         });
         ui.horizontal(|ui| {
-            ui.label("Calories: ");
-            ui.add(egui::DragValue::new(&mut self.new_ingredient_calories).clamp_range(0..=9999));
-        });
-        ui.horizontal(|ui| {
-            egui::Grid::new("category_icon_grid")
-                .spacing(vec2(-4.0, 0.0))
-                .show(ui, |ui| {
-                    for idx in 0..self.categories_list.len() {
-                        let category = &self.categories_list[idx];
-                        let category_selected =
-                            self.new_ingredient_selected_categories.contains(&idx);
+            fn optional_value(ui: &mut Ui, label: &str, value: &mut Option<f32>, default: f32) {
+                let mut enabled = value.is_some();
+                if ui.checkbox(&mut enabled, label).changed() {
+                    *value = enabled.then_some(default);
+                }
+                if let Some(value) = value {
+                    ui.add(
+                        egui::DragValue::new(value)
+                            .speed(0.01)
+                            .clamp_range(0.001..=9999.0),
+                    );
+                }
+            }
 
-                        if ui
-                            .add(toggle_image::toggle_image(
-                                category_selected,
-                                true,
+            optional_value(
+                ui,
+                "Density (g/ml)",
+                &mut self.new_ingredient_conversions.density,
+                1.0,
+            );
+            optional_value(
+                ui,
+                "Piece weight (g)",
+                &mut self.new_ingredient_conversions.piece_weight,
+                100.0,
+            );
+        })
+        .response
+        .on_hover_text("Needed to log this ingredient by volume or by the piece.");
+        ui.horizontal(|ui| {
+            ui.label("Calories: ");
+            ui.add(
+                egui::DragValue::new(&mut self.new_ingredient_calories).clamp_range(0.0..=f32::MAX),
+            );
+            if let Some(nutritional_info) = &self.new_ingredient_nutritional_info {
+                let estimated_calories = nutritional_info.estimate_calories();
+                ui.label(
+                    egui::RichText::new(format!("calculated: {:.1} kcal", estimated_calories))
+                        .weak(),
+                )
+                .on_hover_text("Estimated from the macronutrients entered below.");
+                if ui
+                    .add_enabled(estimated_calories > 0.0, egui::Button::new("Apply"))
+                    .clicked()
+                {
+                    self.new_ingredient_calories = estimated_calories;
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            egui::Grid::new("category_icon_grid")
+                .spacing(vec2(-4.0, 0.0))
+                .show(ui, |ui| {
+                    for category in &self.categories_list {
+                        let category_selected = self
+                            .new_ingredient_selected_categories
+                            .contains(&category.id);
+
+                        if ui
+                            .add(toggle_image::toggle_image(
+                                category_selected,
+                                true,
                                 &get_icon_image_source(&category.icon_name),
                                 category.icon_color,
                                 vec2(16.0, 16.0),
@@ -506,33 +1626,46 @@ impl MyContext {
                                 let index = self
                                     .new_ingredient_selected_categories
                                     .iter()
-                                    .position(|x| *x == idx)
+                                    .position(|x| *x == category.id)
                                     .unwrap();
                                 self.new_ingredient_selected_categories.remove(index);
                             } else {
-                                self.new_ingredient_selected_categories.push(idx);
+                                self.new_ingredient_selected_categories.push(category.id);
                             }
                         }
                     }
                 });
-            if self.categories_list.len() == 0 {
+            if self.categories_list.is_empty() {
                 ui.colored_label(
                     Color32::from_rgb(192, 192, 16),
                     egui::RichText::new("No categories available."),
                 );
-            } else if self.new_ingredient_selected_categories.len() == 0 {
+            } else if self.new_ingredient_selected_categories.is_empty() {
                 ui.colored_label(
                     Color32::from_rgb(192, 192, 16),
                     egui::RichText::new("Consider adding a category."),
                 );
             }
         });
+        if let Some(nutritional_info) = self.new_ingredient_nutritional_info.as_mut() {
+            egui::ScrollArea::vertical()
+                .id_source("new_ingredient_nutrients")
+                .max_height(320.0)
+                .show(ui, |ui| {
+                    nutritional_info_input(ui, nutritional_info);
+                });
+        }
         ui.horizontal(|ui| {
-            if ui.button("Create").clicked() {
+            let create_label = if self.editing_ingredient.is_some() {
+                "Save"
+            } else {
+                "Create"
+            };
+            if ui.button(create_label).clicked() {
                 create!();
             };
             if ui.button("Clear").clicked() {
-                clear!();
+                self.clear_new_ingredient();
             };
             if ui.button("Cancel").clicked() {
                 cancel!();
@@ -540,6 +1673,502 @@ impl MyContext {
         });
     }
 
+    // Writes the fields of the ingredient dialog back into the serving being edited.
+    fn store_new_ingredient_serving(&mut self) {
+        let Some(mut nutritional_info) = self.new_ingredient_nutritional_info.clone() else {
+            return;
+        };
+        nutritional_info.name = self.new_ingredient_serving_name.trim().to_owned();
+        nutritional_info.default_amount = self.new_ingredient_amount;
+        nutritional_info.default_unit = self.new_ingredient_unit;
+        nutritional_info.kilocalories = self.new_ingredient_calories;
+        match self
+            .new_ingredient_servings
+            .get_mut(self.new_ingredient_serving)
+        {
+            Some(serving) => *serving = nutritional_info,
+            None => {
+                self.new_ingredient_serving = self.new_ingredient_servings.len();
+                self.new_ingredient_servings.push(nutritional_info);
+            }
+        }
+    }
+
+    fn load_new_ingredient_serving(&mut self, index: usize) {
+        let nutritional_info = self.new_ingredient_servings[index].clone();
+        self.new_ingredient_serving = index;
+        self.new_ingredient_serving_name = nutritional_info.name.clone();
+        self.new_ingredient_amount = nutritional_info.default_amount;
+        self.new_ingredient_unit = nutritional_info.default_unit;
+        self.new_ingredient_calories = nutritional_info.kilocalories;
+        self.new_ingredient_nutritional_info = Some(nutritional_info);
+    }
+
+    // The validated barcode of the ingredient dialog, which no other ingredient may have.
+    fn new_ingredient_barcode(&mut self) -> Result<Option<String>, String> {
+        let code = self.new_ingredient_barcode.trim();
+        if code.is_empty() {
+            return Ok(None);
+        }
+        let code = barcode::normalize(code)
+            .map_err(|error| format!("Invalid barcode \"{}\": {}", code, error))?;
+        let owner = self
+            .database
+            .get_ingredient_by_barcode(&code)
+            .map_err(|error| error.to_string())?;
+        match owner {
+            Some(owner)
+                if self
+                    .editing_ingredient
+                    .as_ref()
+                    .is_none_or(|ingredient| ingredient.id != owner.id) =>
+            {
+                Err(format!(
+                    "Barcode {} already belongs to {}",
+                    code, owner.name
+                ))
+            }
+            _ => Ok(Some(code)),
+        }
+    }
+
+    // Jumps to the ingredient with the scanned `code` and starts a log entry for it,
+    // unknown codes are offered to be created.
+    fn scan_barcode(&mut self, code: &str) {
+        let Some(ingredient) = self
+            .notifications
+            .report(self.database.get_ingredient_by_barcode(code))
+        else {
+            return;
+        };
+        self.focus_tab = Some("Daily Log");
+        let Some(ingredient) = ingredient else {
+            self.barcode_scan_unknown = Some(code.to_owned());
+            return;
+        };
+
+        self.barcode_scan_unknown = None;
+        self.selected_ingredient = self
+            .ingredients_list
+            .iter()
+            .position(|n| n.id == ingredient.id);
+        self.selected_ingredient_nutrition_info = self.selected_ingredient.map(|_| 0);
+        self.selected_log_entries.clear();
+        if self.editing_log_entry.is_none() {
+            if !self.show_new_log_entry_dialog {
+                self.new_log_entry_date = self.date.unwrap_or(self.new_log_entry_date);
+                self.new_log_entry_amount = 1.0;
+                self.new_log_entry_unit = Unit::Servings;
+                self.new_log_entry_serving = None;
+            }
+            self.show_new_log_entry_dialog = true;
+        }
+        if self
+            .new_log_entry_selected_item
+            .as_ref()
+            .is_some_and(|item| match item {
+                LogItem::Ingredient(selected) => selected.id != ingredient.id,
+                LogItem::Recipe(_) => true,
+            })
+        {
+            self.new_log_entry_amount = 1.0;
+            self.new_log_entry_unit = Unit::Servings;
+            self.new_log_entry_serving = None;
+        }
+        self.new_log_entry_selected_item = Some(LogItem::Ingredient(ingredient));
+    }
+
+    fn edit_ingredient(&mut self, idx: usize) {
+        let ingredient = self.ingredients_list[idx].clone();
+
+        self.new_ingredient_name = ingredient.name.clone();
+        self.new_ingredient_name_was_empty = false;
+        self.new_ingredient_brand = ingredient.brand.clone();
+        self.new_ingredient_barcode = ingredient.barcode.clone().unwrap_or_default();
+        self.new_ingredient_selected_categories = ingredient
+            .categories
+            .iter()
+            .map(|category| category.id)
+            .collect();
+
+        self.new_ingredient_servings = ingredient.nutritional_info.clone();
+        self.new_ingredient_serving_usage = self
+            .notifications
+            .report(self.database.get_serving_usage(&ingredient))
+            .unwrap_or_default();
+        self.load_new_ingredient_serving(0);
+        self.new_ingredient_conversions = ingredient.conversions;
+
+        self.editing_ingredient = Some(ingredient);
+        self.show_new_ingredient_dialog = true;
+    }
+
+    fn read_ingredient_csv(&mut self) {
+        let path = PathBuf::from(self.ingredient_csv_path.trim());
+        let table = std::fs::File::open(&path)
+            .map_err(csv::Error::from)
+            .and_then(ingredient_csv::read_table)
+            .map_err(|error| format!("Unable to read {}: {}", path.display(), error));
+        if let Some(table) = self.notifications.report(table) {
+            self.import_csv_mapping = ingredient_csv::auto_mapping(&table.headers);
+            self.import_csv_table = Some(table);
+            self.import_csv_preview = None;
+        }
+    }
+
+    fn write_ingredient_csv(&mut self) {
+        let Some(mut ingredients) = self.notifications.report(self.database.get_ingredients())
+        else {
+            return;
+        };
+        let Some(archived) = self
+            .notifications
+            .report(self.database.get_archived_ingredients())
+        else {
+            return;
+        };
+        ingredients.extend(archived);
+
+        let path = PathBuf::from(self.ingredient_csv_path.trim());
+        let result = std::fs::File::create(&path)
+            .map_err(csv::Error::from)
+            .and_then(|file| ingredient_csv::export(&ingredients, file))
+            .map_err(|error| format!("Unable to write {}: {}", path.display(), error));
+        self.notifications.report(result);
+    }
+
+    // Column mapping and preview of a CSV file before its ingredients are imported.
+    fn ingredient_import(&mut self, ui: &mut Ui) {
+        let Some(table) = &self.import_csv_table else {
+            return;
+        };
+        let preview = self.import_csv_preview.get_or_insert_with(|| {
+            let existing: Vec<Rc<Ingredient>> = self
+                .ingredients_list
+                .iter()
+                .chain(&self.archived_ingredients_list)
+                .cloned()
+                .collect();
+            ingredient_csv::preview(
+                table,
+                &self.import_csv_mapping,
+                &self.categories_list,
+                &existing,
+            )
+        });
+
+        ui.heading("Import ingredients");
+        let mut mapping_changed = false;
+        egui::CollapsingHeader::new("Columns")
+            .id_source("import_csv_columns")
+            .show(ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .id_source("import_csv_columns_scroll")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("import_csv_mapping")
+                            .num_columns(2)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (column, source) in ingredient_csv::columns()
+                                    .into_iter()
+                                    .zip(self.import_csv_mapping.iter_mut())
+                                {
+                                    ui.label(column);
+                                    egui::ComboBox::from_id_source(("import_csv_column", column))
+                                        .selected_text(
+                                            source
+                                                .and_then(|index| table.headers.get(index))
+                                                .map_or("(none)", String::as_str),
+                                        )
+                                        .show_ui(ui, |ui| {
+                                            mapping_changed |= ui
+                                                .selectable_value(source, None, "(none)")
+                                                .changed();
+                                            for (index, header) in table.headers.iter().enumerate()
+                                            {
+                                                mapping_changed |= ui
+                                                    .selectable_value(source, Some(index), header)
+                                                    .changed();
+                                            }
+                                        });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+
+        let new_count = preview
+            .ingredients
+            .iter()
+            .filter(|imported| !imported.duplicate)
+            .count();
+        ui.label(format!(
+            "{} rows, {} new ingredients, {} duplicates, {} errors",
+            table.rows.len(),
+            new_count,
+            preview.ingredients.len() - new_count,
+            preview.errors.len()
+        ));
+        egui::ScrollArea::vertical()
+            .id_source("import_csv_preview_scroll")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for error in &preview.errors {
+                    ui.colored_label(
+                        Color32::from_rgb(192, 32, 16),
+                        format!("Line {}: {}", error.line, error.message),
+                    );
+                }
+                egui::Grid::new("import_csv_preview")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for imported in &preview.ingredients {
+                            let ingredient = &imported.ingredient;
+                            ui.label(&ingredient.name);
+                            ui.label(egui::RichText::new(&ingredient.brand).italics());
+                            ui.label(format!(
+                                "{} kcal / {} {}",
+                                ingredient.nutritional_info[0].kilocalories,
+                                ingredient.nutritional_info[0].default_amount,
+                                ingredient.nutritional_info[0].default_unit
+                            ));
+                            if imported.duplicate {
+                                ui.weak("already exists, skipped");
+                            } else {
+                                ui.label("");
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        let mut close = false;
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    new_count > 0,
+                    egui::Button::new(format!("Import {} ingredients", new_count)),
+                )
+                .clicked()
+            {
+                let ingredients: Vec<Ingredient> = preview
+                    .ingredients
+                    .iter()
+                    .filter(|imported| !imported.duplicate)
+                    .map(|imported| imported.ingredient.clone())
+                    .collect();
+                if self
+                    .notifications
+                    .report(self.database.insert_ingredients(&ingredients))
+                    .is_some()
+                {
+                    self.update_ingredients = true;
+                    close = true;
+                }
+            }
+            if ui.button("Cancel").clicked() {
+                close = true;
+            }
+        });
+
+        if close {
+            self.import_csv_table = None;
+            self.import_csv_preview = None;
+        } else if mapping_changed {
+            self.import_csv_preview = None;
+        }
+    }
+
+    fn load_open_food_facts(&mut self) {
+        let path = PathBuf::from(self.open_food_facts_path.trim());
+        self.open_product_dump("Open Food Facts", path, read_open_food_facts);
+    }
+
+    fn load_food_data_central(&mut self) {
+        let path = PathBuf::from(self.food_data_central_path.trim());
+        self.open_product_dump("USDA FoodData Central", path, read_food_data_central);
+    }
+
+    fn open_product_dump(&mut self, source: &'static str, path: PathBuf, reader: ProductReader) {
+        self.product_import_source = source;
+        self.product_import_path = Some(path);
+        self.product_import_reader = reader;
+        self.search_products();
+    }
+
+    // Reads the products matching the search on a background thread, the dump
+    // is far too large to keep in memory.
+    fn search_products(&mut self) {
+        let Some(path) = self.product_import_path.clone() else {
+            return;
+        };
+        self.stop_product_search();
+        self.product_import_products.clear();
+        self.product_import_selected.clear();
+        self.product_import_existing.clear();
+        self.product_import_truncated = false;
+
+        let query = ProductQuery::new(&self.product_import_search, PRODUCT_IMPORT_RESULT_LIMIT);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let reader = self.product_import_reader;
+        let thread_query = query.clone();
+        std::thread::spawn(move || {
+            // The receiver is gone when the search was replaced or closed.
+            let _ = sender.send(reader(&path, &thread_query));
+        });
+        self.product_import_loading = Some((query, receiver));
+    }
+
+    fn stop_product_search(&mut self) {
+        if let Some((query, _)) = self.product_import_loading.take() {
+            query.cancel();
+        }
+    }
+
+    // Takes over the products once the background search is done.
+    fn receive_products(&mut self, ctx: &egui::Context) {
+        let Some((_, receiver)) = &self.product_import_loading else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(dump) => {
+                self.product_import_loading = None;
+                match dump {
+                    Ok(dump) => {
+                        self.product_import_products = dump.products;
+                        self.product_import_truncated = dump.truncated;
+                    }
+                    Err(error) => {
+                        self.notifications.error(error);
+                        self.product_import_path = None;
+                    }
+                }
+            }
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(Duration::from_millis(100)),
+            Err(TryRecvError::Disconnected) => {
+                self.product_import_loading = None;
+                self.notifications
+                    .error("Reading the products stopped unexpectedly");
+            }
+        }
+    }
+
+    // Search over an Open Food Facts or FoodData Central dump, picked products
+    // become ingredients.
+    fn product_import(&mut self, ui: &mut Ui) {
+        self.receive_products(ui.ctx());
+
+        ui.heading(format!("Import from {}", self.product_import_source));
+        ui.horizontal(|ui| {
+            let search = ui.add(
+                egui::TextEdit::singleline(&mut self.product_import_search)
+                    .hint_text("Search by name, brand or barcode"),
+            );
+            let submitted = search.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter));
+            if ui.button("Search").clicked() || submitted {
+                self.search_products();
+            }
+            if self.product_import_loading.is_some() {
+                ui.spinner();
+                ui.label("Searching…");
+            } else if self.product_import_truncated {
+                ui.label(format!(
+                    "First {} products, refine the search to see others",
+                    self.product_import_products.len()
+                ));
+            } else {
+                ui.label(format!("{} products", self.product_import_products.len()));
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .id_source("product_import_results")
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("product_import_grid")
+                    .num_columns(5)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (index, product) in self.product_import_products.iter().enumerate() {
+                            let mut selected = self.product_import_selected.contains(&index);
+                            if ui
+                                .checkbox(&mut selected, &product.ingredient.name)
+                                .changed()
+                            {
+                                if selected {
+                                    self.product_import_selected.insert(index);
+                                } else {
+                                    self.product_import_selected.remove(&index);
+                                }
+                            }
+                            ui.label(egui::RichText::new(&product.ingredient.brand).italics());
+                            ui.label(format!(
+                                "{:.0} kcal / 100 g",
+                                product.ingredient.nutritional_info[0].kilocalories
+                            ));
+                            ui.weak(&product.code);
+                            if self.product_import_existing.contains(&index) {
+                                ui.weak("already exists, skipped");
+                            } else {
+                                ui.label("");
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        let mut close = false;
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !self.product_import_selected.is_empty(),
+                    egui::Button::new(format!(
+                        "Import {} selected",
+                        self.product_import_selected.len()
+                    )),
+                )
+                .clicked()
+            {
+                let mut selected: Vec<usize> =
+                    self.product_import_selected.iter().copied().collect();
+                selected.sort_unstable();
+                for index in selected {
+                    let ingredient = &self.product_import_products[index].ingredient;
+                    let Some(existing) = self
+                        .notifications
+                        .report(self.database.find_duplicate_ingredient(ingredient))
+                    else {
+                        continue;
+                    };
+                    if existing.is_some() {
+                        self.product_import_existing.insert(index);
+                        self.product_import_selected.remove(&index);
+                    } else if self
+                        .notifications
+                        .report(self.database.insert_ingredient(ingredient))
+                        .is_some()
+                    {
+                        self.product_import_selected.remove(&index);
+                    }
+                }
+                self.update_ingredients = true;
+            }
+            if ui.button("Close").clicked() {
+                close = true;
+            }
+        });
+
+        if close {
+            self.stop_product_search();
+            self.product_import_path = None;
+            self.product_import_products.clear();
+            self.product_import_selected.clear();
+            self.product_import_existing.clear();
+        }
+    }
+
     fn ingredients_view(&mut self, ui: &mut Ui) {
         ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
             ui.add_enabled_ui(!self.show_new_ingredient_dialog, |ui| {
@@ -561,79 +2190,10 @@ impl MyContext {
                     .clicked()
                 {
                     self.show_new_ingredient_dialog = true;
+                    self.editing_ingredient = None;
                     self.new_ingredient_nutritional_info = Some(NutritionalInfo {
                         default_amount: 1.0,
-                        default_unit: Unit::Grams,
-                        kilocalories: 0.0,
-                        macronutrients: Macronutrients {
-                            proteins: Proteins {
-                                essential_amino_acids: EssentialAminoAcids {
-                                    histidine: 0.0,
-                                    isoleucine: 0.0,
-                                    leucine: 0.0,
-                                    lysine: 0.0,
-                                    methionine: 0.0,
-                                    phenylalanine: 0.0,
-                                    threonine: 0.0,
-                                    tryptophan: 0.0,
-                                    valine: 0.0,
-                                },
-                                non_essential_amino_acids: NonEssentialAminoAcids {
-                                    alanine: 0.0,
-                                    arginine: 0.0,
-                                    asparagine: 0.0,
-                                    aspartic_acid: 0.0,
-                                    cysteine: 0.0,
-                                    glutamic_acid: 0.0,
-                                    glutamine: 0.0,
-                                    glycine: 0.0,
-                                    proline: 0.0,
-                                    serine: 0.0,
-                                    tyrosine: 0.0,
-                                },
-                            },
-                            fats: Fats {
-                                saturated: 0.0,
-                                monounsaturated: 0.0,
-                                polyunsaturated: 0.0,
-                            },
-                            carbohydrates: Carbohydrates {
-                                starch: 0.0,
-                                fiber: 0.0,
-                                sugars: 0.0,
-                                sugar_alcohols: 0.0,
-                            },
-                        },
-                        micronutrients: Micronutrients {
-                            vitamins: Vitamins {
-                                vitamin_a: 0.0,
-                                vitamin_b1: 0.0,
-                                vitamin_b2: 0.0,
-                                vitamin_b3: 0.0,
-                                vitamin_b5: 0.0,
-                                vitamin_b6: 0.0,
-                                vitamin_b9: 0.0,
-                                vitamin_b12: 0.0,
-                                vitamin_c: 0.0,
-                                vitamin_d: 0.0,
-                                vitamin_e: 0.0,
-                                vitamin_k: 0.0,
-                                betaine: 0.0,
-                                choline: 0.0,
-                            },
-                            minerals: Minerals {
-                                calcium: 0.0,
-                                copper: 0.0,
-                                iron: 0.0,
-                                magnesium: 0.0,
-                                manganese: 0.0,
-                                phosphorus: 0.0,
-                                potassium: 0.0,
-                                selenium: 0.0,
-                                sodium: 0.0,
-                                zinc: 0.0,
-                            },
-                        },
+                        ..Default::default()
                     });
                 }
             });
@@ -650,7 +2210,43 @@ impl MyContext {
         if self.show_new_ingredient_dialog {
             self.new_ingredient(ui);
         }
+        if self.import_csv_table.is_some() {
+            ui.separator();
+            self.ingredient_import(ui);
+        }
+        if self.product_import_path.is_some() {
+            ui.separator();
+            self.product_import(ui);
+        }
         ui.separator();
+        if !self.archived_ingredients_list.is_empty() {
+            let mut restored: Option<Rc<Ingredient>> = None;
+            egui::CollapsingHeader::new(format!(
+                "Archived ({})",
+                self.archived_ingredients_list.len()
+            ))
+            .id_source("archived_ingredients")
+            .show(ui, |ui| {
+                for ingredient in &self.archived_ingredients_list {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Restore").clicked() {
+                            restored = Some(ingredient.clone());
+                        }
+                        ui.label(&ingredient.name);
+                        ui.label(egui::RichText::new(&ingredient.brand).italics());
+                    });
+                }
+            });
+            if let Some(ingredient) = restored {
+                let restore_result = self.database.set_ingredient_archived(&ingredient, false);
+                if self.notifications.report(restore_result).is_some() {
+                    self.update_ingredients = true;
+                    self.selected_ingredient = None;
+                    self.selected_ingredient_nutrition_info = None;
+                }
+            }
+            ui.separator();
+        }
         TableBuilder::new(ui)
             .sense(egui::Sense::click())
             .striped(true)
@@ -721,10 +2317,9 @@ impl MyContext {
                     });
                     row.col(|ui| {
                         ui.label(
-                            &self.ingredients_list[row_index].nutritional_info
-                                [self.selected_ingredient_nutrition_info.unwrap_or(0)]
-                            .kilocalories
-                            .to_string(),
+                            self.ingredients_list[row_index].nutritional_info[0]
+                                .kilocalories
+                                .to_string(),
                         );
                     });
 
@@ -737,6 +2332,7 @@ impl MyContext {
                             _ => {
                                 self.selected_ingredient = Some(row_index);
                                 self.selected_ingredient_nutrition_info = Some(0);
+                                self.selected_log_entries.clear();
                             }
                         }
                     }
@@ -763,10 +2359,14 @@ impl MyContext {
                         icon_color: self.new_category_icon_color.clone(),
                     };
 
-                    let _ = self.database.insert_category(&new_category);
-
-                    self.update_categories = true;
-                    cancel_category!();
+                    if self
+                        .notifications
+                        .report(self.database.insert_category(&new_category))
+                        .is_some()
+                    {
+                        self.update_categories = true;
+                        cancel_category!();
+                    }
                 }
             };
         }
@@ -791,7 +2391,7 @@ impl MyContext {
         ui.horizontal(|ui| {
             ui.label("Name: ");
             let name_result = ui.text_edit_singleline(&mut self.new_category_name);
-            if name_result.changed() && self.new_category_name.len() > 0 {
+            if name_result.changed() && !self.new_category_name.is_empty() {
                 self.new_category_name_was_empty = false;
             }
             if self.new_category_name_was_empty {
@@ -803,7 +2403,7 @@ impl MyContext {
             egui::Grid::new("category_icon_grid")
                 .spacing(vec2(-4.0, 0.0))
                 .show(ui, |ui| {
-                    for idx in 0..CATEGORY_ICON_NAMES.len() {
+                    for (idx, icon_name) in CATEGORY_ICON_NAMES.iter().enumerate() {
                         let active: bool = {
                             if let Some(index) = self.new_category_selected_icon {
                                 idx == index
@@ -815,18 +2415,18 @@ impl MyContext {
                             .add(toggle_image::toggle_image(
                                 active,
                                 false,
-                                &get_icon_image_source(CATEGORY_ICON_NAMES[idx]),
+                                &get_icon_image_source(icon_name),
                                 self.new_category_icon_color,
                                 vec2(16.0, 16.0),
                             ))
                             .changed()
                         {
                             self.new_category_selected_icon = Some(idx);
-                            self.new_category_icon_name = CATEGORY_ICON_NAMES[idx].to_owned();
+                            self.new_category_icon_name = icon_name.to_string();
                         }
                     }
                 });
-            if let Some(_) = self.new_category_selected_icon {
+            if self.new_category_selected_icon.is_some() {
                 self.new_category_selected_icon_was_invalid = false;
             }
             if self.new_category_selected_icon_was_invalid {
@@ -935,6 +2535,7 @@ impl MyContext {
                             }
                             _ => {
                                 self.selected_category = Some(row_index);
+                                self.selected_log_entries.clear();
                             }
                         }
                     }
@@ -943,19 +2544,29 @@ impl MyContext {
     }
 
     fn details_view(&mut self, ui: &mut Ui) {
-        fn nutritional_info_view(ui: &mut Ui, ingredient: &Ingredient, index: usize) {
+        fn nutritional_info_view(
+            ui: &mut Ui,
+            ingredient: &Ingredient,
+            index: usize,
+            by_energy: &mut bool,
+        ) {
             egui::ScrollArea::vertical()
                 .auto_shrink(false)
                 .show(ui, |ui| {
                     ui.label(format!(
-                        "per {}{}:",
-                        ingredient.nutritional_info[index].default_amount,
-                        ingredient.nutritional_info[index].default_unit
+                        "per {}:",
+                        ingredient.nutritional_info[index].serving_name()
                     ));
                     ui.label(format!(
                         "Calories: {}",
                         ingredient.nutritional_info[index].kilocalories
                     ));
+                    amino_acid_score_label(
+                        ui,
+                        &ScoredProteins::new(
+                            &ingredient.nutritional_info[index].macronutrients.proteins,
+                        ),
+                    );
                     ui.collapsing("Macronutrients", |ui| {
                         ui.allocate_ui_with_layout(
                             ui.available_size(),
@@ -976,26 +2587,15 @@ impl MyContext {
                                         .carbohydrates
                                         .net_carbs()
                                 ));
-                                ui.add(pie_chart::pie_chart(
-                                    vec2(4.0, 4.0),
-                                    vec![
-                                        PieChartSlice {
-                                            fraction: 1.0 / 3.0,
-                                            color: Color32::LIGHT_GREEN,
-                                            tooltip: "Protein".to_owned(),
-                                        },
-                                        PieChartSlice {
-                                            fraction: 1.0 / 3.0,
-                                            color: Color32::LIGHT_BLUE,
-                                            tooltip: "Fat".to_owned(),
-                                        },
-                                        PieChartSlice {
-                                            fraction: 1.0 / 3.0,
-                                            color: Color32::LIGHT_RED,
-                                            tooltip: "Carbohydrates".to_owned(),
-                                        },
-                                    ],
-                                ));
+                                let (slices, ring_slices) = macronutrient_slices(
+                                    &ingredient.nutritional_info[index].macronutrients,
+                                    *by_energy,
+                                );
+                                ui.add(pie_chart::pie_chart(vec2(4.0, 4.0), slices, ring_slices));
+                                ui.vertical(|ui| {
+                                    ui.selectable_value(by_energy, false, "By weight");
+                                    ui.selectable_value(by_energy, true, "By energy");
+                                });
                             },
                         );
                     });
@@ -1178,6 +2778,8 @@ impl MyContext {
 
         if let Some(idx) = self.selected_ingredient {
             let ingredient = &self.ingredients_list[idx];
+            let mut edit_requested = false;
+            let mut removed = false;
             ui.horizontal(|ui| {
                 for category in &ingredient.categories {
                     let icon_name = &category.icon_name;
@@ -1216,18 +2818,20 @@ impl MyContext {
                         )
                         .clicked()
                     {
-                        let delete_result = self.database.delete_ingredient(&ingredient);
-                        if let Ok(_) = delete_result {
-                            self.update_ingredients = true;
-                            if self.ingredients_list.len() == 1 {
-                                self.selected_ingredient = None;
-                                self.selected_ingredient_nutrition_info = None;
-                            } else if let Some(selected) = self.selected_ingredient {
-                                if selected == self.ingredients_list.len() - 1 {
-                                    self.selected_ingredient = Some(selected - 1);
-                                    self.selected_ingredient_nutrition_info = Some(0);
-                                }
-                            }
+                        let usage_result = self.database.get_ingredient_usage(ingredient);
+                        if let Some(usage) = self.notifications.report(usage_result) {
+                            self.ingredient_delete_usage = Some((ingredient.id, usage));
+                        }
+                    }
+                    if ui
+                        .add(egui::Button::new("Archive").min_size(vec2(0.0, 24.0)))
+                        .on_hover_text("Hide the ingredient but keep the log entries using it.")
+                        .clicked()
+                    {
+                        let archive_result =
+                            self.database.set_ingredient_archived(ingredient, true);
+                        if self.notifications.report(archive_result).is_some() {
+                            removed = true;
                         }
                     }
                     if ui
@@ -1247,15 +2851,86 @@ impl MyContext {
                         )
                         .clicked()
                     {
-                        println!("Edit ingredient button pressed!");
+                        edit_requested = true;
                     }
                 });
             });
+            if let Some((_, usage)) = self
+                .ingredient_delete_usage
+                .filter(|(id, _)| *id == ingredient.id)
+            {
+                ui.horizontal(|ui| {
+                    ui.colored_label(
+                        Color32::from_rgb(192, 32, 16),
+                        format!(
+                            "This will affect {} log {} and {} {}.",
+                            usage.log_entries,
+                            if usage.log_entries == 1 {
+                                "entry"
+                            } else {
+                                "entries"
+                            },
+                            usage.recipes,
+                            if usage.recipes == 1 {
+                                "recipe"
+                            } else {
+                                "recipes"
+                            }
+                        ),
+                    );
+                    if ui.button("Delete anyway").clicked() {
+                        let delete_result = self.database.delete_ingredient(ingredient);
+                        if self.notifications.report(delete_result).is_some() {
+                            removed = true;
+                        }
+                        self.ingredient_delete_usage = None;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.ingredient_delete_usage = None;
+                    }
+                });
+            }
+            // Servings may have been removed since the selection was made.
+            let serving = self
+                .selected_ingredient_nutrition_info
+                .unwrap_or(0)
+                .min(ingredient.nutritional_info.len() - 1);
+            if ingredient.nutritional_info.len() > 1 {
+                ui.horizontal(|ui| {
+                    ui.label("Serving: ");
+                    ComboBox::from_id_source("details_serving")
+                        .selected_text(ingredient.nutritional_info[serving].serving_name())
+                        .show_ui(ui, |ui| {
+                            for (index, nutritional_info) in
+                                ingredient.nutritional_info.iter().enumerate()
+                            {
+                                ui.selectable_value(
+                                    &mut self.selected_ingredient_nutrition_info,
+                                    Some(index),
+                                    nutritional_info.serving_name(),
+                                );
+                            }
+                        });
+                });
+            }
             nutritional_info_view(
                 ui,
                 ingredient,
-                self.selected_ingredient_nutrition_info.unwrap_or(0),
+                serving,
+                &mut self.details_macronutrients_by_energy,
             );
+            if removed {
+                self.update_ingredients = true;
+                if self.ingredients_list.len() == 1 {
+                    self.selected_ingredient = None;
+                    self.selected_ingredient_nutrition_info = None;
+                } else if idx == self.ingredients_list.len() - 1 {
+                    self.selected_ingredient = Some(idx - 1);
+                    self.selected_ingredient_nutrition_info = Some(0);
+                }
+            } else if edit_requested {
+                self.edit_ingredient(idx);
+            }
         } else if let Some(idx) = self.selected_category {
             let category = &self.categories_list[idx];
 
@@ -1292,8 +2967,8 @@ impl MyContext {
                         )
                         .clicked()
                     {
-                        let delete_result = self.database.delete_category(&category);
-                        if let Ok(result) = delete_result {
+                        let delete_result = self.database.delete_category(category);
+                        if let Some(result) = self.notifications.report(delete_result) {
                             self.update_categories = true;
                             if result > 0 {
                                 self.update_ingredients = true;
@@ -1309,6 +2984,8 @@ impl MyContext {
                     }
                 });
             });
+        } else if !self.selected_log_entries.is_empty() {
+            self.log_entry_details(ui);
         } else {
             ui.centered_and_justified(|ui| {
                 ui.label("-nothing selected-");
@@ -1316,124 +2993,1333 @@ impl MyContext {
         }
     }
 
-    fn new_log_entry(&mut self, ui: &mut Ui) {
-        macro_rules! create_log_entry {
-            () => {
-                if let Some(ingredient) = &self.new_log_entry_selected_ingredient {
-                    let log_entry = LogEntry {
-                        id: 0,
-                        ingredient: ingredient.clone(),
-                        fraction: self.new_log_entry_fraction,
-                    };
-
-                    let _ = self
-                        .database
-                        .insert_log_entry(&self.date.unwrap(), &log_entry);
-
-                    self.update_log_entries = true;
-                    cancel_log_entry!();
-                }
-            };
+    fn log_entry_details(&mut self, ui: &mut Ui) {
+        let log_entries: Vec<&LogEntry> = self
+            .selected_log_entries
+            .iter()
+            .map(|&idx| &self.log_entry_list[idx])
+            .collect();
+        let mut edit_requested = false;
+        let mut delete_requested = false;
+        ui.horizontal(|ui| {
+            if let [log_entry] = log_entries.as_slice() {
+                ui.label(
+                    egui::RichText::new(log_entry.item.name())
+                        .heading()
+                        .underline(),
+                );
+                ui.label(egui::RichText::new(log_entry_amount_text(log_entry)).italics());
+            } else {
+                ui.heading(format!("{} log entries", log_entries.len()));
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                if ui
+                    .add(
+                        egui::Button::image_and_text(
+                            egui::Image::new(get_icon_image_source("delete"))
+                                .tint(Color32::RED)
+                                .fit_to_exact_size(vec2(16.0, 16.0))
+                                .texture_options(TextureOptions {
+                                    magnification: TextureFilter::Nearest,
+                                    minification: TextureFilter::Nearest,
+                                    wrap_mode: TextureWrapMode::ClampToEdge,
+                                }),
+                            "Delete",
+                        )
+                        .min_size(vec2(0.0, 24.0)),
+                    )
+                    .clicked()
+                {
+                    delete_requested = true;
+                }
+                if log_entries.len() == 1
+                    && ui
+                        .add(
+                            egui::Button::image_and_text(
+                                egui::Image::new(get_icon_image_source("edit"))
+                                    .tint(Color32::GRAY)
+                                    .fit_to_exact_size(vec2(16.0, 16.0))
+                                    .texture_options(TextureOptions {
+                                        magnification: TextureFilter::Nearest,
+                                        minification: TextureFilter::Nearest,
+                                        wrap_mode: TextureWrapMode::ClampToEdge,
+                                    }),
+                                "Edit",
+                            )
+                            .min_size(vec2(0.0, 24.0)),
+                        )
+                        .clicked()
+                {
+                    edit_requested = true;
+                }
+            });
+        });
+        if let [log_entry] = log_entries.as_slice() {
+            if let Some(date) = self.date {
+                ui.label(format!(
+                    "{}{}{}",
+                    date.format("%d.%m.%Y"),
+                    log_entry
+                        .time
+                        .map_or(String::new(), |time| time.format(" %H:%M").to_string()),
+                    log_entry
+                        .meal
+                        .as_ref()
+                        .map_or(String::new(), |meal| format!(", {}", meal.name))
+                ));
+            }
+        }
+
+        let mut totals = NutrientTotals::default();
+        for log_entry in &log_entries {
+            totals.add_log_entry(log_entry);
+        }
+        let macronutrients = &totals.macronutrients;
+        ui.label(format!("Calories: {:.1}", totals.kilocalories));
+        ui.allocate_ui_with_layout(
+            ui.available_size(),
+            egui::Layout::left_to_right(Align::Center),
+            |ui| {
+                ui.label(format!(
+                    "Protein: {:.1}\nFat: {:.1}\nCarbohydrates (net): {:.1}",
+                    macronutrients.proteins.total_proteins(),
+                    macronutrients.fats.total_fats(),
+                    macronutrients.carbohydrates.net_carbs()
+                ));
+                let (slices, ring_slices) =
+                    macronutrient_slices(macronutrients, self.details_macronutrients_by_energy);
+                ui.add(pie_chart::pie_chart(vec2(4.0, 4.0), slices, ring_slices));
+                ui.vertical(|ui| {
+                    ui.selectable_value(
+                        &mut self.details_macronutrients_by_energy,
+                        false,
+                        "By weight",
+                    );
+                    ui.selectable_value(
+                        &mut self.details_macronutrients_by_energy,
+                        true,
+                        "By energy",
+                    );
+                });
+            },
+        );
+
+        if edit_requested {
+            self.edit_log_entry(self.selected_log_entries[0]);
+        }
+        if delete_requested {
+            self.delete_selected_log_entries();
+        }
+    }
+
+    fn clear_new_log_entry(&mut self) {
+        self.new_log_entry_amount = 1.0;
+        self.new_log_entry_unit = Unit::Servings;
+        self.new_log_entry_serving = None;
+        self.new_log_entry_ingredient_search = String::from("");
+        self.new_log_entry_ingredient_previous_search = String::from("");
+        self.new_log_entry_filtered_items.clear();
+        self.new_log_entry_selected_item = None;
+        self.new_log_entry_new_meal_name = String::from("");
+        self.new_log_entry_time_enabled = false;
+    }
+
+    fn new_log_entry(&mut self, ui: &mut Ui) {
+        macro_rules! create_log_entry {
+            () => {
+                if let Some(item) = &self.new_log_entry_selected_item {
+                    let log_entry = LogEntry {
+                        id: self.editing_log_entry.as_ref().map_or(0, |n| n.id),
+                        item: item.clone(),
+                        amount: self.new_log_entry_amount,
+                        unit: self.new_log_entry_unit,
+                        serving: self
+                            .new_log_entry_serving
+                            .filter(|_| self.new_log_entry_unit == Unit::Servings),
+                        meal: self.new_log_entry_meal.clone(),
+                        time: self
+                            .new_log_entry_time_enabled
+                            .then_some(self.new_log_entry_time),
+                    };
+
+                    let result = if self.editing_log_entry.is_some() {
+                        self.database
+                            .update_log_entry(&self.new_log_entry_date, &log_entry)
+                    } else {
+                        self.database
+                            .insert_log_entry(&self.new_log_entry_date, &log_entry)
+                    };
+
+                    if self.notifications.report(result).is_some() {
+                        self.update_log_entries = true;
+                        cancel_log_entry!();
+                    }
+                }
+            };
+        }
+        macro_rules! cancel_log_entry {
+            () => {
+                self.clear_new_log_entry();
+                self.editing_log_entry = None;
+                self.show_new_log_entry_dialog = false;
+            };
+        }
+
+        ui.heading(if self.editing_log_entry.is_some() {
+            "Edit log entry"
+        } else {
+            "Create new log entry"
+        });
+        ui.horizontal(|ui| {
+            ui.label("Date: ");
+            ui.add(
+                DatePickerButton::new(&mut self.new_log_entry_date)
+                    .id_source("new_log_entry_date")
+                    .format("%d.%m.%Y")
+                    .highlight_weekends(false)
+                    .with_data(&self.log_entry_dates),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Amount: ");
+            ui.add(
+                egui::DragValue::new(&mut self.new_log_entry_amount)
+                    .clamp_range(0..=9999)
+                    .speed(0.1),
+            );
+            // Ingredients offer each of their servings instead of a plain "serving".
+            let mut units: Vec<(Unit, Option<u32>, String)> = Vec::new();
+            let mut selected_text = self.new_log_entry_unit.to_string();
+            match &self.new_log_entry_selected_item {
+                Some(LogItem::Ingredient(ingredient)) => {
+                    for unit in ingredient.available_units() {
+                        if unit != Unit::Servings {
+                            units.push((unit, None, unit.to_string()));
+                        }
+                    }
+                    for (index, nutritional_info) in ingredient.nutritional_info.iter().enumerate()
+                    {
+                        // The first serving also stands for entries without one.
+                        let serving = (index > 0).then_some(nutritional_info.id);
+                        units.push((Unit::Servings, serving, nutritional_info.serving_name()));
+                    }
+                    if self.new_log_entry_unit == Unit::Servings {
+                        if let Some((nutritional_info, _)) = ingredient.scale_serving(
+                            1.0,
+                            Unit::Servings,
+                            self.new_log_entry_serving,
+                        ) {
+                            selected_text = nutritional_info.serving_name();
+                        }
+                    }
+                }
+                _ => units.push((Unit::Servings, None, Unit::Servings.to_string())),
+            }
+            ComboBox::from_id_source("new_log_unit")
+                .width(64.0)
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    for (unit, serving, label) in units {
+                        let selected = self.new_log_entry_unit == unit
+                            && (unit != Unit::Servings || self.new_log_entry_serving == serving);
+                        if ui.selectable_label(selected, label).clicked() {
+                            self.new_log_entry_unit = unit;
+                            self.new_log_entry_serving = serving;
+                        }
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Meal: ");
+            ComboBox::from_id_source("new_log_meal")
+                .width(128.0)
+                .selected_text(
+                    self.new_log_entry_meal
+                        .as_ref()
+                        .map_or("-".to_owned(), |meal| meal.name.clone()),
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.new_log_entry_meal, None, "-");
+                    for meal in &self.meals_list {
+                        ui.selectable_value(
+                            &mut self.new_log_entry_meal,
+                            Some(meal.clone()),
+                            meal.name.clone(),
+                        );
+                    }
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_log_entry_new_meal_name)
+                    .desired_width(96.0)
+                    .hint_text("New meal"),
+            );
+            if ui
+                .add_enabled(
+                    !self.new_log_entry_new_meal_name.trim().is_empty(),
+                    egui::Button::new("Add"),
+                )
+                .clicked()
+            {
+                let name = self.new_log_entry_new_meal_name.trim().to_owned();
+                if let Some(meal) = self.notifications.report(self.database.insert_meal(&name)) {
+                    self.meals_list.push(meal.clone());
+                    self.new_log_entry_meal = Some(meal);
+                    self.new_log_entry_new_meal_name.clear();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.new_log_entry_time_enabled, "Time: ");
+            ui.add_enabled_ui(self.new_log_entry_time_enabled, |ui| {
+                let mut hour = self.new_log_entry_time.hour();
+                let mut minute = self.new_log_entry_time.minute();
+                let hour_changed = ui
+                    .add(egui::DragValue::new(&mut hour).clamp_range(0..=23))
+                    .changed();
+                ui.label(":");
+                let minute_changed = ui
+                    .add(
+                        egui::DragValue::new(&mut minute)
+                            .clamp_range(0..=59)
+                            .custom_formatter(|minute, _| format!("{:02}", minute)),
+                    )
+                    .changed();
+                if hour_changed || minute_changed {
+                    if let Some(time) = NaiveTime::from_hms_opt(hour, minute, 0) {
+                        self.new_log_entry_time = time;
+                    }
+                }
+            });
+        });
+        if self.new_log_entry_ingredient_search.len() > 1
+            && !self
+                .new_log_entry_ingredient_search
+                .eq_ignore_ascii_case(self.new_log_entry_ingredient_previous_search.as_str())
+        {
+            self.new_log_entry_ingredient_previous_search =
+                self.new_log_entry_ingredient_search.clone();
+
+            let search = self.new_log_entry_ingredient_search.to_ascii_lowercase();
+            self.new_log_entry_filtered_items = self
+                .ingredients_list
+                .iter()
+                .filter(|&n| {
+                    n.name.to_ascii_lowercase().contains(&search)
+                        || n.brand.to_ascii_lowercase().contains(&search)
+                        || n.barcode
+                            .as_ref()
+                            .is_some_and(|code| code.contains(&search))
+                })
+                .map(|ingredient| LogItem::Ingredient(ingredient.clone()))
+                .chain(
+                    self.recipes_list
+                        .iter()
+                        .filter(|recipe| recipe.name.to_ascii_lowercase().contains(&search))
+                        .map(|recipe| LogItem::Recipe(recipe.clone())),
+                )
+                .collect();
+        }
+        let mut scanned: Option<String> = None;
+        ui.horizontal(|ui| {
+            ui.label("Food: ");
+            ComboBox::from_id_source("new_log_ingredient")
+                .width(128.0)
+                .selected_text(
+                    self.new_log_entry_selected_item
+                        .as_ref()
+                        .map_or("-".to_owned(), |item| item.name().to_owned()),
+                )
+                .show_ui(ui, |ui| {
+                    let text_edit =
+                        egui::TextEdit::singleline(&mut self.new_log_entry_ingredient_search)
+                            .lock_focus(true)
+                            .hint_text("🔎");
+                    ui.add(text_edit).request_focus();
+                    // Scanners type the code into the search and press Enter.
+                    if ui.input(|input| input.key_pressed(Key::Enter)) {
+                        scanned = barcode::normalize(&self.new_log_entry_ingredient_search).ok();
+                    }
+
+                    for item in &self.new_log_entry_filtered_items {
+                        ui.horizontal(|ui| {
+                            let categories = match item {
+                                LogItem::Ingredient(ingredient) => ingredient.categories.as_slice(),
+                                LogItem::Recipe(_) => &[],
+                            };
+                            for category in categories {
+                                ui.add(
+                                    egui::Image::new(get_icon_image_source(&category.icon_name))
+                                        .tint(category.icon_color)
+                                        .fit_to_exact_size(vec2(16.0, 16.0))
+                                        .texture_options(TextureOptions {
+                                            magnification: TextureFilter::Nearest,
+                                            minification: TextureFilter::Nearest,
+                                            wrap_mode: TextureWrapMode::ClampToEdge,
+                                        }),
+                                );
+                            }
+                            if ui
+                                .selectable_value(
+                                    &mut self.new_log_entry_selected_item,
+                                    Some(item.clone()),
+                                    item.name(),
+                                )
+                                .changed()
+                            {
+                                // Keep the unit if the new item can be measured in it.
+                                let keep_unit = match item {
+                                    LogItem::Ingredient(ingredient) => ingredient
+                                        .available_units()
+                                        .contains(&self.new_log_entry_unit),
+                                    LogItem::Recipe(_) => self.new_log_entry_unit == Unit::Servings,
+                                };
+                                if !keep_unit {
+                                    self.new_log_entry_amount = 1.0;
+                                    self.new_log_entry_unit = Unit::Servings;
+                                }
+                                // Servings belong to the previously selected ingredient.
+                                self.new_log_entry_serving = None;
+                            }
+                            if let LogItem::Recipe(_) = item {
+                                ui.label(egui::RichText::new("recipe").weak());
+                            }
+                        });
+                    }
+                });
+        });
+        if let Some(code) = scanned {
+            self.new_log_entry_ingredient_search.clear();
+            self.new_log_entry_ingredient_previous_search.clear();
+            self.new_log_entry_filtered_items.clear();
+            self.scan_barcode(&code);
+        }
+        ui.horizontal(|ui| {
+            let create_label = if self.editing_log_entry.is_some() {
+                "Save"
+            } else {
+                "Create"
+            };
+            if ui.button(create_label).clicked() {
+                create_log_entry!();
+            };
+            if ui.button("Clear").clicked() {
+                self.clear_new_log_entry();
+            };
+            if ui.button("Cancel").clicked() {
+                cancel_log_entry!();
+            };
+        });
+    }
+
+    fn edit_log_entry(&mut self, idx: usize) {
+        let log_entry = self.log_entry_list[idx].clone();
+
+        self.new_log_entry_date = self.date.unwrap_or(self.new_log_entry_date);
+        self.new_log_entry_amount = log_entry.amount;
+        self.new_log_entry_unit = log_entry.unit;
+        self.new_log_entry_serving = log_entry.serving;
+        self.new_log_entry_ingredient_search.clear();
+        self.new_log_entry_ingredient_previous_search.clear();
+        self.new_log_entry_filtered_items.clear();
+        self.new_log_entry_selected_item = Some(log_entry.item.clone());
+        self.new_log_entry_meal = log_entry.meal.clone();
+        self.new_log_entry_time_enabled = log_entry.time.is_some();
+        if let Some(time) = log_entry.time {
+            self.new_log_entry_time = time;
+        }
+
+        self.editing_log_entry = Some(log_entry);
+        self.show_new_log_entry_dialog = true;
+    }
+
+    fn delete_selected_log_entries(&mut self) {
+        let log_entries: Vec<LogEntry> = self
+            .selected_log_entries
+            .iter()
+            .map(|&idx| self.log_entry_list[idx].clone())
+            .collect();
+        let delete_result = self.database.delete_log_entries(&log_entries);
+        if self.notifications.report(delete_result).is_some() {
+            self.selected_log_entries.clear();
+            self.update_log_entries = true;
+        }
+    }
+
+    fn refresh_recipes(&mut self) {
+        if !self.update_recipes {
+            return;
+        }
+        self.update_recipes = false;
+
+        if let Some(recipes) = self.notifications.report(self.database.get_recipes()) {
+            self.recipes_list = recipes;
+        }
+        // Keep the recipe being edited selected even if its position changed.
+        self.selected_recipe = self.editing_recipe.as_ref().and_then(|editing| {
+            self.recipes_list
+                .iter()
+                .position(|recipe| recipe.id == editing.id)
+        });
+    }
+
+    fn recipes_view(&mut self, ui: &mut Ui) {
+        egui::SidePanel::left("recipes_list")
+            .resizable(true)
+            .default_width(160.0)
+            .show_inside(ui, |ui| {
+                if ui
+                    .add(
+                        egui::Button::image_and_text(
+                            egui::Image::new(get_icon_image_source("add"))
+                                .tint(Color32::GRAY)
+                                .fit_to_exact_size(vec2(16.0, 16.0))
+                                .texture_options(TextureOptions {
+                                    magnification: TextureFilter::Nearest,
+                                    minification: TextureFilter::Nearest,
+                                    wrap_mode: TextureWrapMode::ClampToEdge,
+                                }),
+                            "New recipe",
+                        )
+                        .min_size(vec2(0.0, 24.0)),
+                    )
+                    .clicked()
+                {
+                    self.selected_recipe = None;
+                    self.editing_recipe = Some(Recipe {
+                        id: 0,
+                        name: String::from(""),
+                        servings: 1.0,
+                        items: Vec::new(),
+                    });
+                }
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        for (index, recipe) in self.recipes_list.iter().enumerate() {
+                            if ui
+                                .selectable_label(self.selected_recipe == Some(index), &recipe.name)
+                                .clicked()
+                            {
+                                self.selected_recipe = Some(index);
+                                self.editing_recipe = Some((**recipe).clone());
+                            }
+                        }
+                    });
+            });
+
+        let Some(recipe) = &mut self.editing_recipe else {
+            ui.label("Select a recipe or create a new one.");
+            return;
+        };
+
+        ui.heading(if recipe.id == 0 {
+            "Create new recipe"
+        } else {
+            "Edit recipe"
+        });
+        ui.horizontal(|ui| {
+            ui.label("Name: ");
+            ui.text_edit_singleline(&mut recipe.name);
+            ui.label("Servings: ");
+            ui.add(
+                egui::DragValue::new(&mut recipe.servings)
+                    .clamp_range(0.1..=999.0)
+                    .speed(0.1),
+            );
+        });
+        ui.separator();
+
+        let mut removed_item: Option<usize> = None;
+        egui::Grid::new("recipe_items")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                for (index, item) in recipe.items.iter_mut().enumerate() {
+                    ui.label(&item.ingredient.name);
+                    ui.add(
+                        egui::DragValue::new(&mut item.amount)
+                            .clamp_range(0..=99999)
+                            .speed(0.1)
+                            .max_decimals(3),
+                    );
+                    ComboBox::from_id_source(("recipe_item_unit", index))
+                        .width(48.0)
+                        .selected_text(item.unit.to_string())
+                        .show_ui(ui, |ui| {
+                            for unit in item.ingredient.available_units() {
+                                ui.selectable_value(&mut item.unit, unit, unit.to_string());
+                            }
+                        });
+                    if ui.button("✖").clicked() {
+                        removed_item = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = removed_item {
+            recipe.items.remove(index);
+        }
+
+        ComboBox::from_id_source("recipe_add_ingredient")
+            .width(160.0)
+            .selected_text("Add ingredient")
+            .show_ui(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.recipe_ingredient_search)
+                        .lock_focus(true)
+                        .hint_text("🔎"),
+                )
+                .request_focus();
+
+                let search = self.recipe_ingredient_search.to_ascii_lowercase();
+                for ingredient in self.ingredients_list.iter().filter(|ingredient| {
+                    ingredient.name.to_ascii_lowercase().contains(&search)
+                        || ingredient.brand.to_ascii_lowercase().contains(&search)
+                }) {
+                    if ui.selectable_label(false, &ingredient.name).clicked() {
+                        let nutritional_info = &ingredient.nutritional_info[0];
+                        recipe.items.push(RecipeItem {
+                            ingredient: ingredient.clone(),
+                            amount: nutritional_info.default_amount,
+                            unit: nutritional_info.default_unit,
+                        });
+                    }
+                }
+            });
+        ui.separator();
+
+        let per_serving = recipe.nutrients_per_serving();
+        let macronutrients = &per_serving.macronutrients;
+        ui.label(format!(
+            "Per serving: {:.0} kcal, Protein {:.1} g, Fat {:.1} g, Carbohydrates (net) {:.1} g",
+            per_serving.kilocalories,
+            macronutrients.proteins.total_proteins(),
+            macronutrients.fats.total_fats(),
+            macronutrients.carbohydrates.net_carbs()
+        ));
+        let mut scored_proteins = ScoredProteins::default();
+        scored_proteins.add_recipe(recipe, 1.0);
+        amino_acid_score_label(ui, &scored_proteins);
+
+        let mut close_editor = false;
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    !recipe.name.trim().is_empty() && recipe.servings > 0.0,
+                    egui::Button::new("Save"),
+                )
+                .clicked()
+            {
+                let result = if recipe.id == 0 {
+                    self.database.insert_recipe(recipe).map(|id| recipe.id = id)
+                } else {
+                    self.database.update_recipe(recipe)
+                };
+                if self.notifications.report(result).is_some() {
+                    self.update_recipes = true;
+                    self.update_log_entries = true;
+                }
+            }
+            if recipe.id != 0 && ui.button("Delete").clicked() {
+                let result = self.database.delete_recipe(recipe);
+                if self.notifications.report(result).is_some() {
+                    self.update_recipes = true;
+                    self.update_log_entries = true;
+                    close_editor = true;
+                }
+            }
+            if ui.button("Cancel").clicked() {
+                close_editor = true;
+            }
+        });
+        if close_editor {
+            self.editing_recipe = None;
+            self.selected_recipe = None;
+        }
+    }
+
+    fn goals_view(&mut self, ui: &mut Ui) {
+        fn weekday_name(weekday: Option<Weekday>) -> &'static str {
+            match weekday {
+                None => "Every day",
+                Some(Weekday::Mon) => "Monday",
+                Some(Weekday::Tue) => "Tuesday",
+                Some(Weekday::Wed) => "Wednesday",
+                Some(Weekday::Thu) => "Thursday",
+                Some(Weekday::Fri) => "Friday",
+                Some(Weekday::Sat) => "Saturday",
+                Some(Weekday::Sun) => "Sunday",
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Goals for");
+            ComboBox::from_id_source("goals_weekday")
+                .selected_text(weekday_name(self.goals_selected_weekday))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.goals_selected_weekday, None, "Every day");
+                    for day in 0..7 {
+                        let weekday = Weekday::try_from(day).ok();
+                        ui.selectable_value(
+                            &mut self.goals_selected_weekday,
+                            weekday,
+                            weekday_name(weekday),
+                        );
+                    }
+                });
+        });
+        ui.label(
+            egui::RichText::new(format!(
+                "Days within {:.0}% of a target count as on target. \
+                 The calendar is coloured by the calorie goal.",
+                goals::GOAL_TOLERANCE * 100.0
+            ))
+            .weak(),
+        );
+        ui.separator();
+
+        let weekday = self.goals_selected_weekday;
+        let mut changed_goal: Option<Goal> = None;
+        let mut removed_goal: Option<Goal> = None;
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                egui::Grid::new("goals_grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for nutrient in GoalNutrient::ALL {
+                            let every_day_target = self
+                                .goals_list
+                                .iter()
+                                .find(|goal| goal.nutrient == nutrient && goal.weekday.is_none())
+                                .map(|goal| goal.target);
+
+                            ui.label(nutrient.to_string());
+                            if let Some(goal) = self
+                                .goals_list
+                                .iter_mut()
+                                .find(|goal| goal.nutrient == nutrient && goal.weekday == weekday)
+                            {
+                                let response = ui.add(
+                                    egui::DragValue::new(&mut goal.target)
+                                        .clamp_range(0..=99999)
+                                        .speed(nutrient.default_target() * 0.005)
+                                        .max_decimals(1)
+                                        .suffix(format!(" {}", nutrient.unit())),
+                                );
+                                if response.drag_released()
+                                    || (response.changed() && !response.dragged())
+                                {
+                                    changed_goal = Some(goal.clone());
+                                }
+                                if ui.button("Remove").clicked() {
+                                    removed_goal = Some(goal.clone());
+                                }
+                            } else {
+                                ui.label(
+                                    egui::RichText::new(match (weekday, every_day_target) {
+                                        (Some(_), Some(target)) => {
+                                            format!("{:.1} {} (every day)", target, nutrient.unit())
+                                        }
+                                        _ => "No goal".to_owned(),
+                                    })
+                                    .weak(),
+                                );
+                                if ui.button("Set").clicked() {
+                                    changed_goal = Some(Goal {
+                                        nutrient,
+                                        weekday,
+                                        target: every_day_target
+                                            .unwrap_or_else(|| nutrient.default_target()),
+                                    });
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(goal) = changed_goal {
+            self.notifications.report(self.database.set_goal(&goal));
+            self.update_goals = true;
+        }
+        if let Some(goal) = removed_goal {
+            self.notifications.report(self.database.delete_goal(&goal));
+            self.update_goals = true;
+        }
+    }
+
+    fn refresh_goals(&mut self) {
+        if !self.update_goals {
+            return;
+        }
+        self.update_goals = false;
+
+        if let Some(goals) = self.notifications.report(self.database.get_goals()) {
+            self.goals_list = goals;
+        }
+        // The calendar colours depend on the goals.
+        self.update_log_entries = true;
+    }
+
+    fn refresh_log_entries(&mut self) {
+        self.refresh_goals();
+        if !self.update_log_entries {
+            return;
+        }
+        self.update_log_entries = false;
+        self.update_trends = true;
+        self.update_intakes = true;
+
+        let date = *self
+            .date
+            .get_or_insert_with(|| chrono::offset::Utc::now().date_naive());
+        if let Some(log_entries) = self
+            .notifications
+            .report(self.database.get_log_entries(&date))
+        {
+            self.log_entry_list = log_entries;
+            self.selected_log_entries.clear();
+        }
+        if let Some(log_entry_dates) = self
+            .notifications
+            .report(self.database.get_log_entry_dates())
+        {
+            self.log_entry_dates = log_entry_dates;
+        }
+        if let Some(meals) = self.notifications.report(self.database.get_meals()) {
+            self.meals_list = meals;
+        }
+        // Only the days the calendar shows around `date`, summing the whole log would
+        // make every refresh slower as the log grows.
+        let (start, end) = datepicker::month_range(date);
+        if let Some(daily_calories) = self
+            .notifications
+            .report(self.database.get_daily_calories(&start, &end))
+        {
+            self.goal_day_colors = daily_calories
+                .into_iter()
+                .filter_map(|(date, kilocalories)| {
+                    goals::goal_for_day(&self.goals_list, GoalNutrient::Kilocalories, date).map(
+                        |goal| {
+                            (
+                                date,
+                                goal_status_color(GoalStatus::new(kilocalories, goal.target)),
+                            )
+                        },
+                    )
+                })
+                .collect();
+        }
+    }
+
+    fn statistics_view(&mut self, ui: &mut Ui) {
+        let totals = NutrientTotals::from_log_entries(&self.log_entry_list);
+        let macronutrients = &totals.macronutrients;
+        let protein = macronutrients.proteins.total_proteins();
+        let fat = macronutrients.fats.total_fats();
+        let carbohydrates = &macronutrients.carbohydrates;
+
+        if let Some(date) = self.date {
+            ui.heading(date.format("%d.%m.%Y").to_string());
+        }
+        amino_acid_score_label(ui, &ScoredProteins::from_log_entries(&self.log_entry_list));
+        ui.separator();
+
+        ui.allocate_ui_with_layout(
+            ui.available_size(),
+            egui::Layout::left_to_right(Align::TOP),
+            |ui| {
+                egui::Grid::new("statistics_totals")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        let mut total_row = |label: &str, value: f32, unit: &str| {
+                            ui.label(label);
+                            ui.label(format!("{:.1} {}", value, unit));
+                            ui.end_row();
+                        };
+                        total_row("Calories", totals.kilocalories, "kcal");
+                        total_row("Protein", protein, "g");
+                        total_row("Fat", fat, "g");
+                        total_row("Carbohydrates (net)", carbohydrates.net_carbs(), "g");
+                        total_row("Carbohydrates (total)", carbohydrates.total_carbs(), "g");
+                        total_row("Fiber", carbohydrates.fiber, "g");
+                        total_row("Sugars", carbohydrates.sugars, "g");
+                    });
+
+                let (slices, ring_slices) = macronutrient_slices(macronutrients, true);
+                ui.add(pie_chart::pie_chart(vec2(8.0, 8.0), slices, ring_slices));
+            },
+        );
+    }
+
+    fn refresh_trends(&mut self, range: (NaiveDate, NaiveDate)) {
+        if !self.update_trends && self.trends_loaded_range == Some(range) {
+            return;
+        }
+        self.update_trends = false;
+        self.trends_loaded_range = Some(range);
+
+        // The rolling averages of the first days also take earlier days.
+        let start = trends::rolling_average_start(range.0);
+        if let Some(totals) = self
+            .notifications
+            .report(self.database.get_daily_totals(&start, &range.1))
+        {
+            self.trends_totals = totals;
+        }
+    }
+
+    fn trends_view(&mut self, ui: &mut Ui) {
+        fn shade(color: Color32, factor: f32) -> Color32 {
+            Color32::from_rgb(
+                (color.r() as f32 * factor) as u8,
+                (color.g() as f32 * factor) as u8,
+                (color.b() as f32 * factor) as u8,
+            )
+        }
+
+        // The daily amounts of one nutrient, followed by their rolling average
+        // and the goal if there is one. `amounts` start `leading` days before
+        // the range, for the rolling average only.
+        fn nutrient_series(
+            name: &str,
+            color: Color32,
+            style: ChartStyle,
+            amounts: Vec<Option<f32>>,
+            leading: usize,
+            rolling_average: bool,
+            targets: Option<Vec<Option<f32>>>,
+        ) -> Vec<ChartSeries> {
+            let mut series = Vec::new();
+            if rolling_average {
+                series.push(ChartSeries {
+                    name: format!("{} ({}-day average)", name, ROLLING_AVERAGE_DAYS),
+                    color: shade(color, 0.75),
+                    style: ChartStyle::Line,
+                    values: trends::rolling_average(&amounts, ROLLING_AVERAGE_DAYS)[leading..]
+                        .to_vec(),
+                });
+            }
+            if let Some(targets) = targets.filter(|targets| targets.iter().any(Option::is_some)) {
+                series.push(ChartSeries {
+                    name: format!("{} goal", name),
+                    color: shade(color, 0.55),
+                    style: ChartStyle::Dashed,
+                    values: targets,
+                });
+            }
+            series.insert(
+                0,
+                ChartSeries {
+                    name: name.to_owned(),
+                    color,
+                    style,
+                    values: amounts[leading..].to_vec(),
+                },
+            );
+            series
+        }
+
+        ui.horizontal(|ui| {
+            for range in TrendRange::ALL {
+                ui.selectable_value(&mut self.trends_range, range, range.to_string());
+            }
+            ui.separator();
+            ui.selectable_value(&mut self.trends_style, ChartStyle::Line, "Lines");
+            ui.selectable_value(&mut self.trends_style, ChartStyle::Bar, "Bars");
+            ui.separator();
+            ui.checkbox(
+                &mut self.trends_rolling_average,
+                format!("{}-day average", ROLLING_AVERAGE_DAYS),
+            );
+        });
+        // The preset ranges end on the day shown in the daily log.
+        let end = self
+            .date
+            .unwrap_or_else(|| chrono::offset::Utc::now().date_naive());
+        let range = match self.trends_range.ending_at(end) {
+            Some(range) => range,
+            None => {
+                ui.horizontal(|ui| {
+                    ui.label("From: ");
+                    ui.add(
+                        DatePickerButton::new(&mut self.trends_start)
+                            .id_source("trends_start")
+                            .format("%d.%m.%Y")
+                            .highlight_weekends(false)
+                            .with_data(&self.log_entry_dates),
+                    );
+                    ui.label("To: ");
+                    ui.add(
+                        DatePickerButton::new(&mut self.trends_end)
+                            .id_source("trends_end")
+                            .format("%d.%m.%Y")
+                            .highlight_weekends(false)
+                            .with_data(&self.log_entry_dates),
+                    );
+                });
+                (self.trends_start, self.trends_end)
+            }
+        };
+        ui.separator();
+        if range.0 > range.1 {
+            ui.colored_label(
+                Color32::from_rgb(192, 32, 16),
+                "The start date has to be before the end date.",
+            );
+            return;
+        }
+        self.refresh_trends(range);
+
+        let days = trends::days(range.0, range.1);
+        let history = trends::days(trends::rolling_average_start(range.0), range.1);
+        let leading = history.len() - days.len();
+        let labels: Vec<String> = days
+            .iter()
+            .map(|day| day.format("%d.%m.").to_string())
+            .collect();
+        let style = self.trends_style;
+        let rolling_average = self.trends_rolling_average;
+        let amounts = |amount: &dyn Fn(&NutrientTotals) -> f32| {
+            trends::daily_amounts(&self.trends_totals, &history, amount)
+        };
+        let targets = |nutrient: Option<GoalNutrient>| {
+            nutrient.map(|nutrient| trends::daily_targets(&self.goals_list, nutrient, &days))
+        };
+
+        let calories = nutrient_series(
+            "Calories",
+            Color32::GOLD,
+            style,
+            amounts(&|totals| totals.kilocalories),
+            leading,
+            rolling_average,
+            targets(Some(GoalNutrient::Kilocalories)),
+        );
+        let mut macronutrients = Vec::new();
+        for (nutrient, color) in [
+            (GoalNutrient::Protein, Color32::LIGHT_GREEN),
+            (GoalNutrient::Fat, Color32::LIGHT_BLUE),
+            (GoalNutrient::Carbohydrates, Color32::LIGHT_RED),
+        ] {
+            macronutrients.extend(nutrient_series(
+                &nutrient.to_string(),
+                color,
+                style,
+                amounts(&|totals| nutrient.amount(totals)),
+                leading,
+                rolling_average,
+                targets(Some(nutrient)),
+            ));
+        }
+        let field = self.trends_micronutrient;
+        let micronutrient = nutrient_series(
+            &nutrient_label(field),
+            Color32::from_rgb(200, 160, 255),
+            style,
+            amounts(&|totals| totals.nutrient(field).unwrap_or(0.0)),
+            leading,
+            rolling_average,
+            targets(trends::field_goal_nutrient(field)),
+        );
+
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                ui.strong("Calories");
+                ui.add(chart::chart(10.0, labels.clone(), calories, "kcal"));
+                ui.separator();
+                ui.strong("Macronutrients");
+                ui.add(chart::chart(10.0, labels.clone(), macronutrients, "g"));
+                ui.separator();
+                ComboBox::from_id_source("trends_micronutrient")
+                    .selected_text(nutrient_label(field))
+                    .show_ui(ui, |ui| {
+                        for field in micronutrient_fields() {
+                            ui.selectable_value(
+                                &mut self.trends_micronutrient,
+                                field,
+                                nutrient_label(field),
+                            );
+                        }
+                    });
+                ui.add(chart::chart(
+                    10.0,
+                    labels,
+                    micronutrient,
+                    nutrient_unit(field),
+                ));
+            });
+    }
+
+    fn refresh_intake_profile(&mut self) {
+        if !self.update_intake_profile {
+            return;
+        }
+        self.update_intake_profile = false;
+
+        if let Some(profile) = self
+            .notifications
+            .report(self.database.get_intake_profile())
+        {
+            self.intake_profile = profile;
+        }
+    }
+
+    fn refresh_intakes(&mut self, range: (NaiveDate, NaiveDate)) {
+        if !self.update_intakes && self.intakes_loaded_range == Some(range) {
+            return;
+        }
+        self.update_intakes = false;
+        self.intakes_loaded_range = Some(range);
+
+        if let Some(daily_totals) = self
+            .notifications
+            .report(self.database.get_daily_totals(&range.0, &range.1))
+        {
+            let mut totals = NutrientTotals::default();
+            for day in daily_totals.values() {
+                totals += day;
+            }
+            self.intakes_range_totals = (totals, daily_totals.len());
+        }
+    }
+
+    fn intakes_view(&mut self, ui: &mut Ui) {
+        fn status_color(status: IntakeStatus) -> Color32 {
+            match status {
+                IntakeStatus::Deficit => Color32::YELLOW,
+                IntakeStatus::Adequate => Color32::GREEN,
+                IntakeStatus::Excess => Color32::RED,
+            }
         }
-        macro_rules! clear_log_entry {
-            () => {
-                self.new_log_entry_fraction = 1.0;
-                self.new_log_entry_ingredient_search = String::from("");
-                self.new_log_entry_ingredient_previous_search = String::from("");
-                self.new_log_entry_filtered_ingredients.clear();
-                self.new_log_entry_selected_ingredient = None;
-            };
+
+        fn amount_text(amount: f32, unit: &str) -> String {
+            if amount < 10.0 {
+                format!("{:.2} {}", amount, unit)
+            } else {
+                format!("{:.0} {}", amount, unit)
+            }
         }
-        macro_rules! cancel_log_entry {
-            () => {
-                clear_log_entry!();
-                self.show_new_log_entry_dialog = false;
-            };
+
+        let mut profile = self.intake_profile;
+        ui.horizontal(|ui| {
+            ui.label("Reference intakes for");
+            ComboBox::from_id_source("intake_profile_sex")
+                .selected_text(profile.sex.to_string())
+                .show_ui(ui, |ui| {
+                    for sex in Sex::ALL {
+                        ui.selectable_value(&mut profile.sex, sex, sex.to_string());
+                    }
+                });
+            ComboBox::from_id_source("intake_profile_age")
+                .selected_text(profile.age.to_string())
+                .show_ui(ui, |ui| {
+                    for age in AgeGroup::ALL {
+                        ui.selectable_value(&mut profile.age, age, age.to_string());
+                    }
+                });
+        });
+        if profile != self.intake_profile {
+            self.notifications
+                .report(self.database.set_intake_profile(&profile));
+            self.intake_profile = profile;
         }
 
-        ui.heading("Create new log entry");
         ui.horizontal(|ui| {
-            ui.label("Fraction: ");
+            ui.selectable_value(&mut self.intakes_range_enabled, false, "Day");
+            ui.selectable_value(&mut self.intakes_range_enabled, true, "Date range");
+            if self.intakes_range_enabled {
+                ui.separator();
+                ui.label("From: ");
+                ui.add(
+                    DatePickerButton::new(&mut self.intakes_start)
+                        .id_source("intakes_start")
+                        .format("%d.%m.%Y")
+                        .highlight_weekends(false)
+                        .with_data(&self.log_entry_dates),
+                );
+                ui.label("To: ");
+                ui.add(
+                    DatePickerButton::new(&mut self.intakes_end)
+                        .id_source("intakes_end")
+                        .format("%d.%m.%Y")
+                        .highlight_weekends(false)
+                        .with_data(&self.log_entry_dates),
+                );
+            }
+        });
+
+        let (totals, days) = if self.intakes_range_enabled {
+            let range = (self.intakes_start, self.intakes_end);
+            if range.0 > range.1 {
+                ui.colored_label(
+                    Color32::from_rgb(192, 32, 16),
+                    "The start date has to be before the end date.",
+                );
+                return;
+            }
+            self.refresh_intakes(range);
+            let (totals, days) = &self.intakes_range_totals;
+            ui.label(
+                egui::RichText::new(format!(
+                    "Daily averages over the {} days with log entries.",
+                    days
+                ))
+                .weak(),
+            );
+            (totals.clone(), *days)
+        } else {
+            if let Some(date) = self.date {
+                ui.label(egui::RichText::new(date.format("%d.%m.%Y").to_string()).weak());
+            }
+            (NutrientTotals::from_log_entries(&self.log_entry_list), 1)
+        };
+        ui.separator();
+
+        let rows = reference_intakes::coverage(&totals, days, self.intake_profile);
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                egui::Grid::new("intakes_grid")
+                    .num_columns(5)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for header in ["Nutrient", "Amount", "Reference", "Coverage", "Limit"] {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+
+                        for row in rows {
+                            let unit = nutrient_unit(row.nutrient);
+                            ui.label(nutrient_label(row.nutrient));
+                            ui.label(amount_text(row.amount, unit));
+                            let (Some(intake), Some(percent), Some(status)) =
+                                (row.intake, row.percent(), row.status())
+                            else {
+                                ui.label(egui::RichText::new("No reference").weak());
+                                ui.end_row();
+                                continue;
+                            };
+                            ui.label(amount_text(intake.amount, unit));
+                            let response = ui.add(
+                                egui::ProgressBar::new((percent / 100.0).min(1.0))
+                                    .desired_width(120.0)
+                                    .fill(status_color(status))
+                                    .text(
+                                        egui::RichText::new(format!("{:.0}%", percent))
+                                            .color(Color32::BLACK),
+                                    ),
+                            );
+                            match status {
+                                IntakeStatus::Deficit => {
+                                    response.on_hover_text("Below the reference intake");
+                                }
+                                IntakeStatus::Excess => {
+                                    response.on_hover_text("Above the upper limit");
+                                }
+                                IntakeStatus::Adequate => {}
+                            }
+                            match intake.upper_limit {
+                                Some(limit) => ui.label(amount_text(limit, unit)),
+                                None => ui.label(egui::RichText::new("None").weak()),
+                            };
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    fn log_export(&mut self, ui: &mut Ui) {
+        ui.heading("Export daily logs");
+        ui.horizontal(|ui| {
+            ui.label("From: ");
             ui.add(
-                egui::DragValue::new(&mut self.new_log_entry_fraction)
-                    .clamp_range(0..=9999)
-                    .speed(0.1),
+                DatePickerButton::new(&mut self.log_export_start)
+                    .id_source("log_export_start")
+                    .format("%d.%m.%Y")
+                    .highlight_weekends(false)
+                    .with_data(&self.log_entry_dates),
+            );
+            ui.label("To: ");
+            ui.add(
+                DatePickerButton::new(&mut self.log_export_end)
+                    .id_source("log_export_end")
+                    .format("%d.%m.%Y")
+                    .highlight_weekends(false)
+                    .with_data(&self.log_entry_dates),
             );
         });
-        if self.new_log_entry_ingredient_search.len() > 1
-            && !self
-                .new_log_entry_ingredient_search
-                .eq_ignore_ascii_case(self.new_log_entry_ingredient_previous_search.as_str())
-        {
-            self.new_log_entry_ingredient_previous_search =
-                self.new_log_entry_ingredient_search.clone();
-
-            self.new_log_entry_filtered_ingredients = self
-                .ingredients_list
-                .iter()
-                .filter(|&n| {
-                    n.name
-                        .to_ascii_lowercase()
-                        .contains(&self.new_log_entry_ingredient_search.to_ascii_lowercase())
-                        || n.brand
-                            .to_ascii_lowercase()
-                            .contains(&self.new_log_entry_ingredient_search.to_ascii_lowercase())
-                })
-                .map(|x| x.clone())
-                .collect();
-        }
         ui.horizontal(|ui| {
-            ui.label("Ingredient: ");
-            ComboBox::from_id_source("new_log_ingredient")
-                .width(128.0)
-                .selected_text(
-                    if let Some(ingredient) = &self.new_log_entry_selected_ingredient {
-                        ingredient.name.clone()
-                    } else {
-                        "-".to_owned()
+            for format in [ExportFormat::Csv, ExportFormat::Json] {
+                ui.selectable_value(&mut self.log_export_format, format, format.to_string());
+            }
+            ui.separator();
+            for granularity in [Granularity::Entry, Granularity::Day] {
+                ui.selectable_value(
+                    &mut self.log_export_granularity,
+                    granularity,
+                    granularity.to_string(),
+                );
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("File: ");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.log_export_path).hint_text(
+                    match self.log_export_format {
+                        ExportFormat::Csv => "path/to/log.csv",
+                        ExportFormat::Json => "path/to/log.json",
                     },
-                )
-                .show_ui(ui, |ui| {
-                    let text_edit =
-                        egui::TextEdit::singleline(&mut self.new_log_entry_ingredient_search)
-                            .lock_focus(true)
-                            .hint_text("🔎");
-                    ui.add(text_edit).request_focus();
-
-                    for ingredient in &self.new_log_entry_filtered_ingredients {
-                        ui.horizontal(|ui| {
-                            for category in &ingredient.categories {
-                                ui.add(
-                                    egui::Image::new(get_icon_image_source(&category.icon_name))
-                                        .tint(category.icon_color)
-                                        .fit_to_exact_size(vec2(16.0, 16.0))
-                                        .texture_options(TextureOptions {
-                                            magnification: TextureFilter::Nearest,
-                                            minification: TextureFilter::Nearest,
-                                            wrap_mode: TextureWrapMode::ClampToEdge,
-                                        }),
-                                );
-                            }
-                            ui.selectable_value(
-                                &mut self.new_log_entry_selected_ingredient,
-                                Some(ingredient.clone()),
-                                ingredient.name.clone(),
-                            );
-                        });
-                    }
-                });
+                ),
+            );
         });
+        let valid_range = self.log_export_start <= self.log_export_end;
+        if !valid_range {
+            ui.colored_label(
+                Color32::from_rgb(192, 32, 16),
+                "The start date has to be before the end date.",
+            );
+        }
         ui.horizontal(|ui| {
-            if ui.button("Create").clicked() {
-                create_log_entry!();
-            };
-            if ui.button("Clear").clicked() {
-                clear_log_entry!();
-            };
+            if ui
+                .add_enabled(
+                    valid_range && !self.log_export_path.trim().is_empty(),
+                    egui::Button::new("Export"),
+                )
+                .clicked()
+            {
+                let range = (self.log_export_start, self.log_export_end);
+                if let Some(log_entries) = self
+                    .notifications
+                    .report(self.database.get_log_entries_in_range(&range.0, &range.1))
+                {
+                    let path = PathBuf::from(self.log_export_path.trim());
+                    let result = std::fs::File::create(&path)
+                        .and_then(|file| {
+                            log_export::export(
+                                &log_entries,
+                                range,
+                                self.log_export_format,
+                                self.log_export_granularity,
+                                std::io::BufWriter::new(file),
+                            )
+                        })
+                        .map_err(|error| format!("Unable to write {}: {}", path.display(), error));
+                    if self.notifications.report(result).is_some() {
+                        self.show_log_export_dialog = false;
+                    }
+                }
+            }
             if ui.button("Cancel").clicked() {
-                cancel_log_entry!();
-            };
+                self.show_log_export_dialog = false;
+            }
         });
+        ui.separator();
     }
 
     fn daily_log_view(&mut self, ui: &mut Ui) {
@@ -1441,6 +4327,8 @@ impl MyContext {
             .date
             .get_or_insert_with(|| chrono::offset::Utc::now().date_naive());
 
+        let mut edit_requested = false;
+        let mut delete_requested = false;
         ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
             if ui
                 .add(
@@ -1448,7 +4336,8 @@ impl MyContext {
                         .format("%d.%m.%Y")
                         .highlight_weekends(false)
                         .min_size(vec2(0.0, 24.0))
-                        .with_data(&self.log_entry_dates),
+                        .with_data(&self.log_entry_dates)
+                        .with_data_colors(&self.goal_day_colors),
                 )
                 .changed()
             {
@@ -1473,8 +4362,64 @@ impl MyContext {
                     .clicked()
                 {
                     self.show_new_log_entry_dialog = true;
+                    self.new_log_entry_date = *date;
+                    let now = chrono::offset::Local::now().time();
+                    self.new_log_entry_time =
+                        NaiveTime::from_hms_opt(now.hour(), now.minute(), 0).unwrap_or(now);
+                }
+                if ui
+                    .add_enabled(
+                        self.selected_log_entries.len() == 1,
+                        egui::Button::image_and_text(
+                            egui::Image::new(get_icon_image_source("edit"))
+                                .tint(Color32::GRAY)
+                                .fit_to_exact_size(vec2(16.0, 16.0))
+                                .texture_options(TextureOptions {
+                                    magnification: TextureFilter::Nearest,
+                                    minification: TextureFilter::Nearest,
+                                    wrap_mode: TextureWrapMode::ClampToEdge,
+                                }),
+                            "Edit",
+                        )
+                        .min_size(vec2(0.0, 24.0)),
+                    )
+                    .clicked()
+                {
+                    edit_requested = true;
+                }
+                if ui
+                    .add_enabled(
+                        !self.selected_log_entries.is_empty(),
+                        egui::Button::image_and_text(
+                            egui::Image::new(get_icon_image_source("delete"))
+                                .tint(Color32::RED)
+                                .fit_to_exact_size(vec2(16.0, 16.0))
+                                .texture_options(TextureOptions {
+                                    magnification: TextureFilter::Nearest,
+                                    minification: TextureFilter::Nearest,
+                                    wrap_mode: TextureWrapMode::ClampToEdge,
+                                }),
+                            "Delete",
+                        )
+                        .min_size(vec2(0.0, 24.0)),
+                    )
+                    .on_hover_text("Ctrl+click entries to select several.")
+                    .clicked()
+                {
+                    delete_requested = true;
                 }
             });
+            if ui
+                .add_enabled(
+                    !self.show_log_export_dialog,
+                    egui::Button::new("Export").min_size(vec2(0.0, 24.0)),
+                )
+                .clicked()
+            {
+                self.show_log_export_dialog = true;
+                self.log_export_start = *date - chrono::Days::new(6);
+                self.log_export_end = *date;
+            }
             ui.label(format!(
                 "{} {}",
                 self.log_entry_list.len(),
@@ -1485,38 +4430,161 @@ impl MyContext {
                 }
             ));
         });
+        let date = *date;
+        if edit_requested {
+            self.edit_log_entry(self.selected_log_entries[0]);
+        }
+        if delete_requested {
+            self.delete_selected_log_entries();
+        }
+        if let Some(code) = self.barcode_scan_unknown.clone() {
+            ui.horizontal(|ui| {
+                ui.label(format!("No ingredient has the barcode {}.", code));
+                if ui.button("Create ingredient").clicked() {
+                    self.barcode_scan_unknown = None;
+                    self.editing_ingredient = None;
+                    self.new_ingredient_barcode = code;
+                    self.new_ingredient_servings.clear();
+                    self.new_ingredient_serving = 0;
+                    self.new_ingredient_nutritional_info = Some(NutritionalInfo {
+                        default_amount: 1.0,
+                        ..Default::default()
+                    });
+                    self.show_new_ingredient_dialog = true;
+                    self.focus_tab = Some("Ingredients");
+                }
+                if ui.button("Dismiss").clicked() {
+                    self.barcode_scan_unknown = None;
+                }
+            });
+        }
         if self.show_new_log_entry_dialog {
             self.new_log_entry(ui);
         }
+        if self.show_log_export_dialog {
+            self.log_export(ui);
+        }
+
+        let day_goals = goals::goals_for_day(&self.goals_list, date);
+        if !day_goals.is_empty() {
+            let totals = NutrientTotals::from_log_entries(&self.log_entry_list);
+            egui::CollapsingHeader::new("Goals")
+                .id_source("daily_log_goals")
+                .default_open(true)
+                .show(ui, |ui| {
+                    egui::Grid::new("daily_log_goals_grid")
+                        .num_columns(2)
+                        .show(ui, |ui| {
+                            for goal in day_goals {
+                                let amount = goal.nutrient.amount(&totals);
+                                let progress = if goal.target > 0.0 {
+                                    amount / goal.target
+                                } else {
+                                    1.0
+                                };
+                                let fill = match GoalStatus::new(amount, goal.target) {
+                                    GoalStatus::Under => ui.visuals().selection.bg_fill,
+                                    GoalStatus::On => Color32::DARK_GREEN,
+                                    GoalStatus::Over => Color32::DARK_RED,
+                                };
+                                ui.label(goal.nutrient.to_string());
+                                ui.add(
+                                    egui::ProgressBar::new(progress)
+                                        .desired_width(240.0)
+                                        .fill(fill)
+                                        .text(format!(
+                                            "{:.1} / {:.1} {}",
+                                            amount,
+                                            goal.target,
+                                            goal.nutrient.unit()
+                                        )),
+                                );
+                                ui.end_row();
+                            }
+                        });
+                });
+        }
         ui.separator();
+
+        let mut meal_groups: Vec<(Option<u32>, String, Vec<usize>)> = self
+            .meals_list
+            .iter()
+            .map(|meal| (Some(meal.id), meal.name.clone(), Vec::new()))
+            .collect();
+        meal_groups.push((None, "Other".to_owned(), Vec::new()));
+        for (index, log_entry) in self.log_entry_list.iter().enumerate() {
+            let meal_id = log_entry.meal.as_ref().map(|meal| meal.id);
+            let group = match meal_groups.iter().position(|(id, _, _)| *id == meal_id) {
+                Some(position) => position,
+                None => meal_groups.len() - 1,
+            };
+            meal_groups[group].2.push(index);
+        }
+
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                for (meal_id, meal_name, mut indices) in meal_groups {
+                    if indices.is_empty() {
+                        continue;
+                    }
+                    // Entries without a time go after the timed ones.
+                    indices.sort_by_key(|&index| {
+                        let log_entry = &self.log_entry_list[index];
+                        (log_entry.time.is_none(), log_entry.time, log_entry.id)
+                    });
+
+                    let mut totals = NutrientTotals::default();
+                    for &index in &indices {
+                        totals.add_log_entry(&self.log_entry_list[index]);
+                    }
+                    let macronutrients = &totals.macronutrients;
+                    egui::CollapsingHeader::new(format!(
+                        "{} - {:.0} kcal, P {:.1} g, F {:.1} g, C {:.1} g",
+                        meal_name,
+                        totals.kilocalories,
+                        macronutrients.proteins.total_proteins(),
+                        macronutrients.fats.total_fats(),
+                        macronutrients.carbohydrates.net_carbs()
+                    ))
+                    .id_source(("daily_log_meal", meal_id))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        self.log_entry_table(ui, &indices);
+                    });
+                }
+            });
+    }
+
+    fn log_entry_table(&mut self, ui: &mut Ui, indices: &[usize]) {
         TableBuilder::new(ui)
             .sense(egui::Sense::click())
             .striped(true)
+            .vscroll(false)
             .cell_layout(
                 egui::Layout::centered_and_justified(egui::Direction::LeftToRight)
                     .with_main_align(egui::Align::LEFT),
             )
-            .column(Column::auto().resizable(true))
-            .columns(Column::remainder().resizable(true), 2)
-            .header(20.0, |mut header| {
-                header.col(|ui| {
-                    ui.heading("Name");
-                });
-                header.col(|ui| {
-                    ui.heading("Amount");
-                });
-                header.col(|ui| {
-                    ui.heading("Calories");
-                });
-            })
+            .column(Column::exact(48.0))
+            .column(Column::remainder().at_least(160.0))
+            .columns(Column::exact(120.0), 2)
             .body(|body| {
-                body.rows(30.0, self.log_entry_list.len(), |mut row| {
-                    let row_index = row.index();
-                    row.set_selected(self.selected_log_entry.is_some_and(|idx| idx == row_index));
+                body.rows(30.0, indices.len(), |mut row| {
+                    let row_index = indices[row.index()];
+                    row.set_selected(self.selected_log_entries.contains(&row_index));
 
+                    row.col(|ui| {
+                        if let Some(time) = self.log_entry_list[row_index].time {
+                            ui.label(time.format("%H:%M").to_string());
+                        }
+                    });
                     row.col(|ui| {
                         ui.horizontal(|ui| {
-                            for category in &self.log_entry_list[row_index].ingredient.categories {
+                            let categories = match &self.log_entry_list[row_index].item {
+                                LogItem::Ingredient(ingredient) => ingredient.categories.as_slice(),
+                                LogItem::Recipe(_) => &[],
+                            };
+                            for category in categories {
                                 let icon_name = &category.icon_name;
 
                                 let response = ui.add(
@@ -1543,43 +4611,47 @@ impl MyContext {
                                     );
                                 }
                             }
-                            ui.label(&self.log_entry_list[row_index].ingredient.name);
+                            ui.label(self.log_entry_list[row_index].item.name());
+                            if let LogItem::Recipe(_) = self.log_entry_list[row_index].item {
+                                ui.label(egui::RichText::new("recipe").weak());
+                            }
                         });
                     });
                     row.col(|ui| {
-                        ui.label(format!(
-                            "{} x {} {}",
-                            &self.log_entry_list[row_index].fraction.to_string(),
-                            &self.log_entry_list[row_index].ingredient.nutritional_info[0]
-                                .default_amount
-                                .to_string(),
-                            &self.log_entry_list[row_index].ingredient.nutritional_info[0]
-                                .default_unit
-                                .to_string()
-                        ));
+                        ui.label(log_entry_amount_text(&self.log_entry_list[row_index]));
                     });
 
                     row.col(|ui| {
-                        ui.label(
-                            &self.log_entry_list[row_index]
-                                .calculate_calories(
-                                    self.selected_log_entry_nutrition_info.unwrap_or(0),
-                                )
-                                .to_string(),
-                        );
+                        ui.label(format!(
+                            "{:.1} kcal",
+                            self.log_entry_list[row_index].nutrients().kilocalories
+                        ));
                     });
 
-                    if row.response().clicked() {
-                        match self.selected_log_entry {
-                            Some(current_idx) if current_idx == row_index => {
-                                self.selected_log_entry = None;
-                                self.selected_log_entry_nutrition_info = None;
-                            }
-                            _ => {
-                                self.selected_log_entry = Some(row_index);
-                                self.selected_log_entry_nutrition_info = Some(0);
+                    let response = row.response();
+                    if response.clicked() {
+                        let position = self
+                            .selected_log_entries
+                            .iter()
+                            .position(|&idx| idx == row_index);
+                        if response.ctx.input(|input| input.modifiers.command) {
+                            match position {
+                                Some(position) => {
+                                    self.selected_log_entries.remove(position);
+                                }
+                                None => self.selected_log_entries.push(row_index),
                             }
+                        } else if position.is_some() && self.selected_log_entries.len() == 1 {
+                            self.selected_log_entries.clear();
+                        } else {
+                            self.selected_log_entries = vec![row_index];
                         }
+                        self.selected_ingredient = None;
+                        self.selected_ingredient_nutrition_info = None;
+                        self.selected_category = None;
+                    }
+                    if response.double_clicked() && !self.show_new_log_entry_dialog {
+                        self.edit_log_entry(row_index);
                     }
                 });
             });
@@ -1941,15 +5013,30 @@ impl TabViewer for MyContext {
 
                 if self.update_ingredients {
                     self.update_ingredients = false;
-                    self.ingredients_list = self.database.get_ingredients();
+                    if let Some(ingredients) =
+                        self.notifications.report(self.database.get_ingredients())
+                    {
+                        self.ingredients_list = ingredients;
+                    }
+                    if let Some(ingredients) = self
+                        .notifications
+                        .report(self.database.get_archived_ingredients())
+                    {
+                        self.archived_ingredients_list = ingredients;
+                    }
 
-                    //Upgrade log entries in case ingredient was deleted.
+                    //Upgrade log entries and recipes in case ingredient was deleted.
                     self.update_log_entries = true;
+                    self.update_recipes = true;
                 }
 
                 if self.update_categories {
                     self.update_categories = false;
-                    self.categories_list = self.database.get_categories();
+                    if let Some(categories) =
+                        self.notifications.report(self.database.get_categories())
+                    {
+                        self.categories_list = categories;
+                    }
                 }
 
                 self.ingredients_view(ui);
@@ -1960,21 +5047,42 @@ impl TabViewer for MyContext {
 
                 if self.update_categories {
                     self.update_categories = false;
-                    self.categories_list = self.database.get_categories();
+                    if let Some(categories) =
+                        self.notifications.report(self.database.get_categories())
+                    {
+                        self.categories_list = categories;
+                    }
                 }
 
                 self.categories_view(ui);
             }
             "Details" => self.details_view(ui),
             "Daily Log" => {
-                if self.update_log_entries {
-                    self.update_log_entries = false;
-                    self.log_entry_list = self.database.get_log_entries(&self.date.unwrap());
-                    self.log_entry_dates = self.database.get_log_entry_dates();
-                }
-
+                self.refresh_recipes();
+                self.refresh_log_entries();
                 self.daily_log_view(ui)
             }
+            "Statistics" => {
+                self.refresh_log_entries();
+                self.statistics_view(ui)
+            }
+            "Recipes" => {
+                self.refresh_recipes();
+                self.recipes_view(ui)
+            }
+            "Goals" => {
+                self.refresh_goals();
+                self.goals_view(ui)
+            }
+            "Trends" => {
+                self.refresh_log_entries();
+                self.trends_view(ui)
+            }
+            "Micronutrients" => {
+                self.refresh_log_entries();
+                self.refresh_intake_profile();
+                self.intakes_view(ui)
+            }
             _ => {
                 ui.label(tab.as_str());
             }
@@ -1988,12 +5096,8 @@ impl TabViewer for MyContext {
         _surface: SurfaceIndex,
         _node: NodeIndex,
     ) {
-        match tab.as_str() {
-            _ => {
-                ui.label(tab.to_string());
-                ui.label("This is a context menu");
-            }
-        }
+        ui.label(tab.to_string());
+        ui.label("This is a context menu");
     }
 
     fn closeable(&mut self, tab: &mut Self::Tab) -> bool {
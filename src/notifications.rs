@@ -0,0 +1,51 @@
+use egui::{Color32, RichText, Ui};
+
+pub struct Notifications {
+    messages: Vec<String>,
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self {
+            messages: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn error(&mut self, message: impl std::fmt::Display) {
+        let message = message.to_string();
+        log::error!("{}", message);
+        if !self.messages.contains(&message) {
+            self.messages.push(message);
+        }
+    }
+
+    // Passes successful values through and turns errors into a notification.
+    pub fn report<T, E: std::fmt::Display>(&mut self, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.error(error);
+                None
+            }
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut Ui) {
+        let mut dismissed: Option<usize> = None;
+        for (idx, message) in self.messages.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("✖").on_hover_text("Dismiss").clicked() {
+                    dismissed = Some(idx);
+                }
+                ui.colored_label(Color32::from_rgb(192, 32, 16), RichText::new(message));
+            });
+        }
+        if let Some(idx) = dismissed {
+            self.messages.remove(idx);
+        }
+    }
+}
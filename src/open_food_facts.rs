@@ -0,0 +1,385 @@
+// Reading products from Open Food Facts data dumps, either the tab separated
+// CSV export or the JSONL export with one product per line.
+//
+// Nutrient values in the dumps are given per 100 g, in grams except for the
+// energy. They are converted to the units of `NUTRIENT_FIELDS`.
+//
+// Protein, fat and carbohydrate totals are completed with
+// `NutritionalInfo::complete_totals`.
+//
+// Dumps are several GB large, so they are read line by line and only the
+// products matching a `ProductQuery` are kept.
+
+use crate::barcode;
+use crate::ingredients::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{BufRead, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Dump field and the nutrient it maps to.
+const NUTRIENT_KEYS: [(&str, &str); 27] = [
+    ("saturated-fat_100g", "saturated"),
+    ("monounsaturated-fat_100g", "monounsaturated"),
+    ("polyunsaturated-fat_100g", "polyunsaturated"),
+    ("starch_100g", "starch"),
+    ("fiber_100g", "fiber"),
+    ("sugars_100g", "sugars"),
+    ("polyols_100g", "sugar_alcohols"),
+    ("vitamin-a_100g", "vitamin_a"),
+    ("vitamin-b1_100g", "vitamin_b1"),
+    ("vitamin-b2_100g", "vitamin_b2"),
+    ("vitamin-pp_100g", "vitamin_b3"),
+    ("pantothenic-acid_100g", "vitamin_b5"),
+    ("vitamin-b6_100g", "vitamin_b6"),
+    ("vitamin-b12_100g", "vitamin_b12"),
+    ("vitamin-c_100g", "vitamin_c"),
+    ("vitamin-d_100g", "vitamin_d"),
+    ("vitamin-e_100g", "vitamin_e"),
+    ("vitamin-k_100g", "vitamin_k"),
+    ("calcium_100g", "calcium"),
+    ("copper_100g", "copper"),
+    ("iron_100g", "iron"),
+    ("magnesium_100g", "magnesium"),
+    ("manganese_100g", "manganese"),
+    ("phosphorus_100g", "phosphorus"),
+    ("potassium_100g", "potassium"),
+    ("selenium_100g", "selenium"),
+    ("zinc_100g", "zinc"),
+];
+
+const KILOJOULES_PER_KILOCALORIE: f32 = 4.184;
+const GRAMS_SALT_PER_GRAM_SODIUM: f32 = 2.5;
+
+pub struct Product {
+    // The barcode.
+    pub code: String,
+    pub ingredient: Ingredient,
+}
+
+#[derive(Default)]
+pub struct Dump {
+    pub products: Vec<Product>,
+    // Lines that could not be read, and matching ones without a name or nutrients.
+    pub skipped: usize,
+    // Whether reading stopped at the limit before the end of the dump.
+    pub truncated: bool,
+}
+
+impl Dump {
+    // Adds the product of a line matching `query`, `None` if the line had no
+    // name or nutrients. Returns whether reading should go on.
+    pub fn add(&mut self, product: Option<Product>, query: &ProductQuery) -> bool {
+        match product {
+            None => self.skipped += 1,
+            Some(_) if self.products.len() >= query.limit => {
+                self.truncated = true;
+                return false;
+            }
+            Some(product) => self.products.push(product),
+        }
+        true
+    }
+}
+
+// Which products of a dump are kept: those whose name, brand or barcode
+// contain the text, up to a limit. Clones share the cancellation, so a query
+// handed to a background thread can be stopped from the UI.
+#[derive(Clone)]
+pub struct ProductQuery {
+    text: String,
+    limit: usize,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ProductQuery {
+    pub fn new(text: &str, limit: usize) -> Self {
+        ProductQuery {
+            text: text.trim().to_lowercase(),
+            limit,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn matches(&self, code: &str, name: &str, brands: &str) -> bool {
+        self.text.is_empty()
+            || code.trim() == self.text
+            || name.to_lowercase().contains(&self.text)
+            || brands.to_lowercase().contains(&self.text)
+    }
+}
+
+pub fn read_csv(reader: impl Read, query: &ProductQuery) -> Result<Dump, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .quoting(false)
+        .flexible(true)
+        .from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let columns: HashMap<&str, usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(index, header)| (header, index))
+        .collect();
+    let column = |name: &str| columns.get(name).copied();
+    let (code, name, brands) = (column("code"), column("product_name"), column("brands"));
+
+    let mut dump = Dump::default();
+    for record in reader.records() {
+        if query.is_cancelled() {
+            break;
+        }
+        let Ok(record) = record else {
+            dump.skipped += 1;
+            continue;
+        };
+        let text = |index: Option<usize>| index.and_then(|index| record.get(index)).unwrap_or("");
+        if !query.matches(text(code), text(name), text(brands)) {
+            continue;
+        }
+        let product = product(text(code), text(name), text(brands), |key| {
+            text(column(key)).trim().parse::<f32>().ok()
+        });
+        if !dump.add(product, query) {
+            break;
+        }
+    }
+
+    Ok(dump)
+}
+
+pub fn read_jsonl(reader: impl BufRead, query: &ProductQuery) -> std::io::Result<Dump> {
+    let mut dump = Dump::default();
+    for line in reader.lines() {
+        if query.is_cancelled() {
+            break;
+        }
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Ok(value) = serde_json::from_str::<Value>(&line) else {
+            dump.skipped += 1;
+            continue;
+        };
+        let text = |key: &str| value[key].as_str().unwrap_or("");
+        if !query.matches(text("code"), text("product_name"), text("brands")) {
+            continue;
+        }
+        let nutriments = &value["nutriments"];
+        // Numbers are sometimes written as strings.
+        let product =
+            product(
+                text("code"),
+                text("product_name"),
+                text("brands"),
+                |key| match &nutriments[key] {
+                    Value::Number(number) => number.as_f64().map(|number| number as f32),
+                    Value::String(number) => number.trim().parse::<f32>().ok(),
+                    _ => None,
+                },
+            );
+        if !dump.add(product, query) {
+            break;
+        }
+    }
+
+    Ok(dump)
+}
+
+// Builds the ingredient of one product, `None` without a name or nutrients.
+fn product(
+    code: &str,
+    name: &str,
+    brands: &str,
+    value: impl Fn(&str) -> Option<f32>,
+) -> Option<Product> {
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    // Negative values are errors in the dump.
+    let value = |key: &str| value(key).filter(|value| value.is_finite() && *value >= 0.0);
+
+    let mut nutritional_info = NutritionalInfo {
+        default_amount: 100.0,
+        default_unit: Unit::Grams,
+        ..Default::default()
+    };
+    let mut found = false;
+
+    if let Some(kilocalories) = value("energy-kcal_100g")
+        .or_else(|| value("energy_100g").map(|kilojoules| kilojoules / KILOJOULES_PER_KILOCALORIE))
+    {
+        nutritional_info.kilocalories = kilocalories;
+        found = true;
+    }
+
+    let mut set = |field: &str, grams: f32| {
+        if let (Some(target), Some(units_per_gram)) = (
+            nutritional_info.nutrient_mut(field),
+            nutrient_units_per_gram(field),
+        ) {
+            *target = grams * units_per_gram;
+            found = true;
+        }
+    };
+    for (key, field) in NUTRIENT_KEYS {
+        if let Some(grams) = value(key) {
+            set(field, grams);
+        }
+    }
+    if let Some(grams) = value("vitamin-b9_100g").or_else(|| value("folates_100g")) {
+        set("vitamin_b9", grams);
+    }
+    if let Some(grams) = value("sodium_100g")
+        .or_else(|| value("salt_100g").map(|salt| salt / GRAMS_SALT_PER_GRAM_SODIUM))
+    {
+        set("sodium", grams);
+    }
+
+    // Carbohydrates in the dumps do not include the fiber.
+    let totals = (
+        value("proteins_100g"),
+        value("fat_100g"),
+        value("carbohydrates_100g"),
+    );
+    found |= totals.0.is_some() || totals.1.is_some() || totals.2.is_some();
+    nutritional_info.complete_totals(totals.0, totals.1, totals.2);
+
+    if !found {
+        return None;
+    }
+
+    Some(Product {
+        code: code.trim().to_owned(),
+        ingredient: Ingredient {
+            id: 0,
+            name: name.to_owned(),
+            // Several brands are separated by commas, the first one is the main brand.
+            brand: brands.split(',').next().unwrap_or("").trim().to_owned(),
+            barcode: barcode::normalize(code).ok(),
+            categories: Vec::new(),
+            nutritional_info: vec![nutritional_info],
+            conversions: Conversions::default(),
+            archived: false,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3 * expected.abs().max(1.0),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn csv_dump_values_are_converted_and_totals_kept() {
+        let dump = "\
+code\tproduct_name\tbrands\tenergy-kcal_100g\tproteins_100g\tfat_100g\tsaturated-fat_100g\tcarbohydrates_100g\tsugars_100g\tfiber_100g\tsalt_100g\tvitamin-c_100g\tselenium_100g
+3017620422003\tHazelnut spread\tFerrero,Nutella\t539\t6.3\t30.9\t10.6\t57.5\t56.3\t\t0.107\t0.004\t0.0000123
+0000000000001\t\tNo name\t100\t\t\t\t\t\t\t\t\t
+0000000000002\tWater\t\t\t\t\t\t\t\t\t\t\t
+0000000000003\tBread\t\t\t\t\t\t\t\t3.5\t\t\t
+";
+        let dump = read_csv(dump.as_bytes(), &ProductQuery::new("", 10)).unwrap();
+        assert_eq!(dump.skipped, 2);
+        assert_eq!(dump.products.len(), 2);
+
+        let product = &dump.products[0];
+        assert_eq!(product.code, "3017620422003");
+        assert_eq!(product.ingredient.brand, "Ferrero");
+        assert_eq!(product.ingredient.barcode.as_deref(), Some("3017620422003"));
+        let info = &product.ingredient.nutritional_info[0];
+        assert_eq!(info.default_amount, 100.0);
+        assert_eq!(info.kilocalories, 539.0);
+        let macronutrients = &info.macronutrients;
+        assert_close(macronutrients.proteins.total_proteins(), 6.3);
+        assert_close(macronutrients.fats.saturated, 10.6);
+        assert_close(macronutrients.fats.total_fats(), 30.9);
+        assert_close(macronutrients.carbohydrates.sugars, 56.3);
+        assert_close(macronutrients.carbohydrates.net_carbs(), 57.5);
+        let micronutrients = &info.micronutrients;
+        assert_close(micronutrients.minerals.sodium, 42.8);
+        assert_close(micronutrients.vitamins.vitamin_c, 4.0);
+        assert_close(micronutrients.minerals.selenium, 12.3);
+
+        assert_close(
+            dump.products[1].ingredient.nutritional_info[0]
+                .macronutrients
+                .carbohydrates
+                .fiber,
+            3.5,
+        );
+    }
+
+    #[test]
+    fn jsonl_dump_reads_nutriments() {
+        let dump = r#"{"code":"5449000000996","product_name":"Cola","brands":"Coca-Cola","nutriments":{"energy_100g":180,"sugars_100g":10.6,"carbohydrates_100g":"10.6","sodium_100g":0.01}}
+not json
+
+{"code":"1","product_name":"Empty","nutriments":{}}
+"#;
+        let read = |query: &str| read_jsonl(dump.as_bytes(), &ProductQuery::new(query, 10));
+        let all = read("").unwrap();
+        assert_eq!(all.skipped, 2);
+        assert_eq!(all.products.len(), 1);
+        let info = &all.products[0].ingredient.nutritional_info[0];
+        assert_close(info.kilocalories, 43.02);
+        assert_close(info.macronutrients.carbohydrates.sugars, 10.6);
+        assert_eq!(info.macronutrients.carbohydrates.starch, 0.0);
+        assert_close(info.micronutrients.minerals.sodium, 10.0);
+
+        assert_eq!(read("coca").unwrap().products.len(), 1);
+        assert_eq!(read("5449000000996").unwrap().products.len(), 1);
+        assert!(read("pepsi").unwrap().products.is_empty());
+    }
+
+    #[test]
+    fn csv_dump_reads_zinc_and_stops_at_the_limit() {
+        let dump = "\
+code\tproduct_name\tbrands\tenergy-kcal_100g\tzinc_100g\tvitamin-b9_100g\tfolates_100g
+1\tOat flakes\tMill\t372\t0.0031\t0.00005\t0.00009
+2\tOat bran\tMill\t246\t0.0039\t\t0.00005
+3\tRice\tMill\t360\t0.0011\t\t
+4\tOat drink\tMill\t46\t\t\t
+";
+        let dump = read_csv(dump.as_bytes(), &ProductQuery::new("oat", 2)).unwrap();
+        assert_eq!(dump.products.len(), 2);
+        assert!(dump.truncated);
+
+        let minerals = &dump.products[0].ingredient.nutritional_info[0]
+            .micronutrients
+            .minerals;
+        assert_close(minerals.zinc, 3.1);
+        // Vitamin B9 is preferred over the folates.
+        let vitamins = |index: usize| {
+            dump.products[index].ingredient.nutritional_info[0]
+                .micronutrients
+                .vitamins
+                .clone()
+        };
+        assert_close(vitamins(0).vitamin_b9, 50.0);
+        assert_close(vitamins(1).vitamin_b9, 50.0);
+    }
+}
@@ -6,10 +6,13 @@ pub struct PieChartSlice {
     pub tooltip: String,
 }
 
+const EMPTY_TOOLTIP: &str = "No data";
+
 fn generate_pie_chart(
     ui: &mut egui::Ui,
     size: Vec2,
-    mut slices: Vec<PieChartSlice>,
+    slices: Vec<PieChartSlice>,
+    ring_slices: Vec<PieChartSlice>,
 ) -> egui::Response {
     let desired_size = ui.spacing().interact_size.y * size;
     let (rect, mut response) = ui.allocate_exact_size(desired_size, egui::Sense::hover());
@@ -21,103 +24,221 @@ fn generate_pie_chart(
     if ui.is_rect_visible(rect) {
         let visuals = ui.style().noninteractive();
         let rect = rect.expand(visuals.expansion);
-        let radius = 0.5 * rect.height();
-
-        let mut polygon: Vec<Pos2> = Vec::new();
-
-        let target_segments: u32 = 32;
-        let mut actual_segments: u32 = 0;
-
-        for slice in &slices {
-            actual_segments += (target_segments as f32 * slice.fraction) as u32;
+        let outer_radius = 0.5 * rect.height();
+
+        if slices.iter().map(|slice| slice.fraction).sum::<f32>() <= 0.0 {
+            let stroke_width = 0.25 * outer_radius;
+            ui.painter().circle_stroke(
+                rect.center(),
+                outer_radius - 0.5 * stroke_width,
+                Stroke::new(stroke_width, visuals.weak_bg_fill),
+            );
+            if response.hovered() {
+                egui::show_tooltip(ui.ctx(), egui::Id::new("tooltip"), |ui| {
+                    ui.label(EMPTY_TOOLTIP);
+                });
+            }
+            return response;
         }
 
+        // With a ring around the pie, the pie shrinks to leave room for it.
+        let radius = if ring_slices.is_empty() {
+            outer_radius
+        } else {
+            0.65 * outer_radius
+        };
+
+        let pointer = ui
+            .input(|i| i.pointer.latest_pos())
+            .filter(|pos| pos.distance(rect.center()) <= radius)
+            .map(|pos| {
+                (pos - rect.center())
+                    .angle()
+                    .rem_euclid(std::f32::consts::TAU)
+            });
+
         let mut current_angle: f32 = 0.0;
         for slice in &slices {
-            let segment_count: u32 = (target_segments as f32 * slice.fraction) as u32;
-            let segment_angle: f32 =
-                (std::f32::consts::PI * 2.0 * slice.fraction) / segment_count as f32;
-
-            for seg in 0..=segment_count {
-                let x: f32 = rect.center().x + radius * current_angle.cos();
-                let y: f32 = rect.center().y + radius * current_angle.sin();
-
-                polygon.push(egui::pos2(x, y));
-
-                if seg < segment_count {
-                    current_angle += segment_angle;
-                }
+            let slice_angle = std::f32::consts::TAU * slice.fraction;
+            let arc = slice_arc(rect.center(), radius, current_angle, slice.fraction);
+
+            for triangle in arc.windows(2) {
+                ui.painter().add(egui::Shape::convex_polygon(
+                    vec![rect.center(), triangle[0], triangle[1]],
+                    slice.color,
+                    Stroke::NONE,
+                ));
             }
 
-            polygon.push(rect.center());
-
-            //Try Winding number algorithm!?
-
-            fn is_point_in_polygon(point: Pos2, polygon: &[Pos2]) -> bool {
-                fn ray_line_intersection(r0: Pos2, r1: Pos2, a: Pos2, b: Pos2) -> Option<Pos2> {
-                    let s1: Vec2 = r1 - r0;
-                    let s2: Vec2 = b - a;
-
-                    let s =
-                        (-s1.y * (r0.x - a.x) + s1.x * (r0.y - a.y)) / (-s2.x * s1.y + s1.x * s2.y);
-                    let t =
-                        (s2.x * (r0.y - a.y) - s2.y * (r0.x - a.x)) / (-s2.x * s1.y + s1.x * s2.y);
-
-                    if s >= 0.0 && s <= 1.0 && t >= 0.0 && t <= 1.0 {
-                        return Some(Pos2 {
-                            x: r0.x + (t * s1.x),
-                            y: r0.y + (t * s1.y),
-                        });
-                    }
-
-                    return None;
-                }
-
-                let mut intersection_count: u32 = 0;
-                for idx in 0..polygon.len() {
-                    if let Some(_) = ray_line_intersection(
-                        point,
-                        Pos2 {
-                            x: point.x + 100.0,
-                            y: point.y,
-                        },
-                        polygon[idx],
-                        polygon[if idx == polygon.len() - 1 { 0 } else { idx + 1 }],
-                    ) {
-                        intersection_count += 1;
-                    }
-                }
-                intersection_count % 2 == 1
-            }
-
-            let mut hovered: bool = false;
-            ui.input(|i| {
-                if let Some(pointer_pos) = i.pointer.latest_pos() {
-                    hovered = pointer_pos.distance(rect.center()) <= radius
-                        && is_point_in_polygon(pointer_pos, &polygon);
-                }
+            let hovered = pointer.is_some_and(|angle| {
+                slice.fraction > 0.0
+                    && angle >= current_angle
+                    && angle < current_angle + slice_angle
             });
-
-            let mut stroke: Stroke = Stroke::NONE;
             if hovered {
-                stroke = Stroke::new(2.0, Color32::WHITE);
+                let mut outline = arc;
+                outline.push(rect.center());
+                ui.painter().add(egui::Shape::closed_line(
+                    outline,
+                    Stroke::new(2.0, Color32::WHITE),
+                ));
                 egui::show_tooltip(ui.ctx(), egui::Id::new("tooltip"), |ui| {
                     ui.label(format!("{:.2}% {}", slice.fraction * 100.0, &slice.tooltip));
                 });
             }
 
+            current_angle += slice_angle;
+        }
+
+        draw_ring(
+            ui,
+            rect.center(),
+            0.7 * outer_radius,
+            outer_radius,
+            &ring_slices,
+        );
+    }
+
+    response
+}
+
+// Points along the outer edge of a slice covering `fraction` of the pie from `start_angle`.
+// Each pair of neighbouring points forms a triangle with the center, which keeps every
+// painted shape convex even when the slice itself is not.
+fn slice_arc(center: Pos2, radius: f32, start_angle: f32, fraction: f32) -> Vec<Pos2> {
+    let target_segments: u32 = 32;
+    let segment_count: u32 = ((target_segments as f32 * fraction) as u32).max(1);
+    let segment_angle: f32 = (std::f32::consts::TAU * fraction) / segment_count as f32;
+
+    (0..=segment_count)
+        .map(|seg| {
+            let angle = start_angle + segment_angle * seg as f32;
+            egui::pos2(
+                center.x + radius * angle.cos(),
+                center.y + radius * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+fn draw_ring(
+    ui: &mut egui::Ui,
+    center: Pos2,
+    inner_radius: f32,
+    outer_radius: f32,
+    slices: &[PieChartSlice],
+) {
+    let target_segments: u32 = 64;
+
+    let pointer = ui
+        .input(|i| i.pointer.latest_pos())
+        .filter(|pos| (inner_radius..=outer_radius).contains(&pos.distance(center)))
+        .map(|pos| (pos - center).angle().rem_euclid(std::f32::consts::TAU));
+
+    let point_at = |radius: f32, angle: f32| {
+        egui::pos2(
+            center.x + radius * angle.cos(),
+            center.y + radius * angle.sin(),
+        )
+    };
+
+    let mut current_angle: f32 = 0.0;
+    for slice in slices {
+        let slice_angle = std::f32::consts::TAU * slice.fraction;
+        let segment_count: u32 = ((target_segments as f32 * slice.fraction) as u32).max(1);
+        let segment_angle = slice_angle / segment_count as f32;
+
+        for seg in 0..segment_count {
+            let start = current_angle + segment_angle * seg as f32;
+            let end = start + segment_angle;
             ui.painter().add(egui::Shape::convex_polygon(
-                polygon.clone(),
+                vec![
+                    point_at(inner_radius, start),
+                    point_at(outer_radius, start),
+                    point_at(outer_radius, end),
+                    point_at(inner_radius, end),
+                ],
                 slice.color,
-                stroke,
+                Stroke::NONE,
             ));
-            polygon.clear();
         }
+
+        let hovered = pointer.is_some_and(|angle| {
+            slice.fraction > 0.0 && angle >= current_angle && angle < current_angle + slice_angle
+        });
+        if hovered {
+            let mut outline: Vec<Pos2> = (0..=segment_count)
+                .map(|seg| point_at(outer_radius, current_angle + segment_angle * seg as f32))
+                .collect();
+            outline.extend(
+                (0..=segment_count)
+                    .rev()
+                    .map(|seg| point_at(inner_radius, current_angle + segment_angle * seg as f32)),
+            );
+            ui.painter().add(egui::Shape::closed_line(
+                outline,
+                Stroke::new(2.0, Color32::WHITE),
+            ));
+            egui::show_tooltip(ui.ctx(), egui::Id::new("tooltip"), |ui| {
+                ui.label(format!("{:.2}% {}", slice.fraction * 100.0, &slice.tooltip));
+            });
+        }
+
+        current_angle += slice_angle;
     }
+}
 
-    response
+// Draws `slices` as a pie. Unless `ring_slices` is empty, they are drawn as a ring
+// around the pie, in the same order and direction, e.g. to break each slice down further.
+pub fn pie_chart<'a>(
+    size: Vec2,
+    slices: Vec<PieChartSlice>,
+    ring_slices: Vec<PieChartSlice>,
+) -> impl egui::Widget + 'a {
+    move |ui: &mut egui::Ui| generate_pie_chart(ui, size, slices, ring_slices)
 }
 
-pub fn pie_chart<'a>(size: Vec2, slices: Vec<PieChartSlice>) -> impl egui::Widget + 'a {
-    move |ui: &mut egui::Ui| generate_pie_chart(ui, size, slices)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle_area(a: Pos2, b: Pos2, c: Pos2) -> f32 {
+        0.5 * ((b - a).x * (c - a).y - (b - a).y * (c - a).x)
+    }
+
+    #[test]
+    fn slices_above_one_half_are_split_into_convex_triangles() {
+        let center = egui::pos2(10.0, 10.0);
+        let arc = slice_arc(center, 5.0, 0.5, 0.75);
+
+        let areas: Vec<f32> = arc
+            .windows(2)
+            .map(|pair| triangle_area(center, pair[0], pair[1]))
+            .collect();
+        // Every triangle turns the same way, so none of them folds over a neighbour.
+        assert!(areas.iter().all(|area| *area > 0.0));
+
+        let expected = 0.75 * std::f32::consts::PI * 5.0 * 5.0;
+        let area: f32 = areas.iter().sum();
+        assert!((area - expected).abs() < 0.01 * expected);
+    }
+
+    #[test]
+    fn slices_with_one_dominant_fraction_cover_the_pie_once() {
+        // As in the statistics pie when most of a day's energy comes from one macronutrient.
+        let center = egui::pos2(0.0, 0.0);
+        let mut start_angle = 0.0;
+        let mut area = 0.0;
+        for fraction in [0.8, 0.15, 0.05] {
+            let arc = slice_arc(center, 5.0, start_angle, fraction);
+            area += arc
+                .windows(2)
+                .map(|pair| triangle_area(center, pair[0], pair[1]))
+                .sum::<f32>();
+            start_angle += std::f32::consts::TAU * fraction;
+        }
+
+        let expected = std::f32::consts::PI * 5.0 * 5.0;
+        assert!((area - expected).abs() < 0.01 * expected);
+    }
 }
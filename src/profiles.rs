@@ -0,0 +1,182 @@
+use std::path::{Path, PathBuf};
+
+const APP_DIRECTORY: &str = "sophross";
+const PROFILE_DIRECTORY: &str = "profiles";
+const PROFILE_EXTENSION: &str = "db";
+const LEGACY_DATABASE: &str = "data.db";
+
+pub const DEFAULT_PROFILE: &str = "default";
+pub const DATABASE_ENV: &str = "SOPHROSS_DATABASE";
+pub const PROFILE_ENV: &str = "SOPHROSS_PROFILE";
+
+pub fn data_directory() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(APP_DIRECTORY)
+}
+
+fn profile_directory() -> PathBuf {
+    data_directory().join(PROFILE_DIRECTORY)
+}
+
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.trim().is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_')
+}
+
+pub fn profile_path(name: &str) -> PathBuf {
+    profile_directory().join(format!("{}.{}", name.trim(), PROFILE_EXTENSION))
+}
+
+// Returns the profile name if the path points at a database inside the profile directory.
+pub fn profile_name(path: &Path) -> Option<String> {
+    if path.parent()? != profile_directory() {
+        return None;
+    }
+    if path.extension()? != PROFILE_EXTENSION {
+        return None;
+    }
+    Some(path.file_stem()?.to_string_lossy().into_owned())
+}
+
+pub fn list_profiles() -> Vec<String> {
+    let mut profiles: Vec<String> = std::fs::read_dir(profile_directory())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| profile_name(&entry.path()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if !profiles.iter().any(|profile| profile == DEFAULT_PROFILE) {
+        profiles.push(DEFAULT_PROFILE.to_owned());
+    }
+    profiles.sort();
+    profiles
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DatabaseLocation {
+    File(PathBuf),
+    Profile(String),
+}
+
+impl DatabaseLocation {
+    pub fn path(&self) -> PathBuf {
+        match self {
+            DatabaseLocation::File(path) => path.clone(),
+            DatabaseLocation::Profile(name) => profile_path(name),
+        }
+    }
+}
+
+// Command-line arguments take precedence over environment variables.
+// Without either, a data.db in the working directory is still picked up
+// so that databases created by earlier builds keep working. An invalid profile
+// name is an error rather than falling back, which would open another database.
+pub fn resolve_location(
+    mut args: impl Iterator<Item = String>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<DatabaseLocation, String> {
+    let mut location: Option<DatabaseLocation> = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--database" | "-d" => {
+                if let Some(path) = args.next() {
+                    location = Some(DatabaseLocation::File(PathBuf::from(path)));
+                }
+            }
+            "--profile" | "-p" => {
+                if let Some(name) = args.next() {
+                    location = Some(DatabaseLocation::Profile(name));
+                }
+            }
+            _ => {
+                if let Some(path) = arg.strip_prefix("--database=") {
+                    location = Some(DatabaseLocation::File(PathBuf::from(path)));
+                } else if let Some(name) = arg.strip_prefix("--profile=") {
+                    location = Some(DatabaseLocation::Profile(name.to_owned()));
+                } else {
+                    log::warn!("Ignoring unknown argument '{}'", arg);
+                }
+            }
+        }
+    }
+
+    match location
+        .or_else(|| env(DATABASE_ENV).map(|path| DatabaseLocation::File(PathBuf::from(path))))
+        .or_else(|| env(PROFILE_ENV).map(DatabaseLocation::Profile))
+    {
+        Some(DatabaseLocation::Profile(name)) if !is_valid_profile_name(&name) => Err(format!(
+            "Invalid profile name '{}', only letters, digits, spaces, '-' and '_' are allowed",
+            name
+        )),
+        Some(location) => Ok(location),
+        None => Ok(if Path::new(LEGACY_DATABASE).exists() {
+            DatabaseLocation::File(PathBuf::from(LEGACY_DATABASE))
+        } else {
+            DatabaseLocation::Profile(DEFAULT_PROFILE.to_owned())
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<String>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn arguments_take_precedence_over_environment() {
+        let location = resolve_location(args(&["--profile", "alex"]), |key| {
+            (key == DATABASE_ENV).then(|| "/tmp/other.db".to_owned())
+        });
+        assert_eq!(location, Ok(DatabaseLocation::Profile("alex".to_owned())));
+
+        let location = resolve_location(args(&["--database=/tmp/food.db"]), |_| None);
+        assert_eq!(
+            location,
+            Ok(DatabaseLocation::File(PathBuf::from("/tmp/food.db")))
+        );
+    }
+
+    #[test]
+    fn environment_is_used_without_arguments() {
+        let location = resolve_location(args(&[]), |key| {
+            (key == PROFILE_ENV).then(|| "sam".to_owned())
+        });
+        assert_eq!(location, Ok(DatabaseLocation::Profile("sam".to_owned())));
+    }
+
+    #[test]
+    fn invalid_profile_names_are_not_replaced_by_the_default() {
+        assert!(resolve_location(args(&["--profile", "../escape"]), |_| None).is_err());
+        assert!(resolve_location(args(&["--profile=  "]), |_| None).is_err());
+        assert!(resolve_location(args(&[]), |key| {
+            (key == PROFILE_ENV).then(|| "a/b".to_owned())
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn profile_names_are_restricted() {
+        assert!(is_valid_profile_name("Sam Smith-2"));
+        assert!(!is_valid_profile_name("../escape"));
+        assert!(!is_valid_profile_name("   "));
+    }
+
+    #[test]
+    fn profile_paths_round_trip() {
+        assert_eq!(profile_name(&profile_path("alex")), Some("alex".to_owned()));
+        assert_eq!(profile_name(Path::new("/tmp/alex.db")), None);
+    }
+}
@@ -0,0 +1,367 @@
+// Reference daily intakes and upper limits of the vitamins and minerals, taken
+// from the US Dietary Reference Intakes for people who are neither pregnant nor
+// breastfeeding. The adequate intake stands in where there is no RDA.
+//
+// Upper limits that only apply to supplements and fortified foods (niacin,
+// folate, vitamin E and magnesium) are left out, ordinary food cannot exceed
+// them. For sodium the chronic disease risk reduction intake of 2300 mg serves
+// as the limit.
+
+use crate::ingredients::{micronutrient_fields, NutrientTotals};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sex {
+    Female,
+    Male,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgeGroup {
+    Years14To18,
+    Years19To30,
+    Years31To50,
+    Years51To70,
+    Years71Plus,
+}
+
+macro_rules! impl_sql_index {
+    ($name:ident) => {
+        impl $name {
+            pub fn from_uint(input: u32) -> Option<Self> {
+                Self::ALL.get(input as usize).copied()
+            }
+        }
+
+        impl ToSql for $name {
+            fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+                Ok(ToSqlOutput::Owned(Value::Integer(*self as i64)))
+            }
+        }
+
+        impl FromSql for $name {
+            fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+                let input = value.as_i64()?;
+                u32::try_from(input)
+                    .ok()
+                    .and_then($name::from_uint)
+                    .ok_or(FromSqlError::OutOfRange(input))
+            }
+        }
+    };
+}
+
+impl Sex {
+    pub const ALL: [Sex; 2] = [Self::Female, Self::Male];
+}
+
+impl AgeGroup {
+    pub const ALL: [AgeGroup; 5] = [
+        Self::Years14To18,
+        Self::Years19To30,
+        Self::Years31To50,
+        Self::Years51To70,
+        Self::Years71Plus,
+    ];
+}
+
+impl_sql_index!(Sex);
+impl_sql_index!(AgeGroup);
+
+impl std::fmt::Display for Sex {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Female => write!(f, "Female"),
+            Self::Male => write!(f, "Male"),
+        }
+    }
+}
+
+impl std::fmt::Display for AgeGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Years14To18 => write!(f, "14–18 years"),
+            Self::Years19To30 => write!(f, "19–30 years"),
+            Self::Years31To50 => write!(f, "31–50 years"),
+            Self::Years51To70 => write!(f, "51–70 years"),
+            Self::Years71Plus => write!(f, "71+ years"),
+        }
+    }
+}
+
+// Whom the reference intakes are chosen for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntakeProfile {
+    pub sex: Sex,
+    pub age: AgeGroup,
+}
+
+impl Default for IntakeProfile {
+    fn default() -> Self {
+        IntakeProfile {
+            sex: Sex::Female,
+            age: AgeGroup::Years31To50,
+        }
+    }
+}
+
+// Daily amounts in the units of `NUTRIENT_FIELDS`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReferenceIntake {
+    pub amount: f32,
+    pub upper_limit: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntakeStatus {
+    Deficit,
+    Adequate,
+    Excess,
+}
+
+impl ReferenceIntake {
+    pub fn status(&self, amount: f32) -> IntakeStatus {
+        if self.upper_limit.is_some_and(|limit| amount > limit) {
+            IntakeStatus::Excess
+        } else if amount < self.amount {
+            IntakeStatus::Deficit
+        } else {
+            IntakeStatus::Adequate
+        }
+    }
+}
+
+// The reference intake of the vitamin or mineral `name` for `profile`, `None`
+// for nutrients without one, like betaine.
+pub fn reference_intake(name: &str, profile: IntakeProfile) -> Option<ReferenceIntake> {
+    use AgeGroup::*;
+
+    let teen = profile.age == Years14To18;
+    let by_sex = |female: f32, male: f32| match profile.sex {
+        Sex::Female => female,
+        Sex::Male => male,
+    };
+    let intake = |amount: f32, upper_limit: Option<f32>| {
+        Some(ReferenceIntake {
+            amount,
+            upper_limit,
+        })
+    };
+
+    match name {
+        "vitamin_a" => intake(
+            by_sex(700.0, 900.0),
+            Some(if teen { 2800.0 } else { 3000.0 }),
+        ),
+        "vitamin_b1" => intake(
+            if teen {
+                by_sex(1.0, 1.2)
+            } else {
+                by_sex(1.1, 1.2)
+            },
+            None,
+        ),
+        "vitamin_b2" => intake(
+            if teen {
+                by_sex(1.0, 1.3)
+            } else {
+                by_sex(1.1, 1.3)
+            },
+            None,
+        ),
+        "vitamin_b3" => intake(by_sex(14.0, 16.0), None),
+        "vitamin_b5" => intake(5.0, None),
+        "vitamin_b6" => intake(
+            match profile.age {
+                Years14To18 => by_sex(1.2, 1.3),
+                Years19To30 | Years31To50 => 1.3,
+                Years51To70 | Years71Plus => by_sex(1.5, 1.7),
+            },
+            Some(if teen { 80.0 } else { 100.0 }),
+        ),
+        "vitamin_b9" => intake(400.0, None),
+        "vitamin_b12" => intake(2.4, None),
+        "vitamin_c" => intake(
+            if teen {
+                by_sex(65.0, 75.0)
+            } else {
+                by_sex(75.0, 90.0)
+            },
+            Some(if teen { 1800.0 } else { 2000.0 }),
+        ),
+        "vitamin_d" => intake(
+            if profile.age == Years71Plus {
+                20.0
+            } else {
+                15.0
+            },
+            Some(100.0),
+        ),
+        "vitamin_e" => intake(15.0, None),
+        "vitamin_k" => intake(if teen { 75.0 } else { by_sex(90.0, 120.0) }, None),
+        "choline" => intake(
+            if teen {
+                by_sex(400.0, 550.0)
+            } else {
+                by_sex(425.0, 550.0)
+            },
+            Some(if teen { 3000.0 } else { 3500.0 }),
+        ),
+        "calcium" => intake(
+            match profile.age {
+                Years14To18 => 1300.0,
+                Years19To30 | Years31To50 => 1000.0,
+                Years51To70 => by_sex(1200.0, 1000.0),
+                Years71Plus => 1200.0,
+            },
+            Some(match profile.age {
+                Years14To18 => 3000.0,
+                Years19To30 | Years31To50 => 2500.0,
+                Years51To70 | Years71Plus => 2000.0,
+            }),
+        ),
+        "copper" => intake(
+            if teen { 0.89 } else { 0.9 },
+            Some(if teen { 8.0 } else { 10.0 }),
+        ),
+        "iron" => intake(
+            match profile.age {
+                Years14To18 => by_sex(15.0, 11.0),
+                Years19To30 | Years31To50 => by_sex(18.0, 8.0),
+                Years51To70 | Years71Plus => 8.0,
+            },
+            Some(45.0),
+        ),
+        "magnesium" => intake(
+            match profile.age {
+                Years14To18 => by_sex(360.0, 410.0),
+                Years19To30 => by_sex(310.0, 400.0),
+                _ => by_sex(320.0, 420.0),
+            },
+            None,
+        ),
+        "manganese" => intake(
+            if teen {
+                by_sex(1.6, 2.2)
+            } else {
+                by_sex(1.8, 2.3)
+            },
+            Some(if teen { 9.0 } else { 11.0 }),
+        ),
+        "phosphorus" => intake(
+            if teen { 1250.0 } else { 700.0 },
+            Some(if profile.age == Years71Plus {
+                3000.0
+            } else {
+                4000.0
+            }),
+        ),
+        "potassium" => intake(
+            if teen {
+                by_sex(2300.0, 3000.0)
+            } else {
+                by_sex(2600.0, 3400.0)
+            },
+            None,
+        ),
+        "selenium" => intake(55.0, Some(400.0)),
+        "sodium" => intake(1500.0, Some(2300.0)),
+        "zinc" => intake(
+            if teen {
+                by_sex(9.0, 11.0)
+            } else {
+                by_sex(8.0, 11.0)
+            },
+            Some(if teen { 34.0 } else { 40.0 }),
+        ),
+        _ => None,
+    }
+}
+
+// One vitamin or mineral of a coverage report.
+pub struct CoverageRow {
+    pub nutrient: &'static str,
+    // The average amount per logged day.
+    pub amount: f32,
+    pub intake: Option<ReferenceIntake>,
+}
+
+impl CoverageRow {
+    pub fn percent(&self) -> Option<f32> {
+        self.intake
+            .filter(|intake| intake.amount > 0.0)
+            .map(|intake| self.amount / intake.amount * 100.0)
+    }
+
+    pub fn status(&self) -> Option<IntakeStatus> {
+        self.intake.map(|intake| intake.status(self.amount))
+    }
+}
+
+// Compares the vitamins and minerals of `totals`, logged over `days` days, with
+// the reference intakes of `profile`, one row per nutrient.
+pub fn coverage(totals: &NutrientTotals, days: usize, profile: IntakeProfile) -> Vec<CoverageRow> {
+    let days = days.max(1) as f32;
+    micronutrient_fields()
+        .map(|nutrient| CoverageRow {
+            nutrient,
+            amount: totals.nutrient(nutrient).unwrap_or(0.0) / days,
+            intake: reference_intake(nutrient, profile),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_intakes_depend_on_profile() {
+        let woman = IntakeProfile {
+            sex: Sex::Female,
+            age: AgeGroup::Years31To50,
+        };
+        let older_man = IntakeProfile {
+            sex: Sex::Male,
+            age: AgeGroup::Years71Plus,
+        };
+        assert_eq!(reference_intake("iron", woman).unwrap().amount, 18.0);
+        assert_eq!(reference_intake("iron", older_man).unwrap().amount, 8.0);
+        assert_eq!(
+            reference_intake("vitamin_d", older_man).unwrap().amount,
+            20.0
+        );
+        assert_eq!(
+            reference_intake("vitamin_b9", woman).unwrap().upper_limit,
+            None
+        );
+        assert_eq!(reference_intake("betaine", woman), None);
+        // Every vitamin and mineral but betaine has a reference intake.
+        assert_eq!(
+            micronutrient_fields()
+                .filter(|field| reference_intake(field, woman).is_some())
+                .count(),
+            23
+        );
+    }
+
+    #[test]
+    fn coverage_averages_days_and_flags_deficits_and_excess() {
+        let mut totals = NutrientTotals::default();
+        totals.micronutrients.minerals.sodium = 6000.0;
+        totals.micronutrients.vitamins.vitamin_c = 150.0;
+        totals.micronutrients.minerals.calcium = 2000.0;
+
+        let rows = coverage(&totals, 2, IntakeProfile::default());
+        assert_eq!(rows.len(), 24);
+        let row = |name: &str| rows.iter().find(|row| row.nutrient == name).unwrap();
+
+        assert_eq!(row("sodium").amount, 3000.0);
+        assert_eq!(row("sodium").status(), Some(IntakeStatus::Excess));
+        assert_eq!(row("vitamin_c").percent(), Some(100.0));
+        assert_eq!(row("vitamin_c").status(), Some(IntakeStatus::Adequate));
+        assert_eq!(row("calcium").status(), Some(IntakeStatus::Adequate));
+        assert_eq!(row("zinc").status(), Some(IntakeStatus::Deficit));
+        assert_eq!(row("betaine").status(), None);
+    }
+}
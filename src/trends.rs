@@ -0,0 +1,182 @@
+// Daily nutrient amounts over a range of days, as shown by the trend charts.
+//
+// Days without any log entries have no amount rather than zero, so a day that
+// was not logged does not pull the averages down like a day of fasting.
+
+use crate::goals::{goal_for_day, Goal, GoalNutrient};
+use crate::ingredients::NutrientTotals;
+use chrono::{Days, NaiveDate};
+use std::collections::HashMap;
+
+// Number of days each rolling average is taken over.
+pub const ROLLING_AVERAGE_DAYS: usize = 7;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrendRange {
+    Week,
+    Month,
+    Quarter,
+    Custom,
+}
+
+impl TrendRange {
+    pub const ALL: [TrendRange; 4] = [Self::Week, Self::Month, Self::Quarter, Self::Custom];
+
+    // The days of the range up to and including `end`, `None` for a custom range.
+    pub fn ending_at(&self, end: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        let days = match self {
+            Self::Week => 7,
+            Self::Month => 30,
+            Self::Quarter => 90,
+            Self::Custom => return None,
+        };
+        Some((end.checked_sub_days(Days::new(days - 1))?, end))
+    }
+}
+
+impl std::fmt::Display for TrendRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Week => write!(f, "7 days"),
+            Self::Month => write!(f, "30 days"),
+            Self::Quarter => write!(f, "90 days"),
+            Self::Custom => write!(f, "Custom"),
+        }
+    }
+}
+
+// All days from `start` to `end`, both inclusive.
+pub fn days(start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+    start.iter_days().take_while(|day| *day <= end).collect()
+}
+
+// The goal nutrient measured by a field of `NUTRIENT_FIELDS`, if there is one.
+pub fn field_goal_nutrient(field: &str) -> Option<GoalNutrient> {
+    GoalNutrient::ALL
+        .into_iter()
+        .find(|nutrient| nutrient.field() == Some(field))
+}
+
+// The amount of each of `days`, `None` for days without log entries.
+pub fn daily_amounts(
+    totals: &HashMap<NaiveDate, NutrientTotals>,
+    days: &[NaiveDate],
+    amount: impl Fn(&NutrientTotals) -> f32,
+) -> Vec<Option<f32>> {
+    days.iter()
+        .map(|day| totals.get(day).map(&amount))
+        .collect()
+}
+
+// The target of the goal for `nutrient` on each of `days`, `None` without one.
+pub fn daily_targets(
+    goals: &[Goal],
+    nutrient: GoalNutrient,
+    days: &[NaiveDate],
+) -> Vec<Option<f32>> {
+    days.iter()
+        .map(|day| goal_for_day(goals, nutrient, *day).map(|goal| goal.target))
+        .collect()
+}
+
+// The first day the rolling averages of the days from `start` on are taken
+// over, which has to be loaded along with them.
+pub fn rolling_average_start(start: NaiveDate) -> NaiveDate {
+    start
+        .checked_sub_days(Days::new(ROLLING_AVERAGE_DAYS as u64 - 1))
+        .unwrap_or(start)
+}
+
+// The mean of each amount and the `window - 1` amounts before it, leaving out
+// missing amounts. `None` where the whole window is missing.
+pub fn rolling_average(amounts: &[Option<f32>], window: usize) -> Vec<Option<f32>> {
+    (0..amounts.len())
+        .map(|index| {
+            let start = (index + 1).saturating_sub(window);
+            let present: Vec<f32> = amounts[start..=index].iter().flatten().copied().collect();
+            (!present.is_empty()).then(|| present.iter().sum::<f32>() / present.len() as f32)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_end_at_the_given_day_and_skip_missing_days() {
+        let end = NaiveDate::from_ymd_opt(2024, 3, 7).unwrap();
+        let (start, end) = TrendRange::Week.ending_at(end).unwrap();
+        assert_eq!(start, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(TrendRange::Custom.ending_at(end), None);
+        let days = days(start, end);
+        assert_eq!(days.len(), 7);
+
+        let mut totals = HashMap::new();
+        for (day, kilocalories) in [(days[0], 2000.0), (days[1], 1000.0), (days[5], 2400.0)] {
+            totals.insert(
+                day,
+                NutrientTotals {
+                    kilocalories,
+                    ..Default::default()
+                },
+            );
+        }
+        let amounts = daily_amounts(&totals, &days, |totals| totals.kilocalories);
+        assert_eq!(
+            amounts,
+            vec![
+                Some(2000.0),
+                Some(1000.0),
+                None,
+                None,
+                None,
+                Some(2400.0),
+                None
+            ]
+        );
+        assert_eq!(
+            rolling_average(&amounts, 3),
+            vec![
+                Some(2000.0),
+                Some(1500.0),
+                Some(1500.0),
+                Some(1000.0),
+                None,
+                Some(2400.0),
+                Some(2400.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn first_averages_include_the_days_before_the_range() {
+        let start = NaiveDate::from_ymd_opt(2024, 3, 7).unwrap();
+        let history_start = rolling_average_start(start);
+        assert_eq!(history_start, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+
+        let history = days(history_start, NaiveDate::from_ymd_opt(2024, 3, 8).unwrap());
+        let mut totals = HashMap::new();
+        for (day, kilocalories) in [(history[0], 3000.0), (history[6], 2000.0)] {
+            totals.insert(
+                day,
+                NutrientTotals {
+                    kilocalories,
+                    ..Default::default()
+                },
+            );
+        }
+        let amounts = daily_amounts(&totals, &history, |totals| totals.kilocalories);
+        let leading = ROLLING_AVERAGE_DAYS - 1;
+        assert_eq!(
+            rolling_average(&amounts, ROLLING_AVERAGE_DAYS)[leading..],
+            [Some(2500.0), Some(2000.0)]
+        );
+    }
+
+    #[test]
+    fn fields_are_matched_to_goals() {
+        assert_eq!(field_goal_nutrient("iron"), Some(GoalNutrient::Iron));
+        assert_eq!(field_goal_nutrient("copper"), None);
+    }
+}
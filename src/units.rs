@@ -0,0 +1,245 @@
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+
+const MILLILITERS_PER_TEASPOON: f32 = 4.928_922;
+const MILLILITERS_PER_TABLESPOON: f32 = 14.786_765;
+const MILLILITERS_PER_CUP: f32 = 236.588_24;
+const MILLILITERS_PER_FLUID_OUNCE: f32 = 29.573_53;
+const GRAMS_PER_OUNCE: f32 = 28.349_523;
+const GRAMS_PER_POUND: f32 = 453.592_37;
+
+// New units must be appended, the discriminant is what gets stored in the database.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Unit {
+    #[default]
+    Grams,
+    Teaspoons,
+    Tablespoons,
+    Pieces,
+    Cups,
+    Milliliters,
+    Liters,
+    Ounces,
+    Pounds,
+    FluidOunces,
+    // Multiples of the default amount of a nutritional info, or servings of a recipe.
+    Servings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dimension {
+    Mass,
+    Volume,
+    Count,
+    Servings,
+}
+
+impl ToSql for Unit {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Owned(Value::Integer(*self as i64)))
+    }
+}
+
+impl FromSql for Unit {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let input = value.as_i64()?;
+        u32::try_from(input)
+            .ok()
+            .and_then(Unit::from_uint)
+            .ok_or(FromSqlError::OutOfRange(input))
+    }
+}
+
+impl Unit {
+    pub const ALL: [Unit; 11] = [
+        Self::Grams,
+        Self::Teaspoons,
+        Self::Tablespoons,
+        Self::Pieces,
+        Self::Cups,
+        Self::Milliliters,
+        Self::Liters,
+        Self::Ounces,
+        Self::Pounds,
+        Self::FluidOunces,
+        Self::Servings,
+    ];
+
+    // Units a nutritional info can be given in. Servings are only meaningful
+    // relative to a nutritional info, so they are left out.
+    pub const MEASURES: [Unit; 10] = [
+        Self::Grams,
+        Self::Ounces,
+        Self::Pounds,
+        Self::Milliliters,
+        Self::Liters,
+        Self::Teaspoons,
+        Self::Tablespoons,
+        Self::Cups,
+        Self::FluidOunces,
+        Self::Pieces,
+    ];
+
+    pub fn from_uint(input: u32) -> Option<Self> {
+        Self::ALL.get(input as usize).copied()
+    }
+
+    // Parses the symbol `Display` writes, ignoring case.
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        let symbol = symbol.trim();
+        Self::ALL
+            .into_iter()
+            .find(|unit| unit.to_string().eq_ignore_ascii_case(symbol))
+    }
+
+    pub fn dimension(&self) -> Dimension {
+        match self {
+            Self::Grams | Self::Ounces | Self::Pounds => Dimension::Mass,
+            Self::Teaspoons
+            | Self::Tablespoons
+            | Self::Cups
+            | Self::Milliliters
+            | Self::Liters
+            | Self::FluidOunces => Dimension::Volume,
+            Self::Pieces => Dimension::Count,
+            Self::Servings => Dimension::Servings,
+        }
+    }
+
+    // The size of one unit in grams, milliliters, pieces or servings, depending on its dimension.
+    fn base_amount(&self) -> f32 {
+        match self {
+            Self::Grams | Self::Milliliters | Self::Pieces | Self::Servings => 1.0,
+            Self::Teaspoons => MILLILITERS_PER_TEASPOON,
+            Self::Tablespoons => MILLILITERS_PER_TABLESPOON,
+            Self::Cups => MILLILITERS_PER_CUP,
+            Self::Liters => 1000.0,
+            Self::Ounces => GRAMS_PER_OUNCE,
+            Self::Pounds => GRAMS_PER_POUND,
+            Self::FluidOunces => MILLILITERS_PER_FLUID_OUNCE,
+        }
+    }
+}
+
+impl std::fmt::Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Unit::Grams => write!(f, "g"),
+            Unit::Teaspoons => write!(f, "tsp"),
+            Unit::Tablespoons => write!(f, "Tbsp"),
+            Unit::Pieces => write!(f, "pc"),
+            Unit::Cups => write!(f, "cup"),
+            Unit::Milliliters => write!(f, "ml"),
+            Unit::Liters => write!(f, "l"),
+            Unit::Ounces => write!(f, "oz"),
+            Unit::Pounds => write!(f, "lb"),
+            Unit::FluidOunces => write!(f, "fl oz"),
+            Unit::Servings => write!(f, "serving"),
+        }
+    }
+}
+
+// What is known about an ingredient to convert between dimensions.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Conversions {
+    // Grams per milliliter.
+    pub density: Option<f32>,
+    // Grams per piece.
+    pub piece_weight: Option<f32>,
+}
+
+impl Conversions {
+    fn grams_in(&self, amount: f32, unit: Unit) -> Option<f32> {
+        match unit.dimension() {
+            Dimension::Mass => Some(amount * unit.base_amount()),
+            Dimension::Volume => self
+                .density
+                .map(|density| amount * unit.base_amount() * density),
+            Dimension::Count => self.piece_weight.map(|piece_weight| amount * piece_weight),
+            Dimension::Servings => None,
+        }
+    }
+
+    fn amount_of_grams(&self, grams: f32, unit: Unit) -> Option<f32> {
+        match unit.dimension() {
+            Dimension::Mass => Some(grams / unit.base_amount()),
+            Dimension::Volume => self
+                .density
+                .filter(|density| *density > 0.0)
+                .map(|density| grams / density / unit.base_amount()),
+            Dimension::Count => self
+                .piece_weight
+                .filter(|piece_weight| *piece_weight > 0.0)
+                .map(|piece_weight| grams / piece_weight),
+            Dimension::Servings => None,
+        }
+    }
+
+    // Converts `amount` from one unit to another. Conversions across dimensions
+    // go through grams and need the density or piece weight.
+    pub fn convert(&self, amount: f32, from: Unit, to: Unit) -> Option<f32> {
+        if from == to {
+            Some(amount)
+        } else if from.dimension() == to.dimension() && from.dimension() != Dimension::Servings {
+            Some(amount * from.base_amount() / to.base_amount())
+        } else {
+            self.amount_of_grams(self.grams_in(amount, from)?, to)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Option<f32>, expected: f32) {
+        let actual = actual.unwrap();
+        assert!(
+            (actual - expected).abs() < 1e-3 * expected.abs().max(1.0),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn converts_within_a_dimension() {
+        let conversions = Conversions::default();
+        assert_close(conversions.convert(1.0, Unit::Pounds, Unit::Grams), 453.592);
+        assert_close(conversions.convert(16.0, Unit::Ounces, Unit::Pounds), 1.0);
+        assert_close(
+            conversions.convert(3.0, Unit::Teaspoons, Unit::Tablespoons),
+            1.0,
+        );
+        assert_close(conversions.convert(1.0, Unit::Cups, Unit::FluidOunces), 8.0);
+        assert_close(
+            conversions.convert(0.25, Unit::Liters, Unit::Milliliters),
+            250.0,
+        );
+    }
+
+    #[test]
+    fn converts_across_dimensions_with_density_and_piece_weight() {
+        let conversions = Conversions {
+            density: Some(1.03),
+            piece_weight: Some(50.0),
+        };
+        assert_close(
+            conversions.convert(200.0, Unit::Milliliters, Unit::Grams),
+            206.0,
+        );
+        assert_close(conversions.convert(103.0, Unit::Grams, Unit::Liters), 0.1);
+        assert_close(conversions.convert(2.0, Unit::Pieces, Unit::Ounces), 3.527);
+        assert_close(
+            conversions.convert(1.0, Unit::Pieces, Unit::Milliliters),
+            48.544,
+        );
+    }
+
+    #[test]
+    fn refuses_conversions_without_data() {
+        let conversions = Conversions::default();
+        assert_eq!(conversions.convert(1.0, Unit::Cups, Unit::Grams), None);
+        assert_eq!(conversions.convert(1.0, Unit::Pieces, Unit::Grams), None);
+        assert_eq!(conversions.convert(1.0, Unit::Servings, Unit::Grams), None);
+    }
+}